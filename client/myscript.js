@@ -1,3 +1,14 @@
+// htmx leaves error responses out of the page, but a 422 can carry a message to show next to
+// the form and a 409 explains that someone else changed the todo being edited. Those say where
+// they go with HX-Retarget, other errors are plain text that mustn't replace anything.
+document.addEventListener("htmx:beforeSwap", function (evt) {
+  var status = evt.detail.xhr.status;
+  if ((status === 422 || status === 409) && evt.detail.xhr.getResponseHeader("HX-Retarget")) {
+    evt.detail.shouldSwap = true;
+    evt.detail.isError = false;
+  }
+})
+
 htmx.onLoad(function () {
   // reset the form after creating a new todo
   document.body.addEventListener("todoFormReset", function (evt) {
@@ -6,19 +17,164 @@ htmx.onLoad(function () {
 
   // Sorting of todos
   var sortable = document.getElementById("todos");
+  if (!sortable) {
+    // not a list page
+    return;
+  }
+  // sorted by anything but the list's own order, dragging would be undone on the next render
+  var locked = sortable.dataset.drag === "off";
   var sortableInstance = new Sortable(sortable, {
     animation: 150,
+    disabled: locked,
     ghostClass: 'blue-background-class',
+    // the infinite scroll sentinel isn't a todo
+    filter: '.load-more',
+    // todos can be dragged onto the other lists in the sidebar
+    group: { name: 'todos', pull: true, put: false },
 
     // Disable sorting on the `end` event
     onEnd: function (evt) {
       this.option("disabled", true);
-      htmx.trigger("#todos", "drop-end")
+      if (evt.to === evt.from) {
+        htmx.trigger("#todos", "drop-end")
+        return;
+      }
+      // dropped onto another list: move it there and reorder what's left
+      var moved = evt.item.querySelector("[name=order]").value;
+      evt.item.remove();
+      htmx.ajax("POST", sortable.getAttribute("hx-post"), {
+        source: "#todos",
+        target: "#todos",
+        values: { moved: moved, target_list_id: evt.to.dataset.listId }
+      });
+    }
+  });
+
+  // The other lists in the sidebar accept todos but don't hold on to them
+  document.querySelectorAll("#lists .list-drop").forEach(function (drop) {
+    if (!Sortable.get(drop)) {
+      new Sortable(drop, { group: { name: 'todos', pull: false, put: true } });
     }
   });
 
   // Re-enable sorting on the `htmx:afterSwap` event
   sortable.addEventListener("htmx:afterSwap", function () {
-    sortableInstance.option("disabled", false);
+    sortableInstance.option("disabled", locked);
+  });
+})
+
+// On the calendar, drop a todo on another day to make it due then
+htmx.onLoad(function () {
+  document.querySelectorAll("#calendar .calendar-day").forEach(function (day) {
+    if (Sortable.get(day)) {
+      return;
+    }
+    new Sortable(day, {
+      group: "calendar",
+      sort: false,
+      animation: 150,
+      onEnd: function (evt) {
+        if (evt.to === evt.from) {
+          return;
+        }
+        var calendar = document.getElementById("calendar");
+        htmx.ajax("POST", calendar.dataset.rescheduleUrl, {
+          source: "#calendar",
+          target: "#calendar",
+          swap: "outerHTML",
+          values: { todo_id: evt.item.dataset.todoId, due_at: evt.to.dataset.date }
+        });
+      }
+    });
   });
 })
+
+// On the board, drag cards within a column or onto another list's column
+htmx.onLoad(function () {
+  document.querySelectorAll("#board .board-cards").forEach(function (column) {
+    if (Sortable.get(column)) {
+      return;
+    }
+    new Sortable(column, {
+      group: "board",
+      animation: 150,
+      onEnd: function (evt) {
+        var order = Array.from(evt.to.children).map(function (card) {
+          return card.dataset.todoId;
+        });
+        htmx.ajax("POST", "/board/move", {
+          source: "#board",
+          target: "#board",
+          swap: "outerHTML",
+          values: { todo_id: evt.item.dataset.todoId, list_id: evt.to.dataset.listId, order: order }
+        });
+      }
+    });
+  });
+})
+
+// notifications pushed over /events go away on their own after a while, or when dismissed
+var NOTIFICATION_SECONDS = 10;
+htmx.onLoad(function (elt) {
+  if (!elt.matches || !elt.matches(".notification")) {
+    return;
+  }
+  setTimeout(function () {
+    elt.remove();
+  }, NOTIFICATION_SECONDS * 1000);
+})
+document.addEventListener("click", function (evt) {
+  var dismiss = evt.target.closest && evt.target.closest(".notification .dismiss");
+  if (dismiss) {
+    dismiss.closest(".notification").remove();
+  }
+})
+
+// hide the undo toast once it's too late to undo
+document.addEventListener("undoAvailable", function (evt) {
+  var token = evt.detail.token;
+  setTimeout(function () {
+    var toast = document.querySelector('#toast [data-undo-token="' + token + '"]');
+    if (toast) {
+      toast.remove();
+    }
+  }, evt.detail.seconds * 1000);
+})
+
+// shift-click a todo's selection box to select (or unselect) everything from the last one clicked
+var lastSelected = null;
+document.addEventListener("click", function (evt) {
+  var box = evt.target;
+  if (!box.matches || !box.matches("input.select-todo")) {
+    return;
+  }
+  if (evt.shiftKey && lastSelected && document.body.contains(lastSelected)) {
+    var boxes = Array.from(document.querySelectorAll("input.select-todo"));
+    var start = boxes.indexOf(lastSelected);
+    var end = boxes.indexOf(box);
+    boxes.slice(Math.min(start, end), Math.max(start, end) + 1).forEach(function (b) {
+      b.checked = box.checked;
+    });
+  }
+  lastSelected = box;
+})
+
+// confirmation dialogs (see confirm_dialog.html) close when cancelled, on escape, and once answered
+function closeModal() {
+  document.getElementById("modal").innerHTML = "";
+}
+document.addEventListener("click", function (evt) {
+  if (evt.target.hasAttribute && evt.target.hasAttribute("data-close-modal")) {
+    closeModal();
+  }
+})
+document.addEventListener("keydown", function (evt) {
+  if (evt.key === "Escape" && document.querySelector("#modal .modal")) {
+    closeModal();
+  }
+})
+document.addEventListener("htmx:afterRequest", function (evt) {
+  if (evt.detail.elt.closest && evt.detail.elt.closest("#modal")) {
+    closeModal();
+  }
+})
@@ -0,0 +1,61 @@
+// The part of the htmx sse extension the pages use: an element with sse-connect opens an
+// EventSource to that url, and the elements inside it with sse-swap swap in the events of that
+// name, the way their hx-swap says. The EventSource reconnects by itself when the connection
+// drops, and is closed when htmx removes the element.
+(function () {
+  var api;
+
+  htmx.defineExtension("sse", {
+    init: function (apiRef) {
+      api = apiRef;
+    },
+
+    onEvent: function (name, evt) {
+      var elt = evt.target || evt.detail.elt;
+      switch (name) {
+        case "htmx:beforeCleanupElement":
+          var source = api.getInternalData(elt).sseEventSource;
+          if (source) {
+            source.close();
+          }
+          return;
+
+        case "htmx:afterProcessNode":
+          connect(elt);
+          return;
+      }
+    }
+  });
+
+  function connect(elt) {
+    var url = api.getAttributeValue(elt, "sse-connect");
+    var data = api.getInternalData(elt);
+    if (!url || data.sseEventSource) {
+      return;
+    }
+    var source = new EventSource(url);
+    data.sseEventSource = source;
+    source.onerror = function (err) {
+      api.triggerErrorEvent(elt, "htmx:sseError", { error: err, source: source });
+    };
+
+    var swaps = Array.prototype.slice.call(elt.querySelectorAll("[sse-swap]"));
+    if (elt.hasAttribute("sse-swap")) {
+      swaps.unshift(elt);
+    }
+    swaps.forEach(function (target) {
+      var eventName = api.getAttributeValue(target, "sse-swap");
+      source.addEventListener(eventName, function (event) {
+        swap(target, event);
+      });
+    });
+  }
+
+  function swap(target, event) {
+    var swapSpec = api.getSwapSpecification(target);
+    var settleInfo = api.makeSettleInfo(target);
+    api.selectAndSwap(swapSpec.swapStyle, target, target, event.data, settleInfo);
+    api.settleImmediately(settleInfo.tasks);
+    api.triggerEvent(target, "htmx:sseMessage", event);
+  }
+})();
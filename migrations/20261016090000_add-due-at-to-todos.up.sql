@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE todos
+ADD COLUMN due_at date;
@@ -0,0 +1,3 @@
+ALTER TABLE todos
+DROP COLUMN priority;
+DROP TYPE todo_priority;
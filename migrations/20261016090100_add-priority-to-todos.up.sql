@@ -0,0 +1,4 @@
+-- Add migration script here
+CREATE TYPE todo_priority AS ENUM ('low', 'normal', 'high', 'urgent');
+ALTER TABLE todos
+ADD COLUMN priority todo_priority NOT NULL DEFAULT 'normal';
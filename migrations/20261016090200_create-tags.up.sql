@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE tags (
+  id serial PRIMARY KEY,
+  name text not null unique
+);
+CREATE TABLE todo_tags (
+  todo_id integer not null references todos (id) on delete cascade,
+  tag_id integer not null references tags (id) on delete cascade,
+  PRIMARY KEY (todo_id, tag_id)
+);
@@ -0,0 +1,12 @@
+-- Add migration script here
+CREATE TABLE lists (
+  id serial PRIMARY KEY,
+  name text not null default ''
+);
+-- existing todos go into a default list
+INSERT INTO lists (name) VALUES ('Todos');
+ALTER TABLE todos
+ADD COLUMN list_id integer references lists (id) on delete cascade;
+UPDATE todos set list_id=(select min(id) from lists);
+ALTER TABLE todos ALTER COLUMN list_id SET NOT NULL;
+CREATE INDEX todos_list_id_idx ON todos (list_id);
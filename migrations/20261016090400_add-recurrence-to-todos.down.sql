@@ -0,0 +1,6 @@
+DROP TRIGGER todos_recurrence_day ON todos;
+DROP FUNCTION todos_forget_recurrence_day();
+ALTER TABLE todos
+DROP COLUMN recurrence_day,
+DROP COLUMN recurrence;
+DROP TYPE todo_recurrence;
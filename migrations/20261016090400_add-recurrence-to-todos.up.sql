@@ -0,0 +1,22 @@
+-- Add migration script here
+CREATE TYPE todo_recurrence AS ENUM ('daily', 'weekly', 'monthly');
+ALTER TABLE todos
+ADD COLUMN recurrence todo_recurrence;
+
+-- the day of the month a monthly todo was first due on, carried over to each next occurrence so
+-- that one due on the 31st goes back to the 31st after falling back to the 28th in February
+ALTER TABLE todos ADD COLUMN recurrence_day smallint CHECK (recurrence_day BETWEEN 1 AND 31);
+
+-- moving the due date picks a new day of the month
+CREATE FUNCTION todos_forget_recurrence_day() RETURNS trigger AS $$
+BEGIN
+  IF NEW.due_at IS DISTINCT FROM OLD.due_at THEN
+    NEW.recurrence_day = NULL;
+  END IF;
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER todos_recurrence_day
+  BEFORE UPDATE OF due_at ON todos
+  FOR EACH ROW EXECUTE FUNCTION todos_forget_recurrence_day();
@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE todos
+ADD COLUMN notes text not null default '';
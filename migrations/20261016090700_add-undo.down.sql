@@ -0,0 +1,5 @@
+DROP TABLE undo_actions;
+DROP TYPE undo_action;
+DELETE FROM todos WHERE deleted_at IS NOT NULL;
+ALTER TABLE todos
+DROP COLUMN deleted_at;
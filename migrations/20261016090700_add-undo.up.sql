@@ -0,0 +1,12 @@
+-- Add migration script here
+-- deleted todos are kept around for a little while so that they can be brought back
+ALTER TABLE todos
+ADD COLUMN deleted_at timestamptz;
+CREATE TYPE undo_action AS ENUM ('delete', 'archive');
+CREATE TABLE undo_actions (
+  token text PRIMARY KEY,
+  list_id integer not null references lists (id) on delete cascade,
+  action undo_action not null,
+  todo_ids integer[] not null,
+  created_at timestamptz not null default now()
+);
@@ -0,0 +1,3 @@
+ALTER TABLE todos
+DROP COLUMN remind_days_before,
+DROP COLUMN reminded_at;
@@ -0,0 +1,5 @@
+-- Add migration script here
+-- remind_days_before overrides REMINDER_WINDOW_DAYS for a single todo
+ALTER TABLE todos
+ADD COLUMN remind_days_before integer CHECK (remind_days_before >= 0),
+ADD COLUMN reminded_at timestamptz;
@@ -0,0 +1,5 @@
+UPDATE todos SET position = ranked.rn
+FROM (SELECT id, row_number() OVER (PARTITION BY list_id ORDER BY position) AS rn FROM todos) AS ranked
+WHERE todos.id = ranked.id;
+ALTER TABLE todos
+ALTER COLUMN position TYPE integer;
@@ -0,0 +1,4 @@
+-- Add migration script here
+-- reordering slots a todo in between its new neighbours instead of renumbering the whole list
+ALTER TABLE todos
+ALTER COLUMN position TYPE double precision;
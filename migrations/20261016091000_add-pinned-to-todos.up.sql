@@ -0,0 +1,4 @@
+-- Add migration script here
+-- pinned todos are shown above all the others
+ALTER TABLE todos
+ADD COLUMN pinned boolean NOT NULL DEFAULT false;
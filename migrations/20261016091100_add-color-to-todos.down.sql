@@ -0,0 +1,3 @@
+ALTER TABLE todos
+DROP COLUMN color;
+DROP TYPE todo_color;
@@ -0,0 +1,4 @@
+-- Add migration script here
+CREATE TYPE todo_color AS ENUM ('red', 'orange', 'yellow', 'green', 'blue', 'purple');
+ALTER TABLE todos
+ADD COLUMN color todo_color;
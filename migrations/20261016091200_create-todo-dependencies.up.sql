@@ -0,0 +1,9 @@
+-- Add migration script here
+-- a todo can't be completed while any of its blockers are still open
+CREATE TABLE todo_dependencies (
+  todo_id integer NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+  blocker_id integer NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+  PRIMARY KEY (todo_id, blocker_id),
+  CHECK (todo_id <> blocker_id)
+);
+CREATE INDEX todo_dependencies_blocker_id ON todo_dependencies (blocker_id);
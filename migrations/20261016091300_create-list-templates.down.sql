@@ -0,0 +1,2 @@
+DROP TABLE list_template_items;
+DROP TABLE list_templates;
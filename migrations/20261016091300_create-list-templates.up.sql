@@ -0,0 +1,16 @@
+-- Add migration script here
+-- a named set of todos that can be added to any list in one go
+CREATE TABLE list_templates (
+  id serial PRIMARY KEY,
+  name text not null
+);
+CREATE TABLE list_template_items (
+  id serial PRIMARY KEY,
+  template_id integer not null references list_templates (id) on delete cascade,
+  description text not null,
+  notes text not null default '',
+  priority todo_priority not null default 'normal',
+  -- 1 is the top of the list
+  position integer not null
+);
+CREATE INDEX list_template_items_template_id_idx ON list_template_items (template_id);
@@ -0,0 +1,4 @@
+DROP TRIGGER todos_updated_at ON todos;
+DROP FUNCTION todos_set_updated_at();
+ALTER TABLE todos DROP COLUMN updated_at;
+ALTER TABLE todos DROP COLUMN created_at;
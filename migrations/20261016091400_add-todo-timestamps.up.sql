@@ -0,0 +1,18 @@
+-- Add migration script here
+-- existing todos get the time of the migration, there is no better guess
+ALTER TABLE todos ADD COLUMN created_at timestamptz not null default now();
+ALTER TABLE todos ADD COLUMN updated_at timestamptz not null default now();
+
+-- reordering doesn't count as an update, so only the columns a user edits are listed
+CREATE FUNCTION todos_set_updated_at() RETURNS trigger AS $$
+BEGIN
+  NEW.updated_at = now();
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER todos_updated_at
+  BEFORE UPDATE OF done, description, notes, due_at, priority, recurrence, remind_days_before,
+                   pinned, color, list_id, archived_at, deleted_at
+  ON todos
+  FOR EACH ROW EXECUTE FUNCTION todos_set_updated_at();
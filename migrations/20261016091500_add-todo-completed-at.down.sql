@@ -0,0 +1,3 @@
+DROP TRIGGER todos_completed_at ON todos;
+DROP FUNCTION todos_set_completed_at();
+ALTER TABLE todos DROP COLUMN completed_at;
@@ -0,0 +1,19 @@
+-- Add migration script here
+-- when the todo was last checked, for the activity feed
+ALTER TABLE todos ADD COLUMN completed_at timestamptz;
+UPDATE todos SET completed_at = updated_at WHERE done;
+
+CREATE FUNCTION todos_set_completed_at() RETURNS trigger AS $$
+BEGIN
+  IF NEW.done AND NOT OLD.done THEN
+    NEW.completed_at = now();
+  ELSIF NOT NEW.done THEN
+    NEW.completed_at = NULL;
+  END IF;
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER todos_completed_at
+  BEFORE UPDATE OF done ON todos
+  FOR EACH ROW EXECUTE FUNCTION todos_set_completed_at();
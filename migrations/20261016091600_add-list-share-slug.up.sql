@@ -0,0 +1,3 @@
+-- Add migration script here
+-- anyone with /share/<share_slug> can read the list, null when it isn't shared
+ALTER TABLE lists ADD COLUMN share_slug text unique;
@@ -0,0 +1,11 @@
+-- Add migration script here
+-- earlier descriptions and notes of a todo, saved whenever an edit changes them
+CREATE TABLE todo_revisions (
+  id serial PRIMARY KEY,
+  todo_id integer not null references todos (id) on delete cascade,
+  description text not null,
+  notes text not null,
+  -- when this version was replaced
+  created_at timestamptz not null default now()
+);
+CREATE INDEX todo_revisions_todo_id_idx ON todo_revisions (todo_id);
@@ -0,0 +1,9 @@
+DROP TRIGGER todos_updated_at ON todos;
+
+CREATE TRIGGER todos_updated_at
+  BEFORE UPDATE OF done, description, notes, due_at, priority, recurrence, remind_days_before,
+                   pinned, color, list_id, archived_at, deleted_at
+  ON todos
+  FOR EACH ROW EXECUTE FUNCTION todos_set_updated_at();
+
+ALTER TABLE todos DROP COLUMN estimate_minutes;
@@ -0,0 +1,12 @@
+-- Add migration script here
+-- how long the todo is expected to take
+ALTER TABLE todos ADD COLUMN estimate_minutes integer CHECK (estimate_minutes > 0);
+
+-- changing the estimate is an edit like any other
+DROP TRIGGER todos_updated_at ON todos;
+
+CREATE TRIGGER todos_updated_at
+  BEFORE UPDATE OF done, description, notes, due_at, priority, recurrence, remind_days_before,
+                   estimate_minutes, pinned, color, list_id, archived_at, deleted_at
+  ON todos
+  FOR EACH ROW EXECUTE FUNCTION todos_set_updated_at();
@@ -0,0 +1,3 @@
+DROP TRIGGER todos_completion_streaks ON todos;
+DROP FUNCTION completion_streaks_forget();
+DROP TABLE completion_streaks;
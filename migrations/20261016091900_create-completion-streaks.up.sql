@@ -0,0 +1,26 @@
+-- Add migration script here
+-- the streak of days with at least one completed todo that ended the day before `day`,
+-- worked out on the first visit of each day
+CREATE TABLE completion_streaks (
+  day date PRIMARY KEY,
+  days integer not null
+);
+
+-- unchecking, deleting or restoring a todo completed on an earlier day, or importing one,
+-- changes the streaks worked out after that day
+CREATE FUNCTION completion_streaks_forget() RETURNS trigger AS $$
+BEGIN
+  IF TG_OP <> 'INSERT' THEN
+    DELETE FROM completion_streaks WHERE day > (OLD.completed_at AT TIME ZONE 'utc')::date;
+  END IF;
+  IF TG_OP <> 'DELETE' THEN
+    DELETE FROM completion_streaks WHERE day > (NEW.completed_at AT TIME ZONE 'utc')::date;
+  END IF;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER todos_completion_streaks
+  AFTER INSERT OR DELETE OR UPDATE OF done, completed_at, deleted_at
+  ON todos
+  FOR EACH ROW EXECUTE FUNCTION completion_streaks_forget();
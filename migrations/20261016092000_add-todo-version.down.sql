@@ -0,0 +1,3 @@
+DROP TRIGGER todos_version ON todos;
+DROP FUNCTION todos_bump_version();
+ALTER TABLE todos DROP COLUMN version;
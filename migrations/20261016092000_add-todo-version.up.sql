@@ -0,0 +1,16 @@
+-- Add migration script here
+-- bumped whenever a field of the edit form changes, so that saving a form opened before
+-- someone else's change can be refused rather than overwrite it
+ALTER TABLE todos ADD COLUMN version integer not null default 1;
+
+CREATE FUNCTION todos_bump_version() RETURNS trigger AS $$
+BEGIN
+  NEW.version = OLD.version + 1;
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER todos_version
+  BEFORE UPDATE OF description, notes, due_at, priority, recurrence, remind_days_before, estimate_minutes
+  ON todos
+  FOR EACH ROW EXECUTE FUNCTION todos_bump_version();
@@ -0,0 +1,9 @@
+-- Add migration script here
+-- keys of create requests that were already handled, so retries don't add the todo twice
+-- a key only stands for a todo of the list it was sent to
+CREATE TABLE idempotency_keys (
+  list_id integer not null references lists (id) on delete cascade,
+  key text not null,
+  created_at timestamptz not null default now(),
+  PRIMARY KEY (list_id, key)
+);
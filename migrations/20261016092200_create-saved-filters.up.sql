@@ -0,0 +1,8 @@
+-- Add migration script here
+-- a named filter and sort of a list's todos, kept as the query string the todos are loaded with
+CREATE TABLE saved_filters (
+  id serial PRIMARY KEY,
+  list_id integer not null references lists (id) on delete cascade,
+  name text not null,
+  query text not null
+);
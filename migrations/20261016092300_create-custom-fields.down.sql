@@ -0,0 +1,3 @@
+DROP TABLE todo_custom_values;
+DROP TABLE custom_fields;
+DROP TYPE custom_field_kind;
@@ -0,0 +1,19 @@
+-- Add migration script here
+CREATE TYPE custom_field_kind AS ENUM ('text', 'number', 'date', 'select');
+
+-- extra fields that every todo can have a value for
+CREATE TABLE custom_fields (
+  id serial PRIMARY KEY,
+  name text not null unique,
+  kind custom_field_kind not null,
+  -- the choices of a select field
+  options text[] not null default '{}'
+);
+
+-- values are kept as entered, after checking they suit the field's kind
+CREATE TABLE todo_custom_values (
+  todo_id integer not null references todos (id) on delete cascade,
+  field_id integer not null references custom_fields (id) on delete cascade,
+  value text not null,
+  PRIMARY KEY (todo_id, field_id)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE todos DROP COLUMN email_subject;
+ALTER TABLE todos DROP COLUMN email_from;
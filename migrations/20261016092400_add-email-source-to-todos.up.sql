@@ -0,0 +1,4 @@
+-- Add migration script here
+-- set on todos that were emailed in, see inbound_email
+ALTER TABLE todos ADD COLUMN email_from text;
+ALTER TABLE todos ADD COLUMN email_subject text;
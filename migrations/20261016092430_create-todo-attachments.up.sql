@@ -0,0 +1,10 @@
+-- Add migration script here
+-- files that came with an emailed todo, see inbound_email
+CREATE TABLE todo_attachments (
+  id serial PRIMARY KEY,
+  todo_id integer not null references todos (id) on delete cascade,
+  file_name text not null,
+  data bytea not null
+);
+
+CREATE INDEX todo_attachments_todo_id_idx ON todo_attachments (todo_id);
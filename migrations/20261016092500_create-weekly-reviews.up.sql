@@ -0,0 +1,6 @@
+-- Add migration script here
+-- one row per weekly review email sent, so a restart on a Sunday doesn't send it twice
+CREATE TABLE weekly_reviews (
+  week_ending date primary key,
+  sent_at timestamptz not null default now()
+);
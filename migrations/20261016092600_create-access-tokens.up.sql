@@ -0,0 +1,11 @@
+-- Add migration script here
+CREATE TYPE token_scope AS ENUM ('read', 'read_write');
+-- personal access tokens for the JSON API, only a sha256 of the token itself is kept
+CREATE TABLE access_tokens (
+  id serial primary key,
+  name text not null,
+  scope token_scope not null,
+  token_hash bytea not null unique,
+  created_at timestamptz not null default now(),
+  last_used_at timestamptz
+);
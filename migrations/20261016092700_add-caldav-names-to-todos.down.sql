@@ -0,0 +1,3 @@
+DROP INDEX todos_caldav_name;
+ALTER TABLE todos DROP COLUMN caldav_uid;
+ALTER TABLE todos DROP COLUMN caldav_name;
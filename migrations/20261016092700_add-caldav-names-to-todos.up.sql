@@ -0,0 +1,6 @@
+-- Add migration script here
+-- todos created by CalDAV clients keep the resource name and UID the client chose,
+-- the others are todo-<id>.ics with UID todo-<id>@todo-axum-htmx
+ALTER TABLE todos ADD COLUMN caldav_name text;
+ALTER TABLE todos ADD COLUMN caldav_uid text;
+CREATE UNIQUE INDEX todos_caldav_name ON todos (list_id, caldav_name);
@@ -0,0 +1,11 @@
+-- Add migration script here
+-- chats that may use the Telegram bot, linked with a one-time code from the Telegram page
+CREATE TABLE telegram_chats (
+  chat_id bigint primary key,
+  name text not null,
+  linked_at timestamptz not null default now()
+);
+CREATE TABLE telegram_link_codes (
+  code text primary key,
+  created_at timestamptz not null default now()
+);
@@ -1,4 +1,4 @@
-use axum::{routing::get, Router};
+use axum::{middleware, routing::get, Extension, Router};
 use listenfd::ListenFd;
 use sqlx::postgres::PgPoolOptions;
 use tokio::net::TcpListener;
@@ -7,13 +7,22 @@ use tower_http::{
     trace::TraceLayer,
 };
 
-use crate::todos;
+use crate::{
+    calendar,
+    config::Config,
+    csrf, custom_fields, export, list_templates, lists,
+    mailer::Mailer,
+    notifications::{self, Notifier},
+    openapi, stats, todos, tokens,
+};
 
 pub async fn app() -> Router {
+    let config = Config::from_env();
+
     // Connect to postgres
     let pool = PgPoolOptions::new()
         .max_connections(5)
-        .connect("postgres://localhost/todo-axum-htmx")
+        .connect(&config.database_url)
         .await
         .expect("should be able to connect to DB");
     let row: (i64,) = sqlx::query_as("SELECT $1")
@@ -28,15 +37,76 @@ pub async fn app() -> Router {
         .with_max_level(tracing::Level::DEBUG)
         .init();
 
+    // Handlers that send email can take an `Extension<Mailer>`
+    let mailer =
+        Mailer::from_config(&config.mailer).expect("should be able to configure the mailer");
+
+    // Handlers and background tasks that tell open pages about something take an `Extension<Notifier>`
+    let notifier = Notifier::new();
+
+    // Email reminders about todos that are coming due in the background
+    todos::reminders::spawn(
+        config.reminders,
+        mailer.clone(),
+        notifier.clone(),
+        pool.clone(),
+    );
+
+    // Email a look back at the week on Sundays
+    stats::review::spawn(config.review, mailer.clone(), pool.clone());
+
+    // Remove deleted todos for good once they have been deleted for long enough
+    todos::trash::spawn(config.trash, pool.clone());
+
+    // Renumber the todos of lists that reordering has squeezed together, before it gets in the way
+    todos::rebalance::spawn(config.rebalance, pool.clone());
+
     // Serve files from the client directory, falling back to client/404.html
     let serve_dir = ServeDir::new("client").not_found_service(ServeFile::new("client/404.html"));
 
     // Respond to these routes, otherwise attempt to serve the file from the client directory
     // Also, add tracing of requests and add the postgres pool to the state so that our routes can use it
+    // Every request goes through CSRF protection, which also hands out the token on page loads,
+    // except for the inbound email, Telegram and Slack webhooks and CalDAV
     Router::new()
-        .route("/", get(todos::routes::index))
-        .nest("/todos", todos::routes::routes(&pool))
+        .route("/", get(lists::routes::index).with_state(pool.clone()))
+        .route(
+            "/share/:slug",
+            get(lists::routes::shared).with_state(pool.clone()),
+        )
+        .nest("/lists", lists::routes::routes(&pool))
+        .nest("/lists/:list_id/todos", todos::routes::routes(&pool))
+        .nest("/list_templates", list_templates::routes::routes(&pool))
+        .nest("/custom_fields", custom_fields::routes::routes(&pool))
+        .nest("/calendar", calendar::routes::routes(&pool))
+        .nest("/board", todos::board::routes(&pool))
+        .nest("/today", todos::agenda::routes(&pool))
+        .nest("/stats", stats::routes::routes(&pool))
+        .nest("/tokens", tokens::routes::routes(&pool, config.tokens))
+        .nest("/telegram", todos::telegram::routes(&pool))
+        .nest("/export", todos::todotxt::routes(&pool))
+        .nest("/import", todos::list_import::routes(&pool))
+        .merge(export::routes::routes(&pool, config.feeds))
+        .merge(notifications::routes(notifier.clone()))
+        .merge(openapi::routes())
         .fallback_service(serve_dir)
+        .layer(Extension(mailer))
+        .layer(Extension(notifier.clone()))
+        .layer(middleware::from_fn_with_state(pool.clone(), csrf::protect))
+        // Mail providers, Telegram and Slack can't send a CSRF token, the webhooks check their own secrets
+        .merge(todos::inbound_email::routes(&pool, config.inbound_email))
+        .merge(todos::telegram::webhook_routes(
+            &pool,
+            config.telegram,
+            notifier.clone(),
+        ))
+        .merge(todos::slack::webhook_routes(
+            &pool,
+            config.slack,
+            notifier.clone(),
+        ))
+        // Neither can CalDAV clients, they sign in with a personal access token
+        .merge(todos::caldav::routes(&pool, notifier))
         .layer(TraceLayer::new_for_http())
 }
 
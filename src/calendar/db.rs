@@ -0,0 +1,49 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+use time::Date;
+
+use crate::utils;
+
+use super::CalendarTodo;
+
+// Todos from every list due between `from` and `to`, both included
+pub async fn get_due_todos(
+    from: Date,
+    to: Date,
+    pool: &PgPool,
+) -> Result<Vec<CalendarTodo>, (StatusCode, String)> {
+    sqlx::query_as!(
+        CalendarTodo,
+        r#"select id, list_id, description, done, due_at as "due_at!"
+           from todos
+           where due_at between $1 and $2 and archived_at is null and deleted_at is null
+           ORDER BY due_at, done, priority desc, id"#,
+        from,
+        to,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// Moving the due date means the todo is worth reminding about again
+pub async fn reschedule(
+    todo_id: i32,
+    due_at: Date,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let result = sqlx::query!(
+        "update todos set due_at = $2,
+           reminded_at = case when due_at is distinct from $2 then null else reminded_at end
+         where id = $1 and archived_at is null and deleted_at is null",
+        todo_id,
+        due_at,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, String::from("Todo not found")));
+    }
+    Ok(())
+}
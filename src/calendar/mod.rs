@@ -0,0 +1,15 @@
+use time::Date;
+
+mod db;
+pub mod routes;
+mod templates;
+
+// A todo with a due date, as shown on the calendar
+#[derive(Debug, Clone)]
+struct CalendarTodo {
+    id: i32,
+    list_id: i32,
+    description: String,
+    done: bool,
+    due_at: Date,
+}
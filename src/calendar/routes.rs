@@ -0,0 +1,101 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+
+use axum_extra::extract::Form;
+
+use serde::Deserialize;
+use sqlx::{PgPool, Pool, Postgres};
+use time::{
+    format_description::FormatItem, macros::format_description, Date, Month, OffsetDateTime,
+};
+
+use crate::csrf::CsrfToken;
+use crate::utils::HtmlTemplate;
+
+use super::{db, templates};
+
+// calendar routes, nested under /calendar
+pub fn routes(pool: &Pool<Postgres>) -> Router {
+    Router::new()
+        .route("/", get(current_month))
+        .route("/:year/:month", get(show))
+        .route("/:year/:month/reschedule", post(reschedule))
+        .with_state(pool.clone())
+}
+
+// get /calendar
+async fn current_month(
+    State(pool): State<PgPool>,
+    headers: HeaderMap,
+    csrf_token: CsrfToken,
+) -> Result<Response, (StatusCode, String)> {
+    let today = OffsetDateTime::now_utc().date();
+    show(
+        Path((today.year(), today.month() as u8)),
+        State(pool),
+        headers,
+        csrf_token,
+    )
+    .await
+}
+
+// get /calendar/:year/:month
+// Moving between months swaps in just the month, a full page load gets the whole page
+async fn show(
+    Path((year, month)): Path<(i32, u8)>,
+    State(pool): State<PgPool>,
+    headers: HeaderMap,
+    CsrfToken(csrf_token): CsrfToken,
+) -> Result<Response, (StatusCode, String)> {
+    let template = render_month(year, month, &pool).await?;
+    if headers.contains_key("HX-Request") {
+        return Ok(HtmlTemplate(template).into_response());
+    }
+    Ok(HtmlTemplate(templates::render_show(csrf_token, template)).into_response())
+}
+
+#[derive(Deserialize)]
+struct RescheduleParams {
+    todo_id: i32,
+    due_at: String,
+}
+
+const DATE_FORMAT: &[FormatItem<'static>] = format_description!("[year]-[month]-[day]");
+
+// post /calendar/:year/:month/reschedule
+// A todo was dropped on another day of the month that is being shown
+async fn reschedule(
+    Path((year, month)): Path<(i32, u8)>,
+    State(pool): State<PgPool>,
+    Form(params): Form<RescheduleParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let due_at = Date::parse(&params.due_at, DATE_FORMAT).map_err(|err| {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("Invalid due date: {err}"),
+        )
+    })?;
+    db::reschedule(params.todo_id, due_at, &pool).await?;
+    Ok(HtmlTemplate(render_month(year, month, &pool).await?))
+}
+
+async fn render_month(
+    year: i32,
+    month: u8,
+    pool: &PgPool,
+) -> Result<templates::CalendarMonthTemplate, (StatusCode, String)> {
+    let first = Month::try_from(month)
+        .ok()
+        .and_then(|month| Date::from_calendar_date(year, month, 1).ok())
+        .ok_or((StatusCode::NOT_FOUND, String::from("No such month")))?;
+    let last = first
+        .replace_day(time::util::days_in_year_month(year, first.month()))
+        .expect("the last day of the month should be a valid date");
+    let todos = db::get_due_todos(first, last, pool).await?;
+    Ok(templates::CalendarMonthTemplate::new(first, todos))
+}
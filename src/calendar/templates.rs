@@ -0,0 +1,100 @@
+use askama::Template;
+use time::{util::days_in_year_month, Date, Month, OffsetDateTime};
+
+use super::CalendarTodo;
+
+#[derive(Template)]
+#[template(path = "base.html")]
+struct BaseTemplate {}
+
+// The calendar page, showing one month
+#[derive(Template)]
+#[template(path = "calendar_show.html")]
+pub struct CalendarShowTemplate<'a> {
+    _parent: &'a BaseTemplate,
+    csrf_token: String,
+    month: CalendarMonthTemplate,
+}
+
+pub fn render_show<'a>(
+    csrf_token: String,
+    month: CalendarMonthTemplate,
+) -> CalendarShowTemplate<'a> {
+    CalendarShowTemplate {
+        _parent: &BaseTemplate {},
+        csrf_token,
+        month,
+    }
+}
+
+// A month as a grid of weeks, Monday first, swapped into #calendar when moving between months
+#[derive(Template)]
+#[template(path = "calendar_month.html")]
+pub struct CalendarMonthTemplate {
+    year: i32,
+    month: Month,
+    // 1 to 12, for URLs
+    month_number: u8,
+    // each week has seven days, None for the days of the neighbouring months
+    weeks: Vec<Vec<Option<CalendarDay>>>,
+    previous: (i32, u8),
+    next: (i32, u8),
+}
+
+struct CalendarDay {
+    date: Date,
+    today: bool,
+    todos: Vec<CalendarTodo>,
+}
+
+impl CalendarMonthTemplate {
+    // `first` is the first day of the month, `todos` are the ones due that month by due date
+    pub fn new(first: Date, todos: Vec<CalendarTodo>) -> Self {
+        let (year, month) = (first.year(), first.month());
+        let today = OffsetDateTime::now_utc().date();
+        let mut todos = todos.into_iter().peekable();
+        let mut days: Vec<Option<CalendarDay>> = Vec::new();
+        days.extend((0..first.weekday().number_days_from_monday()).map(|_| None));
+        for day in 1..=days_in_year_month(year, month) {
+            let date = first.replace_day(day).unwrap_or(first);
+            let mut due = Vec::new();
+            while let Some(todo) = todos.next_if(|todo| todo.due_at == date) {
+                due.push(todo);
+            }
+            days.push(Some(CalendarDay {
+                date,
+                today: date == today,
+                todos: due,
+            }));
+        }
+        while !days.len().is_multiple_of(7) {
+            days.push(None);
+        }
+        let mut weeks = Vec::new();
+        let mut days = days.into_iter();
+        loop {
+            let week: Vec<Option<CalendarDay>> = days.by_ref().take(7).collect();
+            if week.is_empty() {
+                break;
+            }
+            weeks.push(week);
+        }
+
+        let previous = match month.previous() {
+            Month::December => (year - 1, 12),
+            previous => (year, previous as u8),
+        };
+        let next = match month.next() {
+            Month::January => (year + 1, 1),
+            next => (year, next as u8),
+        };
+        CalendarMonthTemplate {
+            year,
+            month,
+            month_number: month as u8,
+            weeks,
+            previous,
+            next,
+        }
+    }
+}
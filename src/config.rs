@@ -0,0 +1,206 @@
+use std::{env, time::Duration};
+
+/// Settings read from the environment at startup
+pub struct Config {
+    pub database_url: String,
+    pub mailer: MailerConfig,
+    pub reminders: ReminderConfig,
+    pub review: ReviewConfig,
+    pub trash: TrashConfig,
+    pub rebalance: RebalanceConfig,
+    pub feeds: FeedConfig,
+    pub inbound_email: InboundEmailConfig,
+    pub tokens: TokensConfig,
+    pub telegram: TelegramConfig,
+    pub slack: SlackConfig,
+}
+
+pub struct MailerConfig {
+    /// When unset, emails are logged instead of being sent
+    pub smtp_host: Option<String>,
+    pub smtp_port: u16,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    pub from: String,
+}
+
+pub struct ReminderConfig {
+    /// Where reminders about todos that are coming due are sent. When unset, no reminders are sent.
+    pub to: Option<String>,
+    /// How many days before its due date a todo is included in a reminder,
+    /// unless the todo sets its own number of days
+    pub window_days: i32,
+    /// How often to check for todos that are coming due
+    pub interval: Duration,
+}
+
+pub struct ReviewConfig {
+    /// Where the weekly review is sent on Sundays. When unset, no reviews are sent.
+    pub to: Option<String>,
+    /// How often to check whether this week's review is due
+    pub interval: Duration,
+}
+
+pub struct TrashConfig {
+    /// How many days deleted todos are kept before they are removed for good
+    pub retention_days: i32,
+    /// How often to look for deleted todos that are past the retention period
+    pub interval: Duration,
+}
+
+pub struct RebalanceConfig {
+    /// How often to look for lists whose todo positions have been squeezed together by reordering
+    pub interval: Duration,
+}
+
+/// Secrets that feed readers and calendar apps put in the URL. When one is unset, its feed is off.
+pub struct FeedConfig {
+    /// For /todos/calendar.ics
+    pub calendar_token: Option<String>,
+    /// For /feeds/activity.atom
+    pub activity_token: Option<String>,
+}
+
+/// Turning emails into todos, see todos::inbound_email
+pub struct InboundEmailConfig {
+    /// The secret the mail provider's webhook posts with. When unset, no emails are taken in.
+    pub token: Option<String>,
+    /// Where emailed todos go. When unset, they go to the first list.
+    pub list_id: Option<i32>,
+}
+
+/// Making personal access tokens at /tokens, see tokens::routes
+pub struct TokensConfig {
+    /// What has to be entered to make a token. When unset, no tokens can be made.
+    pub secret: Option<String>,
+}
+
+/// Talking to the Telegram bot, see todos::telegram
+pub struct TelegramConfig {
+    /// The secret_token the bot's webhook was set with. When unset, the bot isn't listened to.
+    pub secret: Option<String>,
+    /// Where todos added from a chat go. When unset, they go to the first list.
+    pub list_id: Option<i32>,
+}
+
+/// Answering the Slack app's slash command and buttons, see todos::slack
+pub struct SlackConfig {
+    /// The app's signing secret. When unset, Slack isn't listened to.
+    pub signing_secret: Option<String>,
+    /// Where todos added from Slack go. When unset, they go to the first list.
+    pub list_id: Option<i32>,
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        Config {
+            database_url: env::var("DATABASE_URL")
+                .unwrap_or(String::from("postgres://localhost/todo-axum-htmx")),
+            mailer: MailerConfig {
+                smtp_host: env::var("SMTP_HOST").ok(),
+                smtp_port: env::var("SMTP_PORT")
+                    .map(|port| port.parse().expect("SMTP_PORT should be a port number"))
+                    .unwrap_or(587),
+                smtp_username: env::var("SMTP_USERNAME").ok(),
+                smtp_password: env::var("SMTP_PASSWORD").ok(),
+                from: env::var("MAIL_FROM").unwrap_or(String::from("Todos <todos@localhost>")),
+            },
+            reminders: ReminderConfig {
+                to: env::var("REMINDER_EMAIL").ok(),
+                window_days: env::var("REMINDER_WINDOW_DAYS")
+                    .map(|days| {
+                        days.parse()
+                            .expect("REMINDER_WINDOW_DAYS should be a number")
+                    })
+                    .unwrap_or(1),
+                interval: env::var("REMINDER_INTERVAL_SECONDS")
+                    .map(|seconds| {
+                        Duration::from_secs(
+                            seconds
+                                .parse()
+                                .expect("REMINDER_INTERVAL_SECONDS should be a number"),
+                        )
+                    })
+                    .unwrap_or(Duration::from_secs(300)),
+            },
+            review: ReviewConfig {
+                to: env::var("WEEKLY_REVIEW_EMAIL").ok(),
+                interval: env::var("WEEKLY_REVIEW_INTERVAL_SECONDS")
+                    .map(|seconds| {
+                        Duration::from_secs(
+                            seconds
+                                .parse()
+                                .expect("WEEKLY_REVIEW_INTERVAL_SECONDS should be a number"),
+                        )
+                    })
+                    .unwrap_or(Duration::from_secs(3600)),
+            },
+            trash: TrashConfig {
+                retention_days: env::var("TRASH_RETENTION_DAYS")
+                    .map(|days| {
+                        days.parse()
+                            .expect("TRASH_RETENTION_DAYS should be a number")
+                    })
+                    .unwrap_or(30),
+                interval: env::var("TRASH_PURGE_INTERVAL_SECONDS")
+                    .map(|seconds| {
+                        Duration::from_secs(
+                            seconds
+                                .parse()
+                                .expect("TRASH_PURGE_INTERVAL_SECONDS should be a number"),
+                        )
+                    })
+                    .unwrap_or(Duration::from_secs(3600)),
+            },
+            rebalance: RebalanceConfig {
+                interval: env::var("REBALANCE_INTERVAL_SECONDS")
+                    .map(|seconds| {
+                        Duration::from_secs(
+                            seconds
+                                .parse()
+                                .expect("REBALANCE_INTERVAL_SECONDS should be a number"),
+                        )
+                    })
+                    .unwrap_or(Duration::from_secs(3600)),
+            },
+            feeds: FeedConfig {
+                calendar_token: env::var("CALENDAR_TOKEN")
+                    .ok()
+                    .filter(|token| !token.is_empty()),
+                activity_token: env::var("FEED_TOKEN")
+                    .ok()
+                    .filter(|token| !token.is_empty()),
+            },
+            inbound_email: InboundEmailConfig {
+                token: env::var("INBOUND_EMAIL_TOKEN")
+                    .ok()
+                    .filter(|token| !token.is_empty()),
+                list_id: env::var("INBOUND_EMAIL_LIST_ID").ok().map(|id| {
+                    id.parse()
+                        .expect("INBOUND_EMAIL_LIST_ID should be a list id")
+                }),
+            },
+            tokens: TokensConfig {
+                secret: env::var("TOKENS_SECRET")
+                    .ok()
+                    .filter(|secret| !secret.is_empty()),
+            },
+            telegram: TelegramConfig {
+                secret: env::var("TELEGRAM_WEBHOOK_SECRET")
+                    .ok()
+                    .filter(|secret| !secret.is_empty()),
+                list_id: env::var("TELEGRAM_LIST_ID")
+                    .ok()
+                    .map(|id| id.parse().expect("TELEGRAM_LIST_ID should be a list id")),
+            },
+            slack: SlackConfig {
+                signing_secret: env::var("SLACK_SIGNING_SECRET")
+                    .ok()
+                    .filter(|secret| !secret.is_empty()),
+                list_id: env::var("SLACK_LIST_ID")
+                    .ok()
+                    .map(|id| id.parse().expect("SLACK_LIST_ID should be a list id")),
+            },
+        }
+    }
+}
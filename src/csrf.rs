@@ -0,0 +1,100 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request},
+    http::{request::Parts, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+
+use crate::tokens::{BearerToken, TokenScope};
+use crate::utils;
+
+const COOKIE_NAME: &str = "csrf_token";
+const HEADER_NAME: &str = "X-CSRF-Token";
+const TOKEN_LENGTH: usize = 32;
+
+/// The CSRF token for the current request, put in place by [`protect`] so that
+/// handlers can hand it to their templates.
+#[derive(Clone, Debug)]
+pub struct CsrfToken(pub String);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for CsrfToken
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<CsrfToken>().cloned().ok_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            String::from("CSRF middleware is not installed"),
+        ))
+    }
+}
+
+/// Double-submit cookie middleware.
+///
+/// Every response carries a random token in the `csrf_token` cookie. Requests that
+/// can change state (anything but GET/HEAD/OPTIONS) must echo that token back in
+/// the `X-CSRF-Token` header, which htmx sends for us via `hx-headers` on `<body>`.
+///
+/// Requests with a valid read-write personal access token as `Authorization: Bearer` are let
+/// through instead: browsers don't attach that header on their own, so it can't be forged
+/// cross-site. Unknown and revoked tokens are turned away, and so are read-only ones on
+/// anything that can change state. The token that was checked is left in the request's
+/// extensions, so [`BearerToken`] doesn't look it up again.
+pub async fn protect(
+    BearerToken(scope): BearerToken,
+    jar: CookieJar,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let existing = jar.get(COOKIE_NAME).map(|c| c.value().to_owned());
+
+    if !is_safe(request.method()) {
+        match scope {
+            Some(TokenScope::ReadWrite) => {}
+            Some(TokenScope::Read) => {
+                return utils::json_error(StatusCode::FORBIDDEN, "This token is read-only")
+            }
+            None => {
+                let submitted = request
+                    .headers()
+                    .get(HEADER_NAME)
+                    .and_then(|v| v.to_str().ok());
+                match (&existing, submitted) {
+                    (Some(expected), Some(submitted))
+                        if utils::tokens_match(expected, submitted) => {}
+                    _ => {
+                        return (StatusCode::FORBIDDEN, "Invalid or missing CSRF token")
+                            .into_response()
+                    }
+                }
+            }
+        }
+    }
+    request.extensions_mut().insert(BearerToken(scope));
+
+    match existing {
+        Some(token) => {
+            request.extensions_mut().insert(CsrfToken(token));
+            next.run(request).await
+        }
+        None => {
+            let token = utils::random_token(TOKEN_LENGTH);
+            request.extensions_mut().insert(CsrfToken(token.clone()));
+            let response = next.run(request).await;
+            let cookie = Cookie::build((COOKIE_NAME, token))
+                .path("/")
+                .http_only(true)
+                .same_site(SameSite::Strict);
+            (jar.add(cookie), response).into_response()
+        }
+    }
+}
+
+fn is_safe(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
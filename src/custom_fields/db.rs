@@ -0,0 +1,123 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+
+use crate::utils;
+
+use super::{CustomField, CustomFieldKind, CustomFieldValue};
+
+pub async fn get_custom_fields(pool: &PgPool) -> Result<Vec<CustomField>, (StatusCode, String)> {
+    sqlx::query_as!(
+        CustomField,
+        r#"select id, name, kind as "kind: CustomFieldKind", options from custom_fields ORDER BY id"#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+pub async fn get_custom_field(
+    field_id: i32,
+    pool: &PgPool,
+) -> Result<CustomField, (StatusCode, String)> {
+    sqlx::query_as!(
+        CustomField,
+        r#"select id, name, kind as "kind: CustomFieldKind", options from custom_fields where id = $1"#,
+        field_id,
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(utils::internal_error)?
+    .ok_or((StatusCode::NOT_FOUND, String::from("Custom field not found")))
+}
+
+pub async fn create_custom_field(
+    name: &str,
+    kind: CustomFieldKind,
+    options: &[String],
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        "insert into custom_fields (name, kind, options) values ($1, $2, $3)
+         on conflict (name) do nothing",
+        name,
+        kind as _,
+        options,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+// Removes the field's values from every todo too
+pub async fn delete_custom_field(field_id: i32, pool: &PgPool) -> Result<(), (StatusCode, String)> {
+    sqlx::query!("delete from custom_fields where id = $1", field_id)
+        .execute(pool)
+        .await
+        .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+// Every field, with the todo's value where it has one
+pub async fn get_todo_values(
+    todo_id: i32,
+    pool: &PgPool,
+) -> Result<Vec<CustomFieldValue>, (StatusCode, String)> {
+    let rows = sqlx::query!(
+        r#"select custom_fields.id, custom_fields.name, custom_fields.kind as "kind: CustomFieldKind",
+                custom_fields.options, todo_custom_values.value as "value?"
+           from custom_fields
+           left join todo_custom_values
+             on todo_custom_values.field_id = custom_fields.id and todo_custom_values.todo_id = $1
+           ORDER BY custom_fields.id"#,
+        todo_id,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(rows
+        .into_iter()
+        .map(|row| CustomFieldValue {
+            field: CustomField {
+                id: row.id,
+                name: row.name,
+                kind: row.kind,
+                options: row.options,
+            },
+            value: row.value,
+        })
+        .collect())
+}
+
+// `None` clears the todo's value for the field
+pub async fn set_todo_value(
+    todo_id: i32,
+    field_id: i32,
+    value: Option<&str>,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    match value {
+        Some(value) => {
+            sqlx::query!(
+                "insert into todo_custom_values (todo_id, field_id, value) values ($1, $2, $3)
+             on conflict (todo_id, field_id) do update set value = excluded.value",
+                todo_id,
+                field_id,
+                value,
+            )
+            .execute(pool)
+            .await
+        }
+        None => {
+            sqlx::query!(
+                "delete from todo_custom_values where todo_id = $1 and field_id = $2",
+                todo_id,
+                field_id,
+            )
+            .execute(pool)
+            .await
+        }
+    }
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
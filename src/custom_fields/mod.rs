@@ -0,0 +1,81 @@
+use std::fmt;
+
+use serde::Deserialize;
+use time::{format_description::FormatItem, macros::format_description, Date};
+
+mod db;
+pub mod routes;
+mod templates;
+
+// What a custom field holds, which decides its input. Matches the `custom_field_kind` postgres enum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "custom_field_kind", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+enum CustomFieldKind {
+    Text,
+    Number,
+    Date,
+    Select,
+}
+
+impl fmt::Display for CustomFieldKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            CustomFieldKind::Text => "text",
+            CustomFieldKind::Number => "number",
+            CustomFieldKind::Date => "date",
+            CustomFieldKind::Select => "select",
+        })
+    }
+}
+
+// A field that every todo can have a value for
+#[derive(Debug, Clone)]
+struct CustomField {
+    id: i32,
+    name: String,
+    kind: CustomFieldKind,
+    // the choices of a select field, empty for the other kinds
+    options: Vec<String>,
+}
+
+// A field along with one todo's value for it, if it has one
+#[derive(Debug, Clone)]
+struct CustomFieldValue {
+    field: CustomField,
+    value: Option<String>,
+}
+
+const DATE_FORMAT: &[FormatItem<'static>] = format_description!("[year]-[month]-[day]");
+
+impl CustomField {
+    // The value to store for what was entered, None to clear it.
+    // The error is shown to the user next to the input.
+    fn parse_value(&self, value: &str) -> Result<Option<String>, String> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(None);
+        }
+        match self.kind {
+            CustomFieldKind::Text => {}
+            CustomFieldKind::Number => {
+                value
+                    .parse::<f64>()
+                    .map_err(|_| format!("{} should be a number", self.name))?;
+            }
+            CustomFieldKind::Date => {
+                Date::parse(value, DATE_FORMAT)
+                    .map_err(|_| format!("{} should be a date", self.name))?;
+            }
+            CustomFieldKind::Select => {
+                if !self.options.iter().any(|option| option == value) {
+                    return Err(format!(
+                        "{value} isn't one of the choices for {}",
+                        self.name
+                    ));
+                }
+            }
+        }
+        Ok(Some(value.to_string()))
+    }
+}
@@ -0,0 +1,131 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{delete, get, put},
+    Router,
+};
+
+use axum_extra::extract::Form;
+
+use serde::Deserialize;
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::utils::HtmlTemplate;
+
+use super::{db, templates, CustomFieldKind};
+
+// custom fields routes, nested under /custom_fields
+// The values of a todo's fields live under /custom_fields/todos/:todo_id
+pub fn routes(pool: &Pool<Postgres>) -> Router {
+    Router::new()
+        .route("/", get(picker).post(create))
+        .route("/:field_id", delete(destroy))
+        .route("/todos/:todo_id", get(todo_values))
+        .route("/todos/:todo_id/:field_id", put(set_todo_value))
+        .with_state(pool.clone())
+}
+
+async fn render_picker(
+    pool: &PgPool,
+) -> Result<HtmlTemplate<templates::CustomFieldsPickerTemplate>, (StatusCode, String)> {
+    Ok(HtmlTemplate(templates::CustomFieldsPickerTemplate {
+        custom_fields: db::get_custom_fields(pool).await?,
+    }))
+}
+
+// get /custom_fields
+async fn picker(State(pool): State<PgPool>) -> Result<impl IntoResponse, (StatusCode, String)> {
+    render_picker(&pool).await
+}
+
+#[derive(Deserialize)]
+struct CustomFieldParams {
+    name: String,
+    kind: CustomFieldKind,
+    // the choices of a select field, separated by commas
+    #[serde(default)]
+    options: String,
+}
+
+// post /custom_fields
+// A name that is already taken is ignored
+async fn create(
+    State(pool): State<PgPool>,
+    Form(params): Form<CustomFieldParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let name = params.name.trim();
+    if name.is_empty() {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("Custom field names can't be blank"),
+        ));
+    }
+    let options: Vec<String> = match params.kind {
+        CustomFieldKind::Select => params
+            .options
+            .split(',')
+            .map(str::trim)
+            .filter(|option| !option.is_empty())
+            .map(String::from)
+            .collect(),
+        _ => vec![],
+    };
+    if params.kind == CustomFieldKind::Select && options.is_empty() {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("Select fields need at least one choice"),
+        ));
+    }
+    db::create_custom_field(name, params.kind, &options, &pool).await?;
+    render_picker(&pool).await
+}
+
+// delete /custom_fields/:field_id
+async fn destroy(
+    Path(field_id): Path<i32>,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    db::delete_custom_field(field_id, &pool).await?;
+    render_picker(&pool).await
+}
+
+// get /custom_fields/todos/:todo_id
+async fn todo_values(
+    Path(todo_id): Path<i32>,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    Ok(HtmlTemplate(templates::TodoCustomFieldsTemplate::new(
+        todo_id,
+        db::get_todo_values(todo_id, &pool).await?,
+        None,
+    )))
+}
+
+#[derive(Deserialize)]
+struct TodoValueParams {
+    #[serde(default)]
+    value: String,
+}
+
+// put /custom_fields/todos/:todo_id/:field_id
+// A value that doesn't suit the field isn't saved, it stays in the input with the reason next to it
+async fn set_todo_value(
+    Path((todo_id, field_id)): Path<(i32, i32)>,
+    State(pool): State<PgPool>,
+    Form(params): Form<TodoValueParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let field = db::get_custom_field(field_id, &pool).await?;
+    let error = match field.parse_value(&params.value) {
+        Ok(value) => {
+            db::set_todo_value(todo_id, field_id, value.as_deref(), &pool).await?;
+            None
+        }
+        Err(message) => Some((field_id, params.value, message)),
+    };
+    Ok(HtmlTemplate(templates::TodoCustomFieldsTemplate::new(
+        todo_id,
+        db::get_todo_values(todo_id, &pool).await?,
+        error,
+    )))
+}
@@ -0,0 +1,55 @@
+use askama::Template;
+
+use super::{CustomField, CustomFieldKind, CustomFieldValue};
+
+// The custom fields, with a form for adding another one
+#[derive(Template)]
+#[template(path = "custom_fields_picker.html")]
+pub struct CustomFieldsPickerTemplate {
+    pub custom_fields: Vec<CustomField>,
+}
+
+// An input for each custom field, shown in a todo's edit form.
+// Each one saves as soon as it is changed.
+#[derive(Template)]
+#[template(path = "todo_custom_fields.html")]
+pub struct TodoCustomFieldsTemplate {
+    todo_id: i32,
+    inputs: Vec<CustomFieldInput>,
+}
+
+struct CustomFieldInput {
+    field: CustomField,
+    // the saved value, or what was entered when it couldn't be saved
+    value: String,
+    error: Option<String>,
+}
+
+impl TodoCustomFieldsTemplate {
+    // `error` is the (field id, what was entered, why it wasn't saved) of a rejected value
+    pub fn new(
+        todo_id: i32,
+        values: Vec<CustomFieldValue>,
+        error: Option<(i32, String, String)>,
+    ) -> Self {
+        let mut error = error;
+        let inputs = values
+            .into_iter()
+            .map(|CustomFieldValue { field, value }| {
+                match error.take_if(|(field_id, _, _)| *field_id == field.id) {
+                    Some((_, entered, message)) => CustomFieldInput {
+                        field,
+                        value: entered,
+                        error: Some(message),
+                    },
+                    None => CustomFieldInput {
+                        field,
+                        value: value.unwrap_or_default(),
+                        error: None,
+                    },
+                }
+            })
+            .collect();
+        TodoCustomFieldsTemplate { todo_id, inputs }
+    }
+}
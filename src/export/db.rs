@@ -0,0 +1,132 @@
+use axum::http::StatusCode;
+use futures::stream::BoxStream;
+use sqlx::PgPool;
+use time::OffsetDateTime;
+
+use crate::utils;
+
+use super::{
+    ActivityEvent, Backup, BackupList, BackupTodo, CalendarTodo, ExportedTodo, MarkdownTodo,
+    BACKUP_SCHEMA_VERSION,
+};
+
+// Every todo that hasn't been deleted, list by list and top to bottom, archived ones included
+pub fn stream_todos(pool: &PgPool) -> BoxStream<'_, Result<ExportedTodo, sqlx::Error>> {
+    sqlx::query_as!(
+        ExportedTodo,
+        r#"select lists.name as list_name, todos.description, todos.done, todos.due_at,
+                todos.priority::text as "priority!",
+                array(select tags.name from todo_tags join tags on tags.id = todo_tags.tag_id
+                      where todo_tags.todo_id = todos.id order by tags.name) as "tags!",
+                todos.created_at, todos.updated_at, todos.archived_at
+           from todos join lists on lists.id = todos.list_id
+           where todos.deleted_at is null
+           ORDER BY lists.name, lists.id, todos.pinned desc, todos.position desc"#
+    )
+    .fetch(pool)
+}
+
+// The name of a list and its current todos, top to bottom. None when there's no such list.
+pub async fn get_markdown_list(
+    list_id: i32,
+    pool: &PgPool,
+) -> Result<Option<(String, Vec<MarkdownTodo>)>, (StatusCode, String)> {
+    let Some(name) = sqlx::query_scalar!("select name from lists where id = $1", list_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(utils::internal_error)?
+    else {
+        return Ok(None);
+    };
+    let todos = sqlx::query_as!(
+        MarkdownTodo,
+        r#"select todos.description, todos.done, todos.pinned, todos.due_at,
+                array(select tags.name from todo_tags join tags on tags.id = todo_tags.tag_id
+                      where todo_tags.todo_id = todos.id order by tags.name) as "tags!"
+           from todos
+           where todos.list_id = $1 and todos.archived_at is null and todos.deleted_at is null
+           ORDER BY todos.pinned desc, todos.position desc"#,
+        list_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(Some((name, todos)))
+}
+
+// Read everything for /export.json in one transaction, so the parts agree with each other
+pub async fn get_backup(pool: &PgPool) -> Result<Backup, (StatusCode, String)> {
+    let mut tx = pool.begin().await.map_err(utils::internal_error)?;
+    let lists = sqlx::query_as!(BackupList, "select id, name from lists ORDER BY id")
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(utils::internal_error)?;
+    let tags = sqlx::query_scalar!("select name from tags ORDER BY name")
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(utils::internal_error)?;
+    let todos = sqlx::query_as!(
+        BackupTodo,
+        r#"select id, list_id, description, notes, done, position, due_at::text as due_at,
+                priority::text as "priority!", recurrence::text as recurrence,
+                remind_days_before, estimate_minutes, pinned, color::text as color,
+                array(select tags.name from todo_tags join tags on tags.id = todo_tags.tag_id
+                      where todo_tags.todo_id = todos.id order by tags.name) as "tags!",
+                array(select blocker_id from todo_dependencies
+                      where todo_dependencies.todo_id = todos.id order by blocker_id) as "blocked_by!",
+                created_at, updated_at, completed_at, archived_at
+           from todos
+           where deleted_at is null
+           ORDER BY list_id, id"#
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(utils::internal_error)?;
+    tx.commit().await.map_err(utils::internal_error)?;
+    Ok(Backup {
+        schema_version: BACKUP_SCHEMA_VERSION,
+        exported_at: OffsetDateTime::now_utc(),
+        lists,
+        tags,
+        todos,
+    })
+}
+
+pub async fn get_calendar_todos(pool: &PgPool) -> Result<Vec<CalendarTodo>, (StatusCode, String)> {
+    sqlx::query_as!(
+        CalendarTodo,
+        r#"select todos.id, lists.name as list_name, todos.description, todos.notes,
+                todos.due_at as "due_at!", todos.updated_at
+           from todos join lists on lists.id = todos.list_id
+           where not todos.done and todos.due_at is not null
+             and todos.archived_at is null and todos.deleted_at is null
+           ORDER BY todos.due_at, todos.id"#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// Soft-deleted todos drop out of the feed, their creation included
+pub async fn get_activity(
+    limit: i64,
+    pool: &PgPool,
+) -> Result<Vec<ActivityEvent>, (StatusCode, String)> {
+    sqlx::query_as!(
+        ActivityEvent,
+        r#"select events.todo_id as "todo_id!", todos.list_id, lists.name as list_name,
+                todos.description, events.completed as "completed!", events.at as "at!"
+           from (select id as todo_id, false as completed, created_at as at from todos
+                 union all
+                 select id, true, completed_at from todos where completed_at is not null) as events
+           join todos on todos.id = events.todo_id
+           join lists on lists.id = todos.list_id
+           where todos.deleted_at is null
+           ORDER BY events.at desc, events.completed desc
+           limit $1"#,
+        limit,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
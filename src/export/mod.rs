@@ -0,0 +1,101 @@
+use serde::Serialize;
+use time::{Date, OffsetDateTime};
+
+mod db;
+pub mod routes;
+mod templates;
+
+// One line of /todos/export.csv
+#[derive(Debug, Clone)]
+struct ExportedTodo {
+    list_name: String,
+    description: String,
+    done: bool,
+    due_at: Option<Date>,
+    priority: String,
+    tags: Vec<String>,
+    created_at: OffsetDateTime,
+    updated_at: OffsetDateTime,
+    archived_at: Option<OffsetDateTime>,
+}
+
+// Bump when the shape of /export.json changes, so that an importer can tell old backups apart
+const BACKUP_SCHEMA_VERSION: u32 = 1;
+
+// Everything in the app, see /export.json. Todos refer to lists, and to the todos blocking
+// them, by id.
+#[derive(Debug, Serialize)]
+struct Backup {
+    schema_version: u32,
+    #[serde(with = "time::serde::rfc3339")]
+    exported_at: OffsetDateTime,
+    lists: Vec<BackupList>,
+    tags: Vec<String>,
+    todos: Vec<BackupTodo>,
+}
+
+#[derive(Debug, Serialize)]
+struct BackupList {
+    id: i32,
+    name: String,
+}
+
+#[derive(Debug, Serialize)]
+struct BackupTodo {
+    id: i32,
+    list_id: i32,
+    description: String,
+    notes: String,
+    done: bool,
+    position: f64,
+    // yyyy-mm-dd
+    due_at: Option<String>,
+    priority: String,
+    recurrence: Option<String>,
+    remind_days_before: Option<i32>,
+    estimate_minutes: Option<i32>,
+    pinned: bool,
+    color: Option<String>,
+    tags: Vec<String>,
+    blocked_by: Vec<i32>,
+    #[serde(with = "time::serde::rfc3339")]
+    created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    updated_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    completed_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    archived_at: Option<OffsetDateTime>,
+}
+
+// One line of /lists/:list_id/export.md
+#[derive(Debug, Clone)]
+struct MarkdownTodo {
+    description: String,
+    done: bool,
+    pinned: bool,
+    due_at: Option<Date>,
+    tags: Vec<String>,
+}
+
+// A pending todo with a due date, see /todos/calendar.ics
+#[derive(Debug, Clone)]
+struct CalendarTodo {
+    id: i32,
+    list_name: String,
+    description: String,
+    notes: String,
+    due_at: Date,
+    updated_at: OffsetDateTime,
+}
+
+// A todo being created or completed, see /feeds/activity.atom
+#[derive(Debug, Clone)]
+struct ActivityEvent {
+    todo_id: i32,
+    list_id: i32,
+    list_name: String,
+    description: String,
+    completed: bool,
+    at: OffsetDateTime,
+}
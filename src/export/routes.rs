@@ -0,0 +1,310 @@
+use askama::Template;
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    BoxError, Extension, Json, Router,
+};
+use futures::{channel::mpsc, SinkExt, StreamExt};
+use serde::Deserialize;
+use sqlx::{PgPool, Pool, Postgres};
+use time::{format_description::well_known::Rfc3339, Date, OffsetDateTime, UtcOffset};
+
+use crate::{config::FeedConfig, ics, utils};
+
+use super::{db, templates, CalendarTodo, ExportedTodo, MarkdownTodo};
+
+// export routes, for getting data out of the app
+// Each feed only exists when it has a token to check, see FeedConfig
+pub fn routes(pool: &Pool<Postgres>, feeds: FeedConfig) -> Router {
+    let mut router = Router::new()
+        .route("/todos/export.csv", get(todos_csv))
+        .route("/export.json", get(backup))
+        .route("/lists/:list_id/export.md", get(list_markdown));
+    if let Some(token) = feeds.calendar_token {
+        router = router.route(
+            "/todos/calendar.ics",
+            get(calendar).layer(Extension(FeedToken(token))),
+        );
+    }
+    if let Some(token) = feeds.activity_token {
+        router = router.route(
+            "/feeds/activity.atom",
+            get(activity).layer(Extension(FeedToken(token))),
+        );
+    }
+    router.with_state(pool.clone())
+}
+
+// The secret the feed behind this extension must be requested with
+#[derive(Clone)]
+struct FeedToken(String);
+
+// Feed readers and calendar apps subscribe to a URL, so the token goes in the query string
+#[derive(Deserialize)]
+struct FeedParams {
+    token: String,
+}
+
+impl FeedParams {
+    fn check(&self, FeedToken(expected): &FeedToken) -> Result<(), (StatusCode, String)> {
+        if utils::tokens_match(expected, &self.token) {
+            Ok(())
+        } else {
+            Err((StatusCode::NOT_FOUND, String::from("Not found")))
+        }
+    }
+}
+
+const CSV_HEADER: [&str; 9] = [
+    "list",
+    "description",
+    "done",
+    "due_at",
+    "priority",
+    "tags",
+    "created_at",
+    "updated_at",
+    "archived_at",
+];
+
+// get /todos/export.csv
+// Rows are written to the response as they come out of postgres, so big exports don't
+// have to fit in memory
+async fn todos_csv(State(pool): State<PgPool>) -> impl IntoResponse {
+    let (mut sender, receiver) = mpsc::channel::<Result<Vec<u8>, BoxError>>(16);
+    tokio::spawn(async move {
+        let mut chunk = csv_line(CSV_HEADER);
+        let mut todos = db::stream_todos(&pool);
+        loop {
+            let done = chunk.is_err();
+            if sender.send(chunk).await.is_err() || done {
+                // the client went away, or the export failed and the body has been aborted
+                return;
+            }
+            chunk = match todos.next().await {
+                Some(Ok(todo)) => csv_line(csv_record(&todo)),
+                Some(Err(err)) => Err(err.into()),
+                None => return,
+            };
+        }
+    });
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        "text/csv; charset=utf-8"
+            .parse()
+            .expect("should be able to create a Content-Type header"),
+    );
+    let today = OffsetDateTime::now_utc().date();
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        format!("attachment; filename=\"todos-{today}.csv\"")
+            .parse()
+            .expect("should be able to create a Content-Disposition header"),
+    );
+    (headers, Body::from_stream(receiver))
+}
+
+// get /export.json
+async fn backup(State(pool): State<PgPool>) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let backup = db::get_backup(&pool).await?;
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        format!(
+            "attachment; filename=\"todos-backup-{}.json\"",
+            backup.exported_at.date()
+        )
+        .parse()
+        .expect("should be able to create a Content-Disposition header"),
+    );
+    Ok((headers, Json(backup)))
+}
+
+fn csv_record(todo: &ExportedTodo) -> [String; 9] {
+    let timestamp = |at: &OffsetDateTime| at.format(&Rfc3339).unwrap_or_default();
+    [
+        todo.list_name.clone(),
+        todo.description.clone(),
+        todo.done.to_string(),
+        todo.due_at
+            .map(|due_at| due_at.to_string())
+            .unwrap_or_default(),
+        todo.priority.clone(),
+        todo.tags.join(", "),
+        timestamp(&todo.created_at),
+        timestamp(&todo.updated_at),
+        todo.archived_at.as_ref().map(timestamp).unwrap_or_default(),
+    ]
+}
+
+// Write one line and hand back its bytes, quoted and escaped as needed
+fn csv_line<I, T>(record: I) -> Result<Vec<u8>, BoxError>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<[u8]>,
+{
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(record)?;
+    writer.into_inner().map_err(|err| err.into_error().into())
+}
+
+// A heading and which todos go under it
+type Section = (&'static str, fn(&MarkdownTodo) -> bool);
+
+// get /lists/:list_id/export.md
+// A GitHub flavored task list, for pasting into issues and wikis. Lists don't have sections,
+// so todos are grouped into pinned, still to do and done.
+async fn list_markdown(
+    Path(list_id): Path<i32>,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let (name, todos) = db::get_markdown_list(list_id, &pool)
+        .await?
+        .ok_or((StatusCode::NOT_FOUND, String::from("List not found")))?;
+
+    let mut markdown = format!("# {}\n", markdown_text(&name));
+    let sections: [Section; 3] = [
+        ("Pinned", |todo| todo.pinned && !todo.done),
+        ("To do", |todo| !todo.pinned && !todo.done),
+        ("Done", |todo| todo.done),
+    ];
+    for (heading, belongs) in sections {
+        let mut section = todos.iter().filter(|todo| belongs(todo)).peekable();
+        if section.peek().is_none() {
+            continue;
+        }
+        markdown.push_str(&format!("\n## {heading}\n\n"));
+        for todo in section {
+            markdown.push_str(&markdown_item(todo));
+        }
+    }
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        "text/markdown; charset=utf-8"
+            .parse()
+            .expect("should be able to create a Content-Type header"),
+    );
+    Ok((headers, markdown))
+}
+
+// e.g. "- [ ] Buy milk (due 2026-10-17, shop, errands)"
+fn markdown_item(todo: &MarkdownTodo) -> String {
+    let check = if todo.done { 'x' } else { ' ' };
+    let mut details: Vec<String> = todo
+        .due_at
+        .map(|due_at| format!("due {due_at}"))
+        .into_iter()
+        .collect();
+    details.extend(todo.tags.iter().map(|tag| markdown_text(tag)));
+    let description = markdown_text(&todo.description);
+    if details.is_empty() {
+        format!("- [{check}] {description}\n")
+    } else {
+        format!("- [{check}] {description} ({})\n", details.join(", "))
+    }
+}
+
+// One line of text that shows up as typed: markdown's punctuation is escaped and line
+// breaks become spaces
+fn markdown_text(text: &str) -> String {
+    let mut escaped = String::new();
+    for word in text.split_whitespace() {
+        if !escaped.is_empty() {
+            escaped.push(' ');
+        }
+        for c in word.chars() {
+            if "\\`*_[]<>#|~".contains(c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+// get /todos/calendar.ics?token=...
+// Todos are all-day events on their due date: calendars that can show VTODOs are few
+// and far between
+async fn calendar(
+    State(pool): State<PgPool>,
+    Extension(token): Extension<FeedToken>,
+    Query(params): Query<FeedParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    params.check(&token)?;
+    let todos = db::get_calendar_todos(&pool).await?;
+
+    let mut calendar = String::new();
+    ics::line(&mut calendar, "BEGIN:VCALENDAR");
+    ics::line(&mut calendar, "VERSION:2.0");
+    ics::line(&mut calendar, "PRODID:-//todo-axum-htmx//todos//EN");
+    ics::line(&mut calendar, "X-WR-CALNAME:Todos");
+    for todo in &todos {
+        ics_event(&mut calendar, todo);
+    }
+    ics::line(&mut calendar, "END:VCALENDAR");
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        "text/calendar; charset=utf-8"
+            .parse()
+            .expect("should be able to create a Content-Type header"),
+    );
+    Ok((headers, calendar))
+}
+
+// get /feeds/activity.atom?token=...
+// The most recently created and completed todos, newest first
+async fn activity(
+    State(pool): State<PgPool>,
+    Extension(token): Extension<FeedToken>,
+    Query(params): Query<FeedParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    params.check(&token)?;
+    let events = db::get_activity(ACTIVITY_FEED_LENGTH, &pool).await?;
+    let atom = templates::ActivityFeedTemplate::new(events)
+        .render()
+        .map_err(utils::internal_error)?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        "application/atom+xml; charset=utf-8"
+            .parse()
+            .expect("should be able to create a Content-Type header"),
+    );
+    Ok((headers, atom))
+}
+
+const ACTIVITY_FEED_LENGTH: i64 = 50;
+
+fn ics_event(feed: &mut String, todo: &CalendarTodo) {
+    let date = |date: Date| date.format(ics::DATE).unwrap_or_default();
+    let updated_at = todo
+        .updated_at
+        .to_offset(UtcOffset::UTC)
+        .format(ics::TIMESTAMP)
+        .unwrap_or_default();
+    let description = match todo.notes.trim() {
+        "" => format!("List: {}", todo.list_name),
+        notes => format!("List: {}\n\n{notes}", todo.list_name),
+    };
+    ics::line(feed, "BEGIN:VEVENT");
+    ics::line(feed, &format!("UID:todo-{}@todo-axum-htmx", todo.id));
+    ics::line(feed, &format!("DTSTAMP:{updated_at}"));
+    ics::line(feed, &format!("LAST-MODIFIED:{updated_at}"));
+    ics::line(feed, &format!("DTSTART;VALUE=DATE:{}", date(todo.due_at)));
+    if let Some(next_day) = todo.due_at.next_day() {
+        ics::line(feed, &format!("DTEND;VALUE=DATE:{}", date(next_day)));
+    }
+    ics::line(feed, &format!("SUMMARY:{}", ics::text(&todo.description)));
+    ics::line(feed, &format!("DESCRIPTION:{}", ics::text(&description)));
+    ics::line(feed, "END:VEVENT");
+}
@@ -0,0 +1,54 @@
+use askama::Template;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use super::ActivityEvent;
+
+#[derive(Template)]
+#[template(path = "activity_feed.xml")]
+pub struct ActivityFeedTemplate {
+    // when the newest event happened
+    updated: String,
+    entries: Vec<ActivityEntry>,
+}
+
+struct ActivityEntry {
+    // stable across fetches, so feed readers don't show an event twice
+    id: String,
+    title: String,
+    list_id: i32,
+    list_name: String,
+    updated: String,
+}
+
+impl ActivityFeedTemplate {
+    pub fn new(events: Vec<ActivityEvent>) -> Self {
+        let timestamp = |at: OffsetDateTime| at.format(&Rfc3339).unwrap_or_default();
+        let updated = timestamp(
+            events
+                .first()
+                .map_or(OffsetDateTime::UNIX_EPOCH, |event| event.at),
+        );
+        let entries = events
+            .into_iter()
+            .map(|event| {
+                let (kind, verb) = if event.completed {
+                    ("completed", "Completed")
+                } else {
+                    ("created", "Added")
+                };
+                ActivityEntry {
+                    id: format!(
+                        "urn:todo-axum-htmx:todo:{}:{kind}:{}",
+                        event.todo_id,
+                        event.at.unix_timestamp()
+                    ),
+                    title: format!("{verb}: {}", event.description),
+                    list_id: event.list_id,
+                    list_name: event.list_name,
+                    updated: timestamp(event.at),
+                }
+            })
+            .collect();
+        ActivityFeedTemplate { updated, entries }
+    }
+}
@@ -0,0 +1,101 @@
+//! Reading and writing iCalendar (RFC 5545), for the calendar feed and CalDAV.
+
+use time::{format_description::FormatItem, macros::format_description, Date};
+
+pub const DATE: &[FormatItem<'static>] = format_description!("[year][month][day]");
+pub const TIMESTAMP: &[FormatItem<'static>] =
+    format_description!("[year][month][day]T[hour][minute][second]Z");
+
+/// Escape a TEXT value (RFC 5545 3.3.11).
+pub fn text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Undo [`text`].
+pub fn unescape(value: &str) -> String {
+    let mut text = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => text.push('\n'),
+            Some(escaped) => text.push(escaped),
+            None => text.push('\\'),
+        }
+    }
+    text
+}
+
+/// Add a content line. Lines end in CRLF and are folded to at most 75 octets, continuation
+/// lines starting with a space (RFC 5545 3.1).
+pub fn line(ics: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            ics.push_str("\r\n ");
+            width = 1;
+        }
+        ics.push(c);
+        width += c.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+/// A content line such as `DUE;VALUE=DATE:20261016`, the name upper-cased and the
+/// parameters left as they are.
+#[derive(Debug, Clone)]
+pub struct Property {
+    pub name: String,
+    pub params: String,
+    pub value: String,
+}
+
+/// The content lines of a calendar, unfolded. Lines that aren't `name:value` are skipped.
+pub fn parse(ics: &str) -> Vec<Property> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continued), Some(last)) => last.push_str(continued),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines.iter().filter_map(|line| property(line)).collect()
+}
+
+// Parameter values can be quoted and contain colons, e.g. ALTREP="https://..."
+fn property(line: &str) -> Option<Property> {
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ':' if !quoted => Some(i),
+        _ => None,
+    })?;
+    let (name, params) = match line[..colon].split_once(';') {
+        Some((name, params)) => (name, params),
+        None => (&line[..colon], ""),
+    };
+    if name.is_empty() {
+        return None;
+    }
+    Some(Property {
+        name: name.trim().to_ascii_uppercase(),
+        params: params.to_string(),
+        value: line[colon + 1..].to_string(),
+    })
+}
+
+/// The date of a DATE or DATE-TIME value, dropping any time and time zone.
+pub fn parse_date(value: &str) -> Option<Date> {
+    Date::parse(value.get(..8)?, DATE).ok()
+}
@@ -1,3 +1,16 @@
 pub mod app;
+pub mod calendar;
+pub mod config;
+pub mod csrf;
+pub mod custom_fields;
+pub mod export;
+pub mod ics;
+pub mod list_templates;
+pub mod lists;
+pub mod mailer;
+pub mod notifications;
+pub mod openapi;
+pub mod stats;
 pub mod todos;
+pub mod tokens;
 pub mod utils;
@@ -0,0 +1,102 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+
+use crate::utils;
+
+use super::ListTemplate;
+
+pub async fn get_list_templates(pool: &PgPool) -> Result<Vec<ListTemplate>, (StatusCode, String)> {
+    sqlx::query_as!(
+        ListTemplate,
+        r#"select id, name,
+                (select count(*) from list_template_items where template_id = list_templates.id) as "item_count!"
+           from list_templates ORDER BY name"#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// Save the list's open todos, top to bottom, as a new template
+pub async fn create_list_template(
+    name: &str,
+    list_id: i32,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let mut tx = pool.begin().await.map_err(utils::internal_error)?;
+    let template = sqlx::query!(
+        "insert into list_templates (name) values ($1) returning id",
+        name
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(utils::internal_error)?;
+    sqlx::query!(
+        "insert into list_template_items (template_id, description, notes, priority, position)
+         select $1, description, notes, priority, row_number() over (ORDER BY pinned desc, position desc)
+         from todos
+         where list_id = $2 and not done and archived_at is null and deleted_at is null",
+        template.id,
+        list_id,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(utils::internal_error)?;
+    tx.commit().await.map_err(utils::internal_error)?;
+    Ok(())
+}
+
+pub async fn rename_list_template(
+    template_id: i32,
+    name: &str,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        "update list_templates set name = $1 where id = $2",
+        name,
+        template_id
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+pub async fn delete_list_template(
+    template_id: i32,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!("delete from list_templates where id = $1", template_id)
+        .execute(pool)
+        .await
+        .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+// Add the template's todos to the top of the list, in the template's order
+pub async fn apply_list_template(
+    template_id: i32,
+    list_id: i32,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let result = sqlx::query!(
+        "insert into todos (list_id, description, notes, priority, position)
+         select $2, description, notes, priority,
+                (select coalesce(max(position), 0) from todos where list_id = $2)
+                  + row_number() over (ORDER BY position desc)
+         from list_template_items
+         where template_id = $1 and exists (select 1 from lists where id = $2)",
+        template_id,
+        list_id,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("That template has no todos to add"),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,12 @@
+mod db;
+pub mod routes;
+mod templates;
+
+// A saved set of todos, e.g. "weekly groceries", that can be added to any list
+#[derive(Debug, Clone)]
+struct ListTemplate {
+    id: i32,
+    name: String,
+    // how many todos it adds
+    item_count: i64,
+}
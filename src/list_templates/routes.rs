@@ -0,0 +1,117 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::{get, post, put},
+    Router,
+};
+
+use axum_extra::extract::Form;
+
+use serde::Deserialize;
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::utils::HtmlTemplate;
+
+use super::{db, templates};
+
+// list templates routes, nested under /list_templates
+// Each request says which list is open with a `list_id` param
+pub fn routes(pool: &Pool<Postgres>) -> Router {
+    Router::new()
+        .route("/", get(picker).post(create))
+        .route("/:template_id", put(update).delete(destroy))
+        .route("/:template_id/apply", post(apply))
+        .with_state(pool.clone())
+}
+
+#[derive(Deserialize)]
+struct ListIdParams {
+    list_id: i32,
+}
+
+#[derive(Deserialize)]
+struct ListTemplateParams {
+    list_id: i32,
+    name: String,
+}
+
+impl ListTemplateParams {
+    fn name(&self) -> Result<&str, (StatusCode, String)> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                String::from("Template names can't be blank"),
+            ));
+        }
+        Ok(name)
+    }
+}
+
+async fn render_picker(
+    list_id: i32,
+    pool: &PgPool,
+) -> Result<HtmlTemplate<templates::ListTemplatesPickerTemplate>, (StatusCode, String)> {
+    Ok(HtmlTemplate(templates::ListTemplatesPickerTemplate {
+        list_id,
+        list_templates: db::get_list_templates(pool).await?,
+    }))
+}
+
+// get /list_templates
+async fn picker(
+    State(pool): State<PgPool>,
+    Query(params): Query<ListIdParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    render_picker(params.list_id, &pool).await
+}
+
+// post /list_templates
+// Saves the open todos in the list as a new template
+async fn create(
+    State(pool): State<PgPool>,
+    Form(params): Form<ListTemplateParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    db::create_list_template(params.name()?, params.list_id, &pool).await?;
+    render_picker(params.list_id, &pool).await
+}
+
+// put /list_templates/:template_id
+async fn update(
+    Path(template_id): Path<i32>,
+    State(pool): State<PgPool>,
+    Form(params): Form<ListTemplateParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    db::rename_list_template(template_id, params.name()?, &pool).await?;
+    render_picker(params.list_id, &pool).await
+}
+
+// delete /list_templates/:template_id
+async fn destroy(
+    Path(template_id): Path<i32>,
+    State(pool): State<PgPool>,
+    Query(params): Query<ListIdParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    db::delete_list_template(template_id, &pool).await?;
+    render_picker(params.list_id, &pool).await
+}
+
+// post /list_templates/:template_id/apply
+// The todos are added to the list, so the whole page is reloaded to show them
+async fn apply(
+    Path(template_id): Path<i32>,
+    State(pool): State<PgPool>,
+    Form(params): Form<ListIdParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    db::apply_list_template(template_id, params.list_id, &pool).await?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "HX-Refresh",
+        "true"
+            .parse()
+            .expect("should be able to create a HX-Refresh header"),
+    );
+    Ok(headers)
+}
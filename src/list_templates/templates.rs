@@ -0,0 +1,12 @@
+use askama::Template;
+
+use super::ListTemplate;
+
+// The saved templates, with a form for saving the current list as another one
+#[derive(Template)]
+#[template(path = "list_templates_picker.html")]
+pub struct ListTemplatesPickerTemplate {
+    // the list that templates are added to, and saved from
+    pub list_id: i32,
+    pub list_templates: Vec<ListTemplate>,
+}
@@ -0,0 +1,178 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+
+use crate::utils;
+
+use super::{List, SavedFilter, SharedTodo};
+
+pub async fn get_lists(pool: &PgPool) -> Result<Vec<List>, (StatusCode, String)> {
+    sqlx::query_as!(List, "select id, name, share_slug from lists ORDER BY id")
+        .fetch_all(pool)
+        .await
+        .map_err(utils::internal_error)
+}
+
+pub async fn get_list(list_id: i32, pool: &PgPool) -> Result<List, (StatusCode, String)> {
+    sqlx::query_as!(
+        List,
+        "select id, name, share_slug from lists where id = $1",
+        list_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(utils::internal_error)?
+    .ok_or((StatusCode::NOT_FOUND, String::from("List not found")))
+}
+
+pub async fn create_list(name: &str, pool: &PgPool) -> Result<i32, (StatusCode, String)> {
+    let row = sqlx::query!("insert into lists (name) values ($1) returning id", name)
+        .fetch_one(pool)
+        .await
+        .map_err(utils::internal_error)?;
+    Ok(row.id)
+}
+
+pub async fn rename_list(
+    list_id: i32,
+    name: &str,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!("update lists set name = $1 where id = $2", name, list_id)
+        .execute(pool)
+        .await
+        .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+// Includes archived todos, which are deleted with the list
+pub async fn count_todos(list_id: i32, pool: &PgPool) -> Result<i64, (StatusCode, String)> {
+    let row = sqlx::query!(
+        r#"select count(*) as "count!" from todos where list_id = $1 and deleted_at is null"#,
+        list_id
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(row.count)
+}
+
+// Deletes the list along with its todos, unless it is the only list left
+pub async fn delete_list(list_id: i32, pool: &PgPool) -> Result<(), (StatusCode, String)> {
+    let result = sqlx::query!(
+        "delete from lists where id = $1 and (select count(*) from lists) > 1",
+        list_id
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("Can't delete the only list"),
+        ));
+    }
+    Ok(())
+}
+
+// Keeps the slug the list already has, so links that were handed out keep working
+pub async fn share_list(list_id: i32, pool: &PgPool) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        "update lists set share_slug = coalesce(share_slug, $2) where id = $1",
+        list_id,
+        utils::random_token(SHARE_SLUG_LENGTH),
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+const SHARE_SLUG_LENGTH: usize = 24;
+
+pub async fn unshare_list(list_id: i32, pool: &PgPool) -> Result<(), (StatusCode, String)> {
+    sqlx::query!("update lists set share_slug = null where id = $1", list_id)
+        .execute(pool)
+        .await
+        .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+pub async fn get_shared_list(slug: &str, pool: &PgPool) -> Result<List, (StatusCode, String)> {
+    sqlx::query_as!(
+        List,
+        "select id, name, share_slug from lists where share_slug = $1",
+        slug
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(utils::internal_error)?
+    .ok_or((StatusCode::NOT_FOUND, String::from("List not found")))
+}
+
+// The list's current todos, in the order the owner sees them unfiltered
+pub async fn get_shared_todos(
+    list_id: i32,
+    pool: &PgPool,
+) -> Result<Vec<SharedTodo>, (StatusCode, String)> {
+    sqlx::query_as!(
+        SharedTodo,
+        r#"select description, done, due_at, notes,
+                array(select tags.name from todo_tags join tags on tags.id = todo_tags.tag_id
+                      where todo_tags.todo_id = todos.id order by tags.name) as "tags!"
+           from todos
+           where list_id = $1 and archived_at is null and deleted_at is null
+           ORDER BY pinned desc, position desc"#,
+        list_id,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+pub async fn get_saved_filters(
+    list_id: i32,
+    pool: &PgPool,
+) -> Result<Vec<SavedFilter>, (StatusCode, String)> {
+    sqlx::query_as!(
+        SavedFilter,
+        "select id, name, query from saved_filters where list_id = $1 ORDER BY lower(name), id",
+        list_id,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+pub async fn create_saved_filter(
+    list_id: i32,
+    name: &str,
+    query: &str,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        "insert into saved_filters (list_id, name, query) values ($1, $2, $3)",
+        list_id,
+        name,
+        query,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+pub async fn delete_saved_filter(
+    list_id: i32,
+    saved_filter_id: i32,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        "delete from saved_filters where id = $1 and list_id = $2",
+        saved_filter_id,
+        list_id,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use time::Date;
+
+mod db;
+pub mod routes;
+mod templates;
+
+#[derive(Debug, Clone)]
+struct List {
+    id: i32,
+    name: String,
+    // set while the list is shared, see /share/:slug
+    share_slug: Option<String>,
+}
+
+// A todo as shown on a shared list, which can't be changed from there
+#[derive(Debug, Clone)]
+struct SharedTodo {
+    description: String,
+    done: bool,
+    due_at: Option<Date>,
+    notes: String,
+    tags: Vec<String>,
+}
+
+// A filter and sort of a list's todos that was given a name, shown under the list in the sidebar
+#[derive(Debug, Clone)]
+struct SavedFilter {
+    id: i32,
+    name: String,
+    // e.g. "status=active&tag=home", as in /lists/:list_id/todos?status=active&tag=home
+    query: String,
+}
@@ -0,0 +1,214 @@
+use axum::{
+    extract::{Path, RawQuery, State},
+    http::{HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Redirect},
+    routing::{delete, get, post},
+    Router,
+};
+
+use axum_extra::extract::Form;
+
+use serde::Deserialize;
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::csrf::CsrfToken;
+use crate::utils::{ConfirmDialogTemplate, HtmlTemplate};
+
+use super::{db, templates};
+
+// lists routes, nested under /lists
+// The todos in each list live under /lists/:list_id/todos
+pub fn routes(pool: &Pool<Postgres>) -> Router {
+    Router::new()
+        .route("/", post(create))
+        .route("/:list_id", get(show).put(update).delete(destroy))
+        .route("/:list_id/confirm_delete", get(confirm_destroy))
+        .route("/:list_id/share", post(share).delete(unshare))
+        .route("/:list_id/saved_filters", post(create_saved_filter))
+        .route("/:list_id/saved_filters/:id", delete(destroy_saved_filter))
+        .with_state(pool.clone())
+}
+
+// get /
+// Show the first list, creating one if there are none
+pub async fn index(State(pool): State<PgPool>) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let lists = db::get_lists(&pool).await?;
+    let list_id = match lists.first() {
+        Some(list) => list.id,
+        None => db::create_list("Todos", &pool).await?,
+    };
+    Ok(Redirect::to(&format!("/lists/{list_id}")))
+}
+
+// get /lists/:list_id
+// Any query params are filters for the todos, which are loaded separately
+async fn show(
+    Path(list_id): Path<i32>,
+    State(pool): State<PgPool>,
+    RawQuery(query): RawQuery,
+    CsrfToken(csrf_token): CsrfToken,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let list = db::get_list(list_id, &pool).await?;
+    let lists = db::get_lists(&pool).await?;
+    let saved_filters = db::get_saved_filters(list_id, &pool).await?;
+    let template = templates::render_show(
+        csrf_token,
+        list,
+        lists,
+        saved_filters,
+        query.unwrap_or_default(),
+    );
+    Ok(HtmlTemplate(template))
+}
+
+#[derive(Deserialize)]
+struct ListParams {
+    name: String,
+}
+
+impl ListParams {
+    fn name(&self) -> Result<&str, (StatusCode, String)> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                String::from("List names can't be blank"),
+            ));
+        }
+        Ok(name)
+    }
+}
+
+// post /lists
+async fn create(
+    State(pool): State<PgPool>,
+    Form(params): Form<ListParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let list_id = db::create_list(params.name()?, &pool).await?;
+    Ok(hx_redirect(&format!("/lists/{list_id}")))
+}
+
+// put /lists/:list_id
+async fn update(
+    Path(list_id): Path<i32>,
+    State(pool): State<PgPool>,
+    Form(params): Form<ListParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    db::rename_list(list_id, params.name()?, &pool).await?;
+    render_sidebar(list_id, &pool).await
+}
+
+// get /lists/:list_id/confirm_delete
+async fn confirm_destroy(
+    Path(list_id): Path<i32>,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let list = db::get_list(list_id, &pool).await?;
+    let message = match db::count_todos(list_id, &pool).await? {
+        0 => format!("Delete {}?", list.name),
+        1 => format!("Delete {} and its todo?", list.name),
+        count => format!("Delete {} and its {count} todos?", list.name),
+    };
+    Ok(HtmlTemplate(ConfirmDialogTemplate {
+        message,
+        confirm_label: String::from("Delete list"),
+        method: "delete",
+        url: format!("/lists/{list_id}"),
+        target: String::from("#lists"),
+    }))
+}
+
+// delete /lists/:list_id
+async fn destroy(
+    Path(list_id): Path<i32>,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    db::delete_list(list_id, &pool).await?;
+    Ok(hx_redirect("/"))
+}
+
+// post /lists/:list_id/share
+async fn share(
+    Path(list_id): Path<i32>,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    db::share_list(list_id, &pool).await?;
+    let list = db::get_list(list_id, &pool).await?;
+    Ok(HtmlTemplate(templates::ListsShareTemplate { list }))
+}
+
+// delete /lists/:list_id/share
+// The old link stops working, sharing again hands out a new one
+async fn unshare(
+    Path(list_id): Path<i32>,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    db::unshare_list(list_id, &pool).await?;
+    let list = db::get_list(list_id, &pool).await?;
+    Ok(HtmlTemplate(templates::ListsShareTemplate { list }))
+}
+
+// get /share/:slug
+// Anyone with the link can see the list, but nothing on the page can change it
+pub async fn shared(
+    Path(slug): Path<String>,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let list = db::get_shared_list(&slug, &pool).await?;
+    let todos = db::get_shared_todos(list.id, &pool).await?;
+    Ok(HtmlTemplate(templates::render_shared(list, todos)))
+}
+
+// post /lists/:list_id/saved_filters
+// Saves the filter and sort the todos are shown with right now, which is in the address bar
+async fn create_saved_filter(
+    Path(list_id): Path<i32>,
+    State(pool): State<PgPool>,
+    headers: HeaderMap,
+    Form(params): Form<ListParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let name = params.name()?;
+    let query = headers
+        .get("HX-Current-URL")
+        .and_then(|url| url.to_str().ok())
+        .and_then(|url| url.parse::<Uri>().ok())
+        .and_then(|uri| uri.query().map(String::from))
+        .filter(|query| !query.is_empty())
+        .ok_or((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("Filter or sort the todos before saving a view"),
+        ))?;
+    db::create_saved_filter(list_id, name, &query, &pool).await?;
+    render_sidebar(list_id, &pool).await
+}
+
+// delete /lists/:list_id/saved_filters/:id
+async fn destroy_saved_filter(
+    Path((list_id, saved_filter_id)): Path<(i32, i32)>,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    db::delete_saved_filter(list_id, saved_filter_id, &pool).await?;
+    render_sidebar(list_id, &pool).await
+}
+
+async fn render_sidebar(
+    list_id: i32,
+    pool: &PgPool,
+) -> Result<HtmlTemplate<templates::ListsSidebarTemplate>, (StatusCode, String)> {
+    Ok(HtmlTemplate(templates::ListsSidebarTemplate {
+        list: db::get_list(list_id, pool).await?,
+        lists: db::get_lists(pool).await?,
+        saved_filters: db::get_saved_filters(list_id, pool).await?,
+    }))
+}
+
+// Send the browser to another page once htmx receives the response
+fn hx_redirect(path: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "HX-Redirect",
+        path.parse()
+            .expect("should be able to create a HX-Redirect header"),
+    );
+    headers
+}
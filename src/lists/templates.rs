@@ -0,0 +1,84 @@
+use askama::Template;
+
+use crate::utils;
+
+use super::{List, SavedFilter, SharedTodo};
+
+#[derive(Template)]
+#[template(path = "base.html")]
+struct BaseTemplate {}
+
+#[derive(Template)]
+#[template(path = "lists_show.html")]
+pub struct ListsShowTemplate<'a> {
+    _parent: &'a BaseTemplate,
+    csrf_token: String,
+    list: List,
+    lists: Vec<List>,
+    saved_filters: Vec<SavedFilter>,
+    // passed on to the request that loads the todos
+    query: String,
+    // tells this page's own changes apart from everyone else's, see notifications
+    tab_id: String,
+}
+
+const TAB_ID_LENGTH: usize = 16;
+
+pub fn render_show<'a>(
+    csrf_token: String,
+    list: List,
+    lists: Vec<List>,
+    saved_filters: Vec<SavedFilter>,
+    query: String,
+) -> ListsShowTemplate<'a> {
+    ListsShowTemplate {
+        _parent: &BaseTemplate {},
+        csrf_token,
+        list,
+        lists,
+        saved_filters,
+        query,
+        tab_id: utils::random_token(TAB_ID_LENGTH),
+    }
+}
+
+// The sidebar on its own, with `list` as the current list
+#[derive(Template)]
+#[template(path = "lists_sidebar.html")]
+pub struct ListsSidebarTemplate {
+    pub list: List,
+    pub lists: Vec<List>,
+    // the current list's
+    pub saved_filters: Vec<SavedFilter>,
+}
+
+// Whether the list is shared, with the link if it is
+#[derive(Template)]
+#[template(path = "lists_share.html")]
+pub struct ListsShareTemplate {
+    pub list: List,
+}
+
+// A read-only page for people the list has been shared with
+#[derive(Template)]
+#[template(path = "lists_shared.html")]
+pub struct ListsSharedTemplate<'a> {
+    _parent: &'a BaseTemplate,
+    list: List,
+    todos: Vec<SharedTodo>,
+}
+
+pub fn render_shared<'a>(list: List, todos: Vec<SharedTodo>) -> ListsSharedTemplate<'a> {
+    ListsSharedTemplate {
+        _parent: &BaseTemplate {},
+        list,
+        todos,
+    }
+}
+
+impl SharedTodo {
+    // notes rendered from markdown and sanitized
+    fn notes_html(&self) -> String {
+        utils::render_markdown(&self.notes)
+    }
+}
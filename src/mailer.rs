@@ -0,0 +1,123 @@
+use std::fmt;
+
+use askama::Template;
+use lettre::{
+    address::AddressError,
+    message::{header::ContentType, Mailbox},
+    transport::smtp::{self, authentication::Credentials},
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+use crate::config::MailerConfig;
+
+/// A plain-text email waiting to be sent
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+impl Email {
+    /// Build an email whose body is the rendered `template`
+    pub fn from_template<T>(
+        to: impl Into<String>,
+        subject: impl Into<String>,
+        template: &T,
+    ) -> askama::Result<Self>
+    where
+        T: Template,
+    {
+        Ok(Email {
+            to: to.into(),
+            subject: subject.into(),
+            body: template.render()?,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum MailerError {
+    Address(AddressError),
+    Message(lettre::error::Error),
+    Smtp(smtp::Error),
+}
+
+impl fmt::Display for MailerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MailerError::Address(err) => write!(f, "invalid email address: {err}"),
+            MailerError::Message(err) => write!(f, "could not build email: {err}"),
+            MailerError::Smtp(err) => write!(f, "could not send email: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for MailerError {}
+
+impl From<AddressError> for MailerError {
+    fn from(err: AddressError) -> Self {
+        MailerError::Address(err)
+    }
+}
+
+impl From<lettre::error::Error> for MailerError {
+    fn from(err: lettre::error::Error) -> Self {
+        MailerError::Message(err)
+    }
+}
+
+impl From<smtp::Error> for MailerError {
+    fn from(err: smtp::Error) -> Self {
+        MailerError::Smtp(err)
+    }
+}
+
+#[derive(Clone)]
+pub enum Mailer {
+    Smtp {
+        transport: AsyncSmtpTransport<Tokio1Executor>,
+        from: Mailbox,
+    },
+    // Used in development: emails are written to the log instead of being sent
+    Log,
+}
+
+impl Mailer {
+    pub fn from_config(config: &MailerConfig) -> Result<Self, MailerError> {
+        let Some(host) = &config.smtp_host else {
+            return Ok(Mailer::Log);
+        };
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::relay(host)?.port(config.smtp_port);
+        if let (Some(username), Some(password)) = (&config.smtp_username, &config.smtp_password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        Ok(Mailer::Smtp {
+            transport: builder.build(),
+            from: config.from.parse()?,
+        })
+    }
+
+    pub async fn send(&self, email: Email) -> Result<(), MailerError> {
+        match self {
+            Mailer::Smtp { transport, from } => {
+                let message = Message::builder()
+                    .from(from.clone())
+                    .to(email.to.parse()?)
+                    .subject(email.subject)
+                    .header(ContentType::TEXT_PLAIN)
+                    .body(email.body)?;
+                transport.send(message).await?;
+            }
+            Mailer::Log => {
+                tracing::info!(
+                    to = %email.to,
+                    subject = %email.subject,
+                    "email not sent (SMTP_HOST is not set):\n{}",
+                    email.body
+                );
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use std::convert::Infallible;
+
+use askama::Template;
+use axum::{
+    extract::Query,
+    response::sse::{Event, KeepAlive, Sse},
+    routing::get,
+    Extension, Router,
+};
+use futures::{future, stream, Stream, StreamExt};
+use serde::Deserialize;
+use tokio::sync::broadcast;
+
+// How many notifications a slow page can fall behind by before it misses some
+const CAPACITY: usize = 64;
+
+/// Pushes notifications to the pages that are open, over /events. Handlers can take an
+/// `Extension<Notifier>`.
+#[derive(Clone)]
+pub struct Notifier {
+    sender: broadcast::Sender<Notification>,
+}
+
+/// A toast for the pages it concerns
+#[derive(Debug, Clone)]
+pub struct Notification {
+    html: String,
+    // only pages showing this list get it, any of the app's own pages when None
+    list_id: Option<i32>,
+    // the tab whose request caused it, which is already up to date
+    origin: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "notification.html")]
+struct NotificationTemplate<'a> {
+    message: &'a str,
+    reload: Option<String>,
+}
+
+impl Notification {
+    /// Something changed in a list, which the other pages showing it don't show yet
+    pub fn list_changed(list_id: i32, origin: Option<String>) -> Self {
+        let template = NotificationTemplate {
+            message: "This list was just changed somewhere else.",
+            reload: Some(format!("/lists/{list_id}")),
+        };
+        Notification {
+            html: template.render().unwrap_or_default(),
+            list_id: Some(list_id),
+            origin,
+        }
+    }
+
+    /// A message for every open page of the app
+    pub fn everyone(message: &str) -> Self {
+        let template = NotificationTemplate {
+            message,
+            reload: None,
+        };
+        Notification {
+            html: template.render().unwrap_or_default(),
+            list_id: None,
+            origin: None,
+        }
+    }
+
+    fn is_for(&self, subscriber: &EventsParams) -> bool {
+        match self.list_id {
+            Some(list_id) => {
+                subscriber.list_id == Some(list_id)
+                    && (self.origin.is_none() || self.origin != subscriber.tab)
+            }
+            // shared lists subscribe without a tab, they only hear about their own list
+            None => subscriber.tab.is_some(),
+        }
+    }
+}
+
+impl Notifier {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        Notifier { sender }
+    }
+
+    pub fn send(&self, notification: Notification) {
+        // nobody listening is fine, there's just no page open
+        let _ = self.sender.send(notification);
+    }
+
+    /// The notifications sent from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<Notification> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// notification routes
+pub fn routes(notifier: Notifier) -> Router {
+    Router::new()
+        .route("/events", get(events))
+        .layer(Extension(notifier))
+}
+
+// What the page is showing, see Notification::is_for
+#[derive(Deserialize)]
+struct EventsParams {
+    list_id: Option<i32>,
+    // sent along as X-Tab-Id by the page's own requests
+    tab: Option<String>,
+}
+
+// get /events
+// The htmx sse extension swaps each `notification` event into the page
+async fn events(
+    Extension(notifier): Extension<Notifier>,
+    Query(params): Query<EventsParams>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = notifier.subscribe();
+    let notifications = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(notification) => return Some((notification, receiver)),
+                // too far behind, carry on with the newest ones
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    let events = notifications
+        .filter(move |notification| future::ready(notification.is_for(&params)))
+        .map(|notification| {
+            Ok(Event::default()
+                .event("notification")
+                .data(notification.html))
+        });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
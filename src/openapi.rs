@@ -0,0 +1,84 @@
+use askama::Template;
+use axum::{response::IntoResponse, routing::get, Json, Router};
+use utoipa::{
+    openapi::{
+        security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+        OpenApi as Spec,
+    },
+    Modify, OpenApi,
+};
+
+use crate::{todos, utils::HtmlTemplate};
+
+// The JSON API, described from the annotations on its handlers and the types they take and
+// return, so that the description changes along with them
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Todos",
+        license(name = "MIT"),
+        description = "Send `Accept: application/json` to get JSON instead of HTML, and a personal access token from /tokens as a bearer token."
+    ),
+    paths(
+        todos::routes::list,
+        todos::routes::create,
+        todos::routes::update,
+        todos::routes::destroy,
+    ),
+    modifiers(&BearerToken),
+    security(("token" = [])),
+)]
+struct ApiDoc;
+
+// Personal access tokens, sent as `Authorization: Bearer <token>`, see tokens::check_scope
+struct BearerToken;
+
+impl Modify for BearerToken {
+    fn modify(&self, openapi: &mut Spec) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+#[derive(Template)]
+#[template(path = "api_docs.html")]
+struct ApiDocsTemplate;
+
+// api docs routes
+pub fn routes() -> Router {
+    Router::new()
+        .route("/api/openapi.json", get(openapi_json))
+        .route("/api/docs", get(docs))
+}
+
+// get /api/openapi.json
+async fn openapi_json() -> impl IntoResponse {
+    Json(ApiDoc::openapi())
+}
+
+// get /api/docs
+// Swagger UI, for trying the API out in the browser
+async fn docs() -> impl IntoResponse {
+    HtmlTemplate(ApiDocsTemplate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_the_todos_routes() {
+        let spec = ApiDoc::openapi();
+        let paths: Vec<&str> = spec.paths.paths.keys().map(String::as_str).collect();
+        assert_eq!(
+            paths,
+            ["/lists/{list_id}/todos", "/lists/{list_id}/todos/{id}"]
+        );
+        let schemas = spec.components.unwrap().schemas;
+        assert!(schemas.contains_key("TodoJson"));
+        assert!(schemas.contains_key("JsonError"));
+    }
+}
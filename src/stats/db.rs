@@ -0,0 +1,169 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+use time::Date;
+
+use crate::utils;
+
+use super::{CompletedCount, ReviewTodo, TagCount};
+
+// Completions on each day from `from` to `to`, days without any included
+pub async fn get_completed_per_day(
+    from: Date,
+    to: Date,
+    pool: &PgPool,
+) -> Result<Vec<CompletedCount>, (StatusCode, String)> {
+    sqlx::query_as!(
+        CompletedCount,
+        r#"select day::date as "day!", count(todos.id) as "completed!"
+           from generate_series($1::date::timestamp, $2::date::timestamp, interval '1 day') as day
+           left join todos on (todos.completed_at at time zone 'utc')::date = day::date
+             and todos.deleted_at is null
+           group by day ORDER BY day"#,
+        from,
+        to,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// Completions in each week (starting on Monday) from the week of `from` to the week of `to`
+pub async fn get_completed_per_week(
+    from: Date,
+    to: Date,
+    pool: &PgPool,
+) -> Result<Vec<CompletedCount>, (StatusCode, String)> {
+    sqlx::query_as!(
+        CompletedCount,
+        r#"select week::date as "day!", count(todos.id) as "completed!"
+           from generate_series(date_trunc('week', $1::date::timestamp), date_trunc('week', $2::date::timestamp), interval '1 week') as week
+           left join todos on date_trunc('week', todos.completed_at at time zone 'utc') = week
+             and todos.deleted_at is null
+           group by week ORDER BY week"#,
+        from,
+        to,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// The average number of seconds between adding a todo and completing it, None until one is completed
+pub async fn get_average_completion_seconds(
+    pool: &PgPool,
+) -> Result<Option<f64>, (StatusCode, String)> {
+    sqlx::query_scalar!(
+        "select extract(epoch from avg(completed_at - created_at))::float8
+         from todos where completed_at is not null and deleted_at is null",
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// The tags on the most todos, busiest first
+pub async fn get_busiest_tags(
+    limit: i64,
+    pool: &PgPool,
+) -> Result<Vec<TagCount>, (StatusCode, String)> {
+    sqlx::query_as!(
+        TagCount,
+        r#"select tags.name, count(todos.id) as "todos!", count(todos.id) filter (where todos.done) as "completed!"
+           from tags
+           join todo_tags on todo_tags.tag_id = tags.id
+           join todos on todos.id = todo_tags.todo_id and todos.deleted_at is null
+           group by tags.id ORDER BY 2 desc, tags.name
+           limit $1"#,
+        limit,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// Todos completed from `from` to `to`, in the order they were completed
+pub async fn get_completed_between(
+    from: Date,
+    to: Date,
+    pool: &PgPool,
+) -> Result<Vec<ReviewTodo>, (StatusCode, String)> {
+    sqlx::query_as!(
+        ReviewTodo,
+        r#"select lists.name as list_name, todos.description, todos.due_at
+           from todos join lists on lists.id = todos.list_id
+           where todos.done and todos.deleted_at is null
+             and (todos.completed_at at time zone 'utc')::date between $1 and $2
+           ORDER BY todos.completed_at"#,
+        from,
+        to,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// Pending todos that came due from `from` up to, but not including, `to`
+pub async fn get_slipped(
+    from: Date,
+    to: Date,
+    pool: &PgPool,
+) -> Result<Vec<ReviewTodo>, (StatusCode, String)> {
+    sqlx::query_as!(
+        ReviewTodo,
+        r#"select lists.name as list_name, todos.description, todos.due_at
+           from todos join lists on lists.id = todos.list_id
+           where not todos.done and todos.archived_at is null and todos.deleted_at is null
+             and todos.due_at >= $1 and todos.due_at < $2
+           ORDER BY todos.due_at, todos.id"#,
+        from,
+        to,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// Pending todos due from `from` up to and including `to`
+pub async fn get_due_between(
+    from: Date,
+    to: Date,
+    pool: &PgPool,
+) -> Result<Vec<ReviewTodo>, (StatusCode, String)> {
+    sqlx::query_as!(
+        ReviewTodo,
+        r#"select lists.name as list_name, todos.description, todos.due_at
+           from todos join lists on lists.id = todos.list_id
+           where not todos.done and todos.archived_at is null and todos.deleted_at is null
+             and todos.due_at between $1 and $2
+           ORDER BY todos.due_at, todos.id"#,
+        from,
+        to,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+pub async fn review_sent(week_ending: Date, pool: &PgPool) -> Result<bool, (StatusCode, String)> {
+    sqlx::query_scalar!(
+        r#"select exists(select 1 from weekly_reviews where week_ending = $1) as "sent!""#,
+        week_ending,
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+pub async fn mark_review_sent(
+    week_ending: Date,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        "insert into weekly_reviews (week_ending) values ($1) on conflict do nothing",
+        week_ending,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
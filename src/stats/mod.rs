@@ -0,0 +1,30 @@
+use time::Date;
+
+mod db;
+pub mod review;
+pub mod routes;
+mod templates;
+
+// How many todos were completed on a day, or in the week starting that day
+#[derive(Debug, Clone)]
+struct CompletedCount {
+    day: Date,
+    completed: i64,
+}
+
+// A tag with how many todos carry it
+#[derive(Debug, Clone)]
+struct TagCount {
+    name: String,
+    todos: i64,
+    completed: i64,
+}
+
+// A todo in the weekly review email, see review
+#[derive(Debug, Clone)]
+struct ReviewTodo {
+    list_name: String,
+    description: String,
+    // always there for the slipped and upcoming ones
+    due_at: Option<Date>,
+}
@@ -0,0 +1,80 @@
+use askama::Template;
+use sqlx::PgPool;
+use time::{Date, Duration, OffsetDateTime, Weekday};
+
+use crate::{
+    config::ReviewConfig,
+    mailer::{Email, Mailer},
+};
+
+use super::{db, ReviewTodo};
+
+#[derive(Template)]
+#[template(path = "weekly_review_email.txt")]
+struct WeeklyReviewEmailTemplate {
+    from: Date,
+    to: Date,
+    completed: Vec<ReviewTodo>,
+    slipped: Vec<ReviewTodo>,
+    upcoming: Vec<ReviewTodo>,
+}
+
+/// Email WEEKLY_REVIEW_EMAIL a look back at the week every Sunday: what was completed, what
+/// slipped past its due date and what is due in the week ahead.
+pub fn spawn(config: ReviewConfig, mailer: Mailer, pool: PgPool) {
+    let Some(to) = config.to else {
+        tracing::info!("WEEKLY_REVIEW_EMAIL is not set, not sending weekly reviews");
+        return;
+    };
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.interval);
+        loop {
+            interval.tick().await;
+            let today = OffsetDateTime::now_utc().date();
+            if today.weekday() != Weekday::Sunday {
+                continue;
+            }
+            if let Err(err) = send_review(&to, today, &mailer, &pool).await {
+                tracing::error!(%err, "could not send the weekly review");
+            }
+        }
+    });
+}
+
+// The week runs from Monday to `sunday`
+async fn send_review(to: &str, sunday: Date, mailer: &Mailer, pool: &PgPool) -> Result<(), String> {
+    if db::review_sent(sunday, pool)
+        .await
+        .map_err(|(_, err)| err)?
+    {
+        return Ok(());
+    }
+    let monday = sunday - Duration::days(6);
+    let completed = db::get_completed_between(monday, sunday, pool)
+        .await
+        .map_err(|(_, err)| err)?;
+    let slipped = db::get_slipped(monday, sunday, pool)
+        .await
+        .map_err(|(_, err)| err)?;
+    let upcoming = db::get_due_between(sunday, sunday + Duration::days(7), pool)
+        .await
+        .map_err(|(_, err)| err)?;
+
+    let subject = format!("Your week: {} completed", completed.len());
+    let template = WeeklyReviewEmailTemplate {
+        from: monday,
+        to: sunday,
+        completed,
+        slipped,
+        upcoming,
+    };
+    let email = Email::from_template(to, subject, &template).map_err(|err| err.to_string())?;
+    mailer.send(email).await.map_err(|err| err.to_string())?;
+
+    // Only recorded once the email is out, so a failed send is retried on the next tick
+    db::mark_review_sent(sunday, pool)
+        .await
+        .map_err(|(_, err)| err)?;
+    tracing::info!(%sunday, "sent the weekly review");
+    Ok(())
+}
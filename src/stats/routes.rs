@@ -0,0 +1,39 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::get, Router};
+
+use sqlx::{PgPool, Pool, Postgres};
+use time::{Duration, OffsetDateTime};
+
+use crate::csrf::CsrfToken;
+use crate::utils::HtmlTemplate;
+
+use super::{db, templates};
+
+// How far back the charts go
+const DAYS: i64 = 30;
+const WEEKS: i64 = 12;
+const BUSIEST_TAGS: i64 = 10;
+
+// stats routes, nested under /stats
+pub fn routes(pool: &Pool<Postgres>) -> Router {
+    Router::new().route("/", get(show)).with_state(pool.clone())
+}
+
+// get /stats
+async fn show(
+    State(pool): State<PgPool>,
+    CsrfToken(csrf_token): CsrfToken,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let today = OffsetDateTime::now_utc().date();
+    let per_day = db::get_completed_per_day(today - Duration::days(DAYS - 1), today, &pool).await?;
+    let per_week =
+        db::get_completed_per_week(today - Duration::weeks(WEEKS - 1), today, &pool).await?;
+    let average_seconds = db::get_average_completion_seconds(&pool).await?;
+    let tags = db::get_busiest_tags(BUSIEST_TAGS, &pool).await?;
+    Ok(HtmlTemplate(templates::StatsShowTemplate::new(
+        csrf_token,
+        per_day,
+        per_week,
+        average_seconds,
+        tags,
+    )))
+}
@@ -0,0 +1,116 @@
+use askama::Template;
+use time::Date;
+
+use super::{CompletedCount, TagCount};
+
+#[derive(Template)]
+#[template(path = "base.html")]
+struct BaseTemplate {}
+
+// Charts and tables about what's been done
+#[derive(Template)]
+#[template(path = "stats_show.html")]
+pub struct StatsShowTemplate<'a> {
+    _parent: &'a BaseTemplate,
+    csrf_token: String,
+    per_day: BarChart,
+    per_week: BarChart,
+    // e.g. "2 days 3 hours", None until a todo has been completed
+    average_completion: Option<String>,
+    tags: Vec<TagCount>,
+}
+
+impl<'a> StatsShowTemplate<'a> {
+    pub fn new(
+        csrf_token: String,
+        per_day: Vec<CompletedCount>,
+        per_week: Vec<CompletedCount>,
+        average_seconds: Option<f64>,
+        tags: Vec<TagCount>,
+    ) -> Self {
+        StatsShowTemplate {
+            _parent: &BaseTemplate {},
+            csrf_token,
+            per_day: BarChart::new(per_day),
+            per_week: BarChart::new(per_week),
+            average_completion: average_seconds.map(|seconds| format_duration(seconds as i64)),
+            tags,
+        }
+    }
+}
+
+// Chart dimensions, in SVG user units
+const CHART_WIDTH: i64 = 600;
+const CHART_HEIGHT: i64 = 120;
+
+// A bar per count, scaled so the highest bar fills the chart
+struct BarChart {
+    width: i64,
+    height: i64,
+    bars: Vec<Bar>,
+    // the counts again, for the table under the chart
+    counts: Vec<CompletedCount>,
+}
+
+struct Bar {
+    x: i64,
+    y: i64,
+    width: i64,
+    height: i64,
+    day: Date,
+    completed: i64,
+}
+
+impl BarChart {
+    fn new(counts: Vec<CompletedCount>) -> Self {
+        let max = counts
+            .iter()
+            .map(|count| count.completed)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let slot = CHART_WIDTH / (counts.len() as i64).max(1);
+        let bars = counts
+            .iter()
+            .enumerate()
+            .map(|(i, count)| {
+                let height = count.completed * CHART_HEIGHT / max;
+                Bar {
+                    x: i as i64 * slot,
+                    y: CHART_HEIGHT - height,
+                    // leave a gap between bars
+                    width: (slot - 2).max(1),
+                    height,
+                    day: count.day,
+                    completed: count.completed,
+                }
+            })
+            .collect();
+        BarChart {
+            width: CHART_WIDTH,
+            height: CHART_HEIGHT,
+            bars,
+            counts,
+        }
+    }
+}
+
+// The two largest units, e.g. "3 days 4 hours" or "12 minutes"
+fn format_duration(seconds: i64) -> String {
+    let plural = |n: i64, unit: &str| {
+        if n == 1 {
+            format!("1 {unit}")
+        } else {
+            format!("{n} {unit}s")
+        }
+    };
+    let (days, hours, minutes) = (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60);
+    match (days, hours, minutes) {
+        (0, 0, 0) => String::from("less than a minute"),
+        (0, 0, minutes) => plural(minutes, "minute"),
+        (0, hours, 0) => plural(hours, "hour"),
+        (0, hours, minutes) => format!("{} {}", plural(hours, "hour"), plural(minutes, "minute")),
+        (days, 0, _) => plural(days, "day"),
+        (days, hours, _) => format!("{} {}", plural(days, "day"), plural(hours, "hour")),
+    }
+}
@@ -0,0 +1,36 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+
+use sqlx::{PgPool, Pool, Postgres};
+use time::OffsetDateTime;
+
+use crate::csrf::CsrfToken;
+use crate::utils::HtmlTemplate;
+
+use super::templates;
+
+// agenda routes, nested under /today
+// The agenda gathers what's overdue and what's due today from every list
+pub fn routes(pool: &Pool<Postgres>) -> Router {
+    Router::new().route("/", get(show)).with_state(pool.clone())
+}
+
+// get /today
+// htmx requests get just the agenda, a full page load gets the whole page
+async fn show(
+    State(pool): State<PgPool>,
+    headers: HeaderMap,
+    CsrfToken(csrf_token): CsrfToken,
+) -> Result<Response, (StatusCode, String)> {
+    let today = OffsetDateTime::now_utc().date();
+    let agenda = templates::render_agenda_inner(today, &pool).await?;
+    if headers.contains_key("HX-Request") {
+        return Ok(HtmlTemplate(agenda).into_response());
+    }
+    Ok(HtmlTemplate(templates::render_agenda(csrf_token, agenda)).into_response())
+}
@@ -0,0 +1,58 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Router,
+};
+
+use axum_extra::extract::Form;
+
+use serde::Deserialize;
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::csrf::CsrfToken;
+use crate::utils::HtmlTemplate;
+
+use super::{db, templates};
+
+// board routes, nested under /board
+// The board shows every list as a column of cards that can be dragged within and between lists
+pub fn routes(pool: &Pool<Postgres>) -> Router {
+    Router::new()
+        .route("/", get(show))
+        .route("/move", post(move_card))
+        .with_state(pool.clone())
+}
+
+// get /board
+async fn show(
+    State(pool): State<PgPool>,
+    CsrfToken(csrf_token): CsrfToken,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let columns = templates::render_board_columns(&pool).await?;
+    Ok(HtmlTemplate(templates::render_board(csrf_token, columns)))
+}
+
+#[derive(Deserialize)]
+struct BoardMoveParams {
+    todo_id: i32,
+    // the column the card was dropped in
+    list_id: i32,
+    // the ids of that column's cards, top to bottom, after the drop
+    #[serde(default)]
+    order: Vec<i32>,
+}
+
+// post /board/move
+async fn move_card(
+    State(pool): State<PgPool>,
+    Form(params): Form<BoardMoveParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let from_list_id = db::get_list_id(params.todo_id, &pool).await?;
+    if from_list_id != params.list_id {
+        db::move_todos(from_list_id, &[params.todo_id], params.list_id, &pool).await?;
+    }
+    db::reorder(params.list_id, &params.order, &pool).await?;
+    Ok(HtmlTemplate(templates::render_board_columns(&pool).await?))
+}
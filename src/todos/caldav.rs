@@ -0,0 +1,835 @@
+use askama::Template;
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts, Path, State},
+    http::{header, request::Parts, HeaderMap, HeaderName, Method, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    routing::any,
+    Extension, Router,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use quick_xml::{
+    escape::resolve_predefined_entity,
+    events::Event,
+    name::{Namespace, ResolveResult},
+    NsReader, XmlVersion,
+};
+use sqlx::{PgPool, Pool, Postgres};
+use time::{Date, OffsetDateTime, UtcOffset};
+
+use crate::{
+    ics,
+    notifications::{Notification, Notifier},
+    tokens::{self, TokenScope},
+    utils,
+};
+
+use super::{
+    db,
+    routes::MAX_DESCRIPTION_CHARS,
+    templates::{CalDavMultistatusTemplate, DavKind, DavResponse},
+    CalDavList, CalDavTodo, Priority,
+};
+
+// CalDAV routes, so that apps like Apple Reminders or Tasks.org (through DAVx5) can sync todos.
+// Each list is a calendar of VTODOs. Clients sign in with any user name and a personal access
+// token as the password, so these are merged outside the CSRF protection.
+pub fn routes(pool: &Pool<Postgres>, notifier: Notifier) -> Router {
+    Router::new()
+        .route("/.well-known/caldav", any(well_known))
+        .route("/caldav", any(home))
+        .route("/caldav/", any(home))
+        .route("/caldav/lists/:list_id", any(calendar))
+        .route("/caldav/lists/:list_id/", any(calendar))
+        .route("/caldav/lists/:list_id/:name", any(resource))
+        .layer(Extension(notifier))
+        .with_state(pool.clone())
+}
+
+const METHODS: &str = "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, REPORT";
+
+const DAV: Namespace = Namespace("DAV:");
+const CALDAV: Namespace = Namespace("urn:ietf:params:xml:ns:caldav");
+
+// Names go in hrefs percent-encoded, apart from the characters URLs allow as they are
+const HREF_NAME: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b'@');
+
+// A VTODO sent by a client, as much of it as a todo can hold
+pub(super) struct VTodo {
+    pub(super) uid: Option<String>,
+    pub(super) description: String,
+    pub(super) notes: String,
+    pub(super) due_at: Option<Date>,
+    pub(super) priority: Priority,
+    pub(super) done: bool,
+}
+
+// The scope of the personal access token the client signed in with
+struct DavAuth(TokenScope);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for DavAuth
+where
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let password = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Basic "))
+            .and_then(|v| BASE64.decode(v.trim()).ok())
+            .and_then(|credentials| String::from_utf8(credentials).ok())
+            .and_then(|credentials| {
+                let (_user, password) = credentials.split_once(':')?;
+                Some(password.to_string())
+            });
+        let scope = match password {
+            Some(password) => tokens::scope_of(&password, &PgPool::from_ref(state))
+                .await
+                .map_err(IntoResponse::into_response)?,
+            None => None,
+        };
+        scope.map(DavAuth).ok_or_else(|| {
+            (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Basic realm=\"Todos\"")],
+                "Sign in with a personal access token as the password",
+            )
+                .into_response()
+        })
+    }
+}
+
+// any /.well-known/caldav
+// Where clients look for the server when given just the host (RFC 6764)
+async fn well_known() -> Redirect {
+    Redirect::permanent("/caldav/")
+}
+
+// any /caldav/
+// The principal signing in, which is also the home of its calendars: one for each list
+async fn home(
+    State(pool): State<PgPool>,
+    DavAuth(scope): DavAuth,
+    method: Method,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    match method.as_str() {
+        "OPTIONS" => Ok(options()),
+        "PROPFIND" => {
+            let mut responses = vec![home_response()];
+            if with_members(&headers) {
+                let lists = db::get_caldav_lists(None, &pool).await?;
+                responses.extend(lists.iter().map(|list| calendar_response(list, scope)));
+            }
+            multistatus(responses)
+        }
+        _ => Ok(method_not_allowed()),
+    }
+}
+
+// any /caldav/lists/:list_id/
+// A list as a calendar. Clients compare its ctag and the etags of its todos to find changes,
+// then fetch the todos that changed with a REPORT.
+async fn calendar(
+    State(pool): State<PgPool>,
+    DavAuth(scope): DavAuth,
+    Path(list_id): Path<i32>,
+    method: Method,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Response, (StatusCode, String)> {
+    let list = get_list(list_id, &pool).await?;
+    match method.as_str() {
+        "OPTIONS" => Ok(options()),
+        "PROPFIND" => {
+            let mut responses = vec![calendar_response(&list, scope)];
+            if with_members(&headers) {
+                let todos = db::get_caldav_todos(list_id, None, &pool).await?;
+                responses.extend(todos.iter().map(|todo| todo_response(list_id, todo, false)));
+            }
+            multistatus(responses)
+        }
+        "REPORT" => {
+            let report = parse_report(&body)?;
+            let todos = db::get_caldav_todos(list_id, None, &pool).await?;
+            match report {
+                Report::Multiget(hrefs) => multistatus(multiget(list_id, &todos, hrefs)),
+                Report::Query(kinds)
+                    if !kinds.is_empty() && !kinds.iter().any(|kind| kind == "VTODO") =>
+                {
+                    // a calendar-query for events, and there are none
+                    multistatus(Vec::new())
+                }
+                Report::Query(_) => {
+                    // any other calendar-query gets every todo, clients filter for themselves
+                    let responses = todos
+                        .iter()
+                        .map(|todo| todo_response(list_id, todo, true))
+                        .collect();
+                    multistatus(responses)
+                }
+            }
+        }
+        _ => Ok(method_not_allowed()),
+    }
+}
+
+// any /caldav/lists/:list_id/:name
+// A todo as a VTODO. Clients create todos by putting a calendar at a name of their choosing.
+async fn resource(
+    State(pool): State<PgPool>,
+    Extension(notifier): Extension<Notifier>,
+    DavAuth(scope): DavAuth,
+    Path((list_id, name)): Path<(i32, String)>,
+    method: Method,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Response, (StatusCode, String)> {
+    get_list(list_id, &pool).await?;
+    let todo = db::get_caldav_todos(list_id, Some(&name), &pool)
+        .await?
+        .pop();
+    let writes = matches!(method, Method::PUT | Method::DELETE);
+    if writes && scope != TokenScope::ReadWrite {
+        return Err((
+            StatusCode::FORBIDDEN,
+            String::from("This token is read-only"),
+        ));
+    }
+    if writes {
+        check_preconditions(&headers, todo.as_ref().map(etag).as_deref())?;
+    }
+
+    match (method.as_str(), todo) {
+        ("OPTIONS", _) => Ok(options()),
+        ("GET" | "HEAD", Some(todo)) => Ok((
+            [
+                (
+                    header::CONTENT_TYPE,
+                    String::from("text/calendar; charset=utf-8"),
+                ),
+                (header::ETAG, etag(&todo)),
+            ],
+            vcalendar(&todo),
+        )
+            .into_response()),
+        ("PROPFIND", Some(todo)) => multistatus(vec![todo_response(list_id, &todo, false)]),
+        ("PUT", todo) => {
+            let vtodo = parse_vtodo(&body).map_err(|err| (StatusCode::FORBIDDEN, err))?;
+            let status = match todo {
+                Some(_) => StatusCode::NO_CONTENT,
+                None => StatusCode::CREATED,
+            };
+            db::save_caldav_todo(list_id, &name, todo.as_ref(), &vtodo, &pool).await?;
+            notifier.send(Notification::list_changed(list_id, None));
+            Ok(status.into_response())
+        }
+        ("DELETE", Some(todo)) => {
+            db::delete_todos(list_id, &[todo.id], &pool).await?;
+            notifier.send(Notification::list_changed(list_id, None));
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        ("GET" | "HEAD" | "PROPFIND" | "DELETE", None) => {
+            Err((StatusCode::NOT_FOUND, String::from("Not found")))
+        }
+        _ => Ok(method_not_allowed()),
+    }
+}
+
+async fn get_list(list_id: i32, pool: &PgPool) -> Result<CalDavList, (StatusCode, String)> {
+    db::get_caldav_lists(Some(list_id), pool)
+        .await?
+        .pop()
+        .ok_or((StatusCode::NOT_FOUND, String::from("Not found")))
+}
+
+fn options() -> Response {
+    (
+        StatusCode::OK,
+        [
+            (HeaderName::from_static("dav"), "1, 3, calendar-access"),
+            (header::ALLOW, METHODS),
+        ],
+    )
+        .into_response()
+}
+
+fn method_not_allowed() -> Response {
+    (StatusCode::METHOD_NOT_ALLOWED, [(header::ALLOW, METHODS)]).into_response()
+}
+
+fn multistatus(responses: Vec<DavResponse>) -> Result<Response, (StatusCode, String)> {
+    let xml = CalDavMultistatusTemplate { responses }
+        .render()
+        .map_err(utils::internal_error)?;
+    Ok((
+        StatusCode::MULTI_STATUS,
+        [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+        xml,
+    )
+        .into_response())
+}
+
+// A PROPFIND on a collection covers its members too, unless the client asks for Depth: 0.
+// Depth: infinity is treated as 1, there's nothing deeper.
+fn with_members(headers: &HeaderMap) -> bool {
+    headers
+        .get("Depth")
+        .is_none_or(|depth| depth.as_bytes() != b"0")
+}
+
+// If-Match and If-None-Match, so that a client doesn't overwrite a change it hasn't seen yet
+fn check_preconditions(
+    headers: &HeaderMap,
+    etag: Option<&str>,
+) -> Result<(), (StatusCode, String)> {
+    let matches = |header: HeaderName| {
+        let value = headers.get(header)?.to_str().ok()?;
+        Some(etag.is_some_and(|etag| {
+            value == "*" || value.split(',').any(|candidate| candidate.trim() == etag)
+        }))
+    };
+    if matches(header::IF_MATCH) == Some(false) || matches(header::IF_NONE_MATCH) == Some(true) {
+        return Err((
+            StatusCode::PRECONDITION_FAILED,
+            String::from("The todo was changed since, fetch it again"),
+        ));
+    }
+    Ok(())
+}
+
+fn home_response() -> DavResponse {
+    DavResponse {
+        href: String::from("/caldav/"),
+        kind: DavKind::Home,
+        displayname: String::new(),
+        ctag: String::new(),
+        etag: String::new(),
+        writable: false,
+        calendar_data: None,
+    }
+}
+
+fn calendar_response(list: &CalDavList, scope: TokenScope) -> DavResponse {
+    DavResponse {
+        href: format!("/caldav/lists/{}/", list.id),
+        kind: DavKind::Calendar,
+        displayname: list.name.clone(),
+        ctag: list.ctag.clone(),
+        etag: String::new(),
+        writable: scope == TokenScope::ReadWrite,
+        calendar_data: None,
+    }
+}
+
+fn todo_response(list_id: i32, todo: &CalDavTodo, with_data: bool) -> DavResponse {
+    DavResponse {
+        href: format!(
+            "/caldav/lists/{list_id}/{}",
+            utf8_percent_encode(&todo.name, HREF_NAME)
+        ),
+        kind: DavKind::Todo,
+        displayname: String::new(),
+        ctag: String::new(),
+        etag: etag(todo),
+        writable: false,
+        calendar_data: with_data.then(|| vcalendar(todo)),
+    }
+}
+
+// The todos a calendar-multiget REPORT asks for by href, and a 404 for each one that's gone
+fn multiget(list_id: i32, todos: &[CalDavTodo], hrefs: Vec<String>) -> Vec<DavResponse> {
+    hrefs
+        .into_iter()
+        .map(|href| {
+            let name = href.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+            let name = percent_decode_str(name).decode_utf8_lossy();
+            match todos.iter().find(|todo| todo.name == name) {
+                Some(todo) => todo_response(list_id, todo, true),
+                None => DavResponse {
+                    href,
+                    kind: DavKind::Missing,
+                    displayname: String::new(),
+                    ctag: String::new(),
+                    etag: String::new(),
+                    writable: false,
+                    calendar_data: None,
+                },
+            }
+        })
+        .collect()
+}
+
+// What a REPORT asks for (RFC 4791 7.8 and 7.9)
+#[derive(Debug, PartialEq)]
+enum Report {
+    // the resources at these hrefs
+    Multiget(Vec<String>),
+    // the components of these kinds, e.g. VTODO, or of any kind when there are none
+    Query(Vec<String>),
+}
+
+// Clients differ in the prefixes they give the DAV: and CalDAV namespaces, or whether they
+// use a default one, so elements are matched by namespace rather than by name
+fn parse_report(body: &str) -> Result<Report, (StatusCode, String)> {
+    let invalid = |err: quick_xml::Error| (StatusCode::BAD_REQUEST, format!("Invalid XML: {err}"));
+    let mut reader = NsReader::from_str(body);
+    let mut report = None;
+    // the text so far of the <href> being read
+    let mut href: Option<String> = None;
+    // elements opened and not yet closed, as a body cut short still parses up to where it ends
+    let mut depth = 0;
+    loop {
+        let (namespace, event) = reader.read_resolved_event().map_err(invalid)?;
+        let (element, empty) = match &event {
+            Event::Start(element) => {
+                depth += 1;
+                (element, false)
+            }
+            Event::Empty(element) => (element, true),
+            Event::Text(text) => {
+                if let Some(href) = &mut href {
+                    href.push_str(&text.xml10_content());
+                }
+                continue;
+            }
+            Event::GeneralRef(reference) => {
+                if let Some(href) = &mut href {
+                    match reference.resolve_char_ref().map_err(invalid)? {
+                        Some(c) => href.push(c),
+                        None => href.push_str(
+                            resolve_predefined_entity(&reference.xml10_content()).unwrap_or(""),
+                        ),
+                    }
+                }
+                continue;
+            }
+            Event::End(_) => {
+                depth -= 1;
+                if let (Some(href), Some(Report::Multiget(hrefs))) = (href.take(), &mut report) {
+                    hrefs.push(href.trim().to_string());
+                }
+                continue;
+            }
+            Event::Eof if depth > 0 => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    String::from("The report ends before all of its elements are closed"),
+                ))
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        let name = element.local_name();
+        match &mut report {
+            None => {
+                report =
+                    match name.as_ref() {
+                        "calendar-multiget" if namespace == ResolveResult::Bound(CALDAV) => {
+                            Some(Report::Multiget(Vec::new()))
+                        }
+                        "calendar-query" if namespace == ResolveResult::Bound(CALDAV) => {
+                            Some(Report::Query(Vec::new()))
+                        }
+                        _ => return Err((
+                            StatusCode::FORBIDDEN,
+                            String::from(
+                                "Only calendar-multiget and calendar-query reports are supported",
+                            ),
+                        )),
+                    };
+            }
+            Some(Report::Multiget(_)) => {
+                if name.as_ref() == "href" && namespace == ResolveResult::Bound(DAV) && !empty {
+                    href = Some(String::new());
+                }
+            }
+            Some(Report::Query(kinds)) => {
+                if name.as_ref() == "comp-filter" && namespace == ResolveResult::Bound(CALDAV) {
+                    let kind = element
+                        .try_get_attribute("name")
+                        .map_err(|err| invalid(err.into()))?
+                        .map(|attribute| attribute.normalized_value(XmlVersion::Implicit1_0))
+                        .transpose()
+                        .map_err(invalid)?
+                        .unwrap_or_default();
+                    if !kind.eq_ignore_ascii_case("VCALENDAR") {
+                        kinds.push(kind.to_ascii_uppercase());
+                    }
+                }
+            }
+        }
+    }
+    report.ok_or_else(|| (StatusCode::BAD_REQUEST, String::from("The report is empty")))
+}
+
+// Changes whenever the todo does, see the todos_updated_at trigger
+fn etag(todo: &CalDavTodo) -> String {
+    format!(
+        "\"{}-{}\"",
+        todo.id,
+        todo.updated_at.unix_timestamp_nanos() / 1000
+    )
+}
+
+fn vcalendar(todo: &CalDavTodo) -> String {
+    let timestamp = |at: OffsetDateTime| {
+        at.to_offset(UtcOffset::UTC)
+            .format(ics::TIMESTAMP)
+            .unwrap_or_default()
+    };
+    let updated_at = timestamp(todo.updated_at);
+    let mut calendar = String::new();
+    ics::line(&mut calendar, "BEGIN:VCALENDAR");
+    ics::line(&mut calendar, "VERSION:2.0");
+    ics::line(&mut calendar, "PRODID:-//todo-axum-htmx//todos//EN");
+    ics::line(&mut calendar, "BEGIN:VTODO");
+    ics::line(&mut calendar, &format!("UID:{}", todo.uid));
+    ics::line(&mut calendar, &format!("DTSTAMP:{updated_at}"));
+    ics::line(
+        &mut calendar,
+        &format!("CREATED:{}", timestamp(todo.created_at)),
+    );
+    ics::line(&mut calendar, &format!("LAST-MODIFIED:{updated_at}"));
+    ics::line(
+        &mut calendar,
+        &format!("SUMMARY:{}", ics::text(&todo.description)),
+    );
+    if !todo.notes.is_empty() {
+        ics::line(
+            &mut calendar,
+            &format!("DESCRIPTION:{}", ics::text(&todo.notes)),
+        );
+    }
+    if let Some(due_at) = todo.due_at {
+        let due_at = due_at.format(ics::DATE).unwrap_or_default();
+        ics::line(&mut calendar, &format!("DUE;VALUE=DATE:{due_at}"));
+    }
+    if let Some(priority) = ics_priority(todo.priority) {
+        ics::line(&mut calendar, &format!("PRIORITY:{priority}"));
+    }
+    if todo.done {
+        ics::line(&mut calendar, "STATUS:COMPLETED");
+        if let Some(completed_at) = todo.completed_at {
+            ics::line(
+                &mut calendar,
+                &format!("COMPLETED:{}", timestamp(completed_at)),
+            );
+        }
+    } else {
+        ics::line(&mut calendar, "STATUS:NEEDS-ACTION");
+    }
+    ics::line(&mut calendar, "END:VTODO");
+    ics::line(&mut calendar, "END:VCALENDAR");
+    calendar
+}
+
+// iCalendar priorities go from 1 (highest) to 9 (lowest), 0 meaning none
+fn ics_priority(priority: Priority) -> Option<u8> {
+    match priority {
+        Priority::Urgent => Some(1),
+        Priority::High => Some(3),
+        Priority::Normal => None,
+        Priority::Low => Some(9),
+    }
+}
+
+fn priority_from_ics(priority: u8) -> Priority {
+    match priority {
+        1..=2 => Priority::Urgent,
+        3..=4 => Priority::High,
+        6..=9 => Priority::Low,
+        _ => Priority::Normal,
+    }
+}
+
+// The todo in the first VTODO of a calendar. Whatever a todo has no place for, like alarms
+// or repeat rules, is dropped.
+fn parse_vtodo(calendar: &str) -> Result<VTodo, String> {
+    let properties =
+        vtodo_properties(calendar).ok_or("Only VTODOs can be stored in a list of todos")?;
+    let value = |name: &str| {
+        properties
+            .iter()
+            .find(|property| property.name == name)
+            .map(|property| property.value.as_str())
+    };
+
+    let summary = value("SUMMARY").map(ics::unescape).unwrap_or_default();
+    let description: String = summary
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(MAX_DESCRIPTION_CHARS)
+        .collect();
+    let done = match value("STATUS") {
+        Some(status) => status.eq_ignore_ascii_case("COMPLETED"),
+        None => value("COMPLETED").is_some(),
+    };
+    Ok(VTodo {
+        uid: value("UID").map(str::to_string),
+        description: if description.is_empty() {
+            String::from("Untitled")
+        } else {
+            description
+        },
+        notes: value("DESCRIPTION").map(ics::unescape).unwrap_or_default(),
+        due_at: value("DUE").and_then(ics::parse_date),
+        priority: value("PRIORITY")
+            .and_then(|priority| priority.trim().parse().ok())
+            .map_or(Priority::Normal, priority_from_ics),
+        done,
+    })
+}
+
+// The properties of the first VTODO, leaving out those of components inside it such as VALARM
+fn vtodo_properties(calendar: &str) -> Option<Vec<ics::Property>> {
+    let mut properties = Vec::new();
+    let mut inside = false;
+    let mut nested = 0;
+    for property in ics::parse(calendar) {
+        let begin = property.name == "BEGIN";
+        let end = property.name == "END";
+        if !inside {
+            inside = begin && property.value.trim().eq_ignore_ascii_case("VTODO");
+        } else if begin {
+            nested += 1;
+        } else if end && nested > 0 {
+            nested -= 1;
+        } else if end {
+            return Some(properties);
+        } else if nested == 0 {
+            properties.push(property);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::{date, datetime};
+
+    use super::*;
+
+    fn todo(name: &str) -> CalDavTodo {
+        CalDavTodo {
+            id: 1,
+            name: name.to_string(),
+            uid: String::from("todo-1@todos"),
+            description: String::from("Buy milk"),
+            notes: String::new(),
+            done: false,
+            completed_at: None,
+            due_at: None,
+            priority: Priority::Normal,
+            created_at: datetime!(2026-10-01 9:00 UTC),
+            updated_at: datetime!(2026-10-01 9:00 UTC),
+        }
+    }
+
+    #[test]
+    fn reads_a_multiget_from_davx5() {
+        let body = r#"<?xml version='1.0' encoding='UTF-8' ?><CAL:calendar-multiget xmlns="DAV:" xmlns:CAL="urn:ietf:params:xml:ns:caldav"><prop><getetag /><CAL:calendar-data /></prop><href>/caldav/lists/1/todo-1.ics</href><href>/caldav/lists/1/0b1d%20e4.ics</href></CAL:calendar-multiget>"#;
+        assert_eq!(
+            parse_report(body),
+            Ok(Report::Multiget(vec![
+                String::from("/caldav/lists/1/todo-1.ics"),
+                String::from("/caldav/lists/1/0b1d%20e4.ics"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn reads_a_multiget_from_thunderbird() {
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<calendar-multiget xmlns:D="DAV:" xmlns="urn:ietf:params:xml:ns:caldav">
+  <D:prop>
+    <D:getetag/>
+    <calendar-data/>
+  </D:prop>
+  <D:href>/caldav/lists/1/todo-1.ics</D:href>
+</calendar-multiget>"#;
+        assert_eq!(
+            parse_report(body),
+            Ok(Report::Multiget(vec![String::from(
+                "/caldav/lists/1/todo-1.ics"
+            )]))
+        );
+    }
+
+    #[test]
+    fn reads_a_multiget_from_apple_calendar() {
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<B:calendar-multiget xmlns:B="urn:ietf:params:xml:ns:caldav">
+  <A:prop xmlns:A="DAV:">
+    <A:getetag/>
+    <B:calendar-data/>
+  </A:prop>
+  <A:href xmlns:A="DAV:">
+    /caldav/lists/1/4F0C2A1E-7B3D.ics
+  </A:href>
+  <A:href xmlns:A="DAV:">/caldav/lists/1/a&amp;b&#x2e;ics</A:href>
+</B:calendar-multiget>"#;
+        assert_eq!(
+            parse_report(body),
+            Ok(Report::Multiget(vec![
+                String::from("/caldav/lists/1/4F0C2A1E-7B3D.ics"),
+                String::from("/caldav/lists/1/a&b.ics"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn ignores_hrefs_outside_the_dav_namespace() {
+        let body = r#"<c:calendar-multiget xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav" xmlns:x="http://example.com/ns"><x:href>/elsewhere</x:href><d:href>/caldav/lists/1/todo-1.ics</d:href></c:calendar-multiget>"#;
+        assert_eq!(
+            parse_report(body),
+            Ok(Report::Multiget(vec![String::from(
+                "/caldav/lists/1/todo-1.ics"
+            )]))
+        );
+    }
+
+    #[test]
+    fn reads_the_kinds_a_query_asks_for() {
+        // DAVx5, for Tasks.org
+        let todos = r#"<?xml version='1.0' encoding='UTF-8' ?><CAL:calendar-query xmlns="DAV:" xmlns:CAL="urn:ietf:params:xml:ns:caldav"><prop><getetag /></prop><CAL:filter><CAL:comp-filter name="VCALENDAR"><CAL:comp-filter name="VTODO" /></CAL:comp-filter></CAL:filter></CAL:calendar-query>"#;
+        assert_eq!(
+            parse_report(todos),
+            Ok(Report::Query(vec![String::from("VTODO")]))
+        );
+        // Thunderbird, looking for events in a time range
+        let events = r#"<?xml version="1.0" encoding="UTF-8"?>
+<calendar-query xmlns:D="DAV:" xmlns="urn:ietf:params:xml:ns:caldav">
+  <D:prop>
+    <D:getetag/>
+  </D:prop>
+  <filter>
+    <comp-filter name='VCALENDAR'>
+      <comp-filter name = 'VEVENT'>
+        <time-range start="20260901T000000Z" end="20261231T000000Z"/>
+      </comp-filter>
+    </comp-filter>
+  </filter>
+</calendar-query>"#;
+        assert_eq!(
+            parse_report(events),
+            Ok(Report::Query(vec![String::from("VEVENT")]))
+        );
+    }
+
+    #[test]
+    fn reads_a_query_for_any_kind() {
+        // Apple Calendar, asking for everything in the calendar
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<B:calendar-query xmlns:B="urn:ietf:params:xml:ns:caldav">
+  <A:prop xmlns:A="DAV:">
+    <A:getetag/>
+    <A:getcontenttype/>
+  </A:prop>
+  <B:filter>
+    <B:comp-filter name="VCALENDAR"/>
+  </B:filter>
+</B:calendar-query>"#;
+        assert_eq!(parse_report(body), Ok(Report::Query(Vec::new())));
+    }
+
+    #[test]
+    fn rejects_other_reports() {
+        let sync = r#"<?xml version="1.0" encoding="utf-8" ?><d:sync-collection xmlns:d="DAV:"><d:sync-token/><d:sync-level>1</d:sync-level><d:prop><d:getetag/></d:prop></d:sync-collection>"#;
+        assert_eq!(parse_report(sync).unwrap_err().0, StatusCode::FORBIDDEN);
+        // the right name in the wrong namespace
+        let unbound =
+            "<calendar-multiget><href>/caldav/lists/1/todo-1.ics</href></calendar-multiget>";
+        assert_eq!(parse_report(unbound).unwrap_err().0, StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn rejects_bodies_that_arent_reports() {
+        assert_eq!(parse_report("").unwrap_err().0, StatusCode::BAD_REQUEST);
+        let broken = r#"<c:calendar-multiget xmlns:c="urn:ietf:params:xml:ns:caldav"><d:href>"#;
+        assert_eq!(parse_report(broken).unwrap_err().0, StatusCode::BAD_REQUEST);
+    }
+
+    // PROPFIND bodies aren't read, every property is sent whichever ones a client asks for
+    #[test]
+    fn propfinds_cover_members_unless_depth_is_zero() {
+        let depth = |value: &str| {
+            HeaderMap::from_iter([(HeaderName::from_static("depth"), value.parse().unwrap())])
+        };
+        assert!(!with_members(&depth("0")));
+        assert!(with_members(&depth("1")));
+        assert!(with_members(&depth("infinity")));
+        assert!(with_members(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn finds_todos_by_their_encoded_names() {
+        let todos = [todo("todo-1.ics"), todo("Buy milk & eggs.ics")];
+        let hrefs: Vec<String> = todos
+            .iter()
+            .map(|todo| todo_response(1, todo, false).href)
+            .collect();
+        assert_eq!(
+            hrefs,
+            [
+                "/caldav/lists/1/todo-1.ics",
+                "/caldav/lists/1/Buy%20milk%20%26%20eggs.ics"
+            ]
+        );
+
+        let mut requested = hrefs.clone();
+        requested.push(String::from("/caldav/lists/1/gone.ics"));
+        let kinds: Vec<DavKind> = multiget(1, &todos, requested)
+            .into_iter()
+            .map(|response| response.kind)
+            .collect();
+        assert_eq!(kinds, [DavKind::Todo, DavKind::Todo, DavKind::Missing]);
+    }
+
+    #[test]
+    fn reads_a_vtodo_from_apple_reminders() {
+        let calendar = "BEGIN:VCALENDAR\r\n\
+            VERSION:2.0\r\n\
+            PRODID:-//Apple Inc.//iOS 17.0//EN\r\n\
+            BEGIN:VTODO\r\n\
+            UID:4F0C2A1E-7B3D\r\n\
+            SUMMARY:Call the\r\n  \u{20}plumber\r\n\
+            DESCRIPTION:About the leak\\, again\r\n\
+            DUE;VALUE=DATE:20261020\r\n\
+            PRIORITY:3\r\n\
+            STATUS:NEEDS-ACTION\r\n\
+            BEGIN:VALARM\r\n\
+            ACTION:DISPLAY\r\n\
+            DESCRIPTION:Reminder\r\n\
+            END:VALARM\r\n\
+            END:VTODO\r\n\
+            END:VCALENDAR\r\n";
+        let vtodo = parse_vtodo(calendar).unwrap();
+        assert_eq!(vtodo.uid.as_deref(), Some("4F0C2A1E-7B3D"));
+        assert_eq!(vtodo.description, "Call the plumber");
+        assert_eq!(vtodo.notes, "About the leak, again");
+        assert_eq!(vtodo.due_at, Some(date!(2026 - 10 - 20)));
+        assert_eq!(vtodo.priority, Priority::High);
+        assert!(!vtodo.done);
+    }
+
+    #[test]
+    fn rejects_calendars_without_a_vtodo() {
+        let event =
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:Lunch\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        assert!(parse_vtodo(event).is_err());
+    }
+}
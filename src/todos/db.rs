@@ -1,33 +1,1508 @@
+use std::collections::{HashMap, HashSet};
+
 use axum::http::StatusCode;
-use sqlx::PgPool;
+use sqlx::{PgPool, Postgres, Transaction};
+use time::{Date, OffsetDateTime};
 
 use crate::utils;
 
-use super::Todo;
+use super::{
+    caldav::VTodo,
+    import::{ImportedList, ImportedTodo},
+    inbound_email::InboundEmail,
+    reposition, AgendaTodo, ArchivedTodo, BoardTodo, CalDavList, CalDavTodo, Color, DueTodo, Page,
+    Priority, Recurrence, TelegramChat, Todo, TodoAttachment, TodoCounts, TodoFilter, TodoRevision,
+    TodoTxtTodo, UndoAction, IDEMPOTENCY_KEY_TTL_HOURS, UNDO_WINDOW_SECONDS,
+};
 
-pub async fn get_todos(pool: &PgPool) -> Result<Vec<Todo>, (StatusCode, String)> {
+// Pass `None` for `page` to get every matching todo
+pub async fn get_todos(
+    list_id: i32,
+    filter: &TodoFilter,
+    page: Option<Page>,
+    pool: &PgPool,
+) -> Result<Vec<Todo>, (StatusCode, String)> {
+    let today = OffsetDateTime::now_utc().date();
     sqlx::query_as!(
         Todo,
-        "select id, done, description, position from todos ORDER BY position desc"
+        r#"select id, list_id, done, completed_at, description, notes, due_at, priority as "priority: Priority",
+                recurrence as "recurrence: Recurrence", remind_days_before, estimate_minutes, pinned,
+                color as "color: Color",
+                array(select tags.name from todo_tags join tags on tags.id = todo_tags.tag_id
+                      where todo_tags.todo_id = todos.id order by tags.name) as "tags!",
+                array(select blockers.id from todo_dependencies join todos as blockers on blockers.id = todo_dependencies.blocker_id
+                      where todo_dependencies.todo_id = todos.id and not blockers.done
+                        and blockers.archived_at is null and blockers.deleted_at is null
+                      order by blockers.id) as "blocker_ids!",
+                array(select blockers.description from todo_dependencies join todos as blockers on blockers.id = todo_dependencies.blocker_id
+                      where todo_dependencies.todo_id = todos.id and not blockers.done
+                        and blockers.archived_at is null and blockers.deleted_at is null
+                      order by blockers.id) as "blocker_descriptions!",
+                array(select custom_fields.name from todo_custom_values join custom_fields on custom_fields.id = todo_custom_values.field_id
+                      where todo_custom_values.todo_id = todos.id order by custom_fields.id) as "custom_field_names!",
+                array(select todo_custom_values.value from todo_custom_values join custom_fields on custom_fields.id = todo_custom_values.field_id
+                      where todo_custom_values.todo_id = todos.id order by custom_fields.id) as "custom_field_values!",
+                created_at, updated_at, version, email_from, email_subject
+         from todos
+         where list_id = $5 and archived_at is null and deleted_at is null
+           and ($6::text = 'all' or done = ($6::text = 'completed'))
+           and ($1::bool is not true or (not done and due_at < $2))
+           and ($4::text is null or exists (
+                select 1 from todo_tags join tags on tags.id = todo_tags.tag_id
+                where todo_tags.todo_id = todos.id and tags.name = $4))
+           and ($9::todo_color is null or color = $9)
+           and ($10::bool is not true or completed_at >= $11)
+         ORDER BY pinned desc,
+                  case when $3::text = 'due_at' then due_at end asc nulls last,
+                  case when $3::text = 'priority' then priority end desc,
+                  case when $3::text = 'created_at' then created_at end desc,
+                  case when $3::text = 'updated_at' then updated_at end desc,
+                  case when $3::text = 'alphabetical' then lower(description) end asc,
+                  position desc
+         limit $7 offset $8"#,
+        filter.overdue,
+        today,
+        filter.sort.as_str(),
+        filter.tag,
+        list_id,
+        filter.status.as_str(),
+        page.map(|p| p.limit),
+        page.map(|p| p.offset).unwrap_or(0),
+        filter.color as _,
+        filter.completed_today,
+        today.midnight().assume_utc(),
     )
     .fetch_all(pool)
     .await
     .map_err(utils::internal_error)
 }
 
-pub async fn delete_todos(todos: Vec<Todo>, pool: &PgPool) -> Result<(), (StatusCode, String)> {
-    let delete_ids = todos.iter().map(|t| t.id).collect::<Vec<_>>();
+pub async fn get_todo(
+    list_id: i32,
+    todo_id: i32,
+    pool: &PgPool,
+) -> Result<Todo, (StatusCode, String)> {
+    sqlx::query_as!(
+        Todo,
+        r#"select id, list_id, done, completed_at, description, notes, due_at, priority as "priority: Priority",
+                recurrence as "recurrence: Recurrence", remind_days_before, estimate_minutes, pinned,
+                color as "color: Color",
+                array(select tags.name from todo_tags join tags on tags.id = todo_tags.tag_id
+                      where todo_tags.todo_id = todos.id order by tags.name) as "tags!",
+                array(select blockers.id from todo_dependencies join todos as blockers on blockers.id = todo_dependencies.blocker_id
+                      where todo_dependencies.todo_id = todos.id and not blockers.done
+                        and blockers.archived_at is null and blockers.deleted_at is null
+                      order by blockers.id) as "blocker_ids!",
+                array(select blockers.description from todo_dependencies join todos as blockers on blockers.id = todo_dependencies.blocker_id
+                      where todo_dependencies.todo_id = todos.id and not blockers.done
+                        and blockers.archived_at is null and blockers.deleted_at is null
+                      order by blockers.id) as "blocker_descriptions!",
+                array(select custom_fields.name from todo_custom_values join custom_fields on custom_fields.id = todo_custom_values.field_id
+                      where todo_custom_values.todo_id = todos.id order by custom_fields.id) as "custom_field_names!",
+                array(select todo_custom_values.value from todo_custom_values join custom_fields on custom_fields.id = todo_custom_values.field_id
+                      where todo_custom_values.todo_id = todos.id order by custom_fields.id) as "custom_field_values!",
+                created_at, updated_at, version, email_from, email_subject
+         from todos
+         where id = $1 and list_id = $2 and archived_at is null and deleted_at is null"#,
+        todo_id,
+        list_id,
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(utils::internal_error)?
+    .ok_or((StatusCode::NOT_FOUND, String::from("Todo not found")))
+}
+
+// How many todos in the list are still to be done and how many are done,
+// narrowed down by the filter's tag and color but not its status
+pub async fn count_todos(
+    list_id: i32,
+    filter: &TodoFilter,
+    pool: &PgPool,
+) -> Result<TodoCounts, (StatusCode, String)> {
+    sqlx::query_as!(
+        TodoCounts,
+        r#"select count(*) filter (where not done) as "active!", count(*) filter (where done) as "done!",
+                coalesce(sum(estimate_minutes) filter (where not done), 0) as "active_estimate_minutes!"
+           from todos
+           where list_id = $1 and archived_at is null and deleted_at is null
+             and ($2::text is null or exists (
+                  select 1 from todo_tags join tags on tags.id = todo_tags.tag_id
+                  where todo_tags.todo_id = todos.id and tags.name = $2))
+             and ($3::todo_color is null or color = $3)"#,
+        list_id,
+        filter.tag,
+        filter.color as _,
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// Deleted todos are only marked as such until the chance to undo has passed, see record_undo.
+// Returns the ids that were deleted.
+pub async fn delete_todos(
+    list_id: i32,
+    ids: &[i32],
+    pool: &PgPool,
+) -> Result<Vec<i32>, (StatusCode, String)> {
     // https://github.com/launchbadge/sqlx/blob/main/FAQ.md#how-can-i-do-a-select--where-foo-in--query
-    sqlx::query!("delete from todos where id = ANY($1)", &delete_ids)
+    let rows = sqlx::query!(
+        "update todos set deleted_at = now()
+         where list_id = $1 and id = ANY($2) and deleted_at is null
+         returning id",
+        list_id,
+        ids,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(rows.into_iter().map(|row| row.id).collect())
+}
+
+pub async fn get_archived_todos(
+    list_id: i32,
+    pool: &PgPool,
+) -> Result<Vec<ArchivedTodo>, (StatusCode, String)> {
+    sqlx::query_as!(
+        ArchivedTodo,
+        r#"select id, description, archived_at as "archived_at!" from todos
+         where list_id = $1 and archived_at is not null and deleted_at is null
+         ORDER BY archived_at desc"#,
+        list_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// Returns the ids that were archived
+pub async fn archive_completed(
+    list_id: i32,
+    pool: &PgPool,
+) -> Result<Vec<i32>, (StatusCode, String)> {
+    let rows = sqlx::query!(
+        "update todos set archived_at = now()
+         where list_id = $1 and done and archived_at is null and deleted_at is null
+         returning id",
+        list_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(rows.into_iter().map(|row| row.id).collect())
+}
+
+// Put an archived todo back in the list, where it shows up as done
+pub async fn restore(
+    list_id: i32,
+    todo_id: i32,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let restored = sqlx::query!(
+        "update todos set archived_at = null where id = $1 and list_id = $2 and archived_at is not null and deleted_at is null",
+        todo_id,
+        list_id
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    if restored.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            String::from("Archived todo not found"),
+        ));
+    }
+    Ok(())
+}
+
+// Remember a change so that it can be undone, returning the token that undoes it.
+// Also forgets changes that can no longer be undone. The todos they deleted stay in the
+// database until the trash task removes them for good.
+pub async fn record_undo(
+    list_id: i32,
+    action: UndoAction,
+    todo_ids: &[i32],
+    pool: &PgPool,
+) -> Result<String, (StatusCode, String)> {
+    sqlx::query!(
+        "delete from undo_actions where created_at < now() - make_interval(secs => $1)",
+        UNDO_WINDOW_SECONDS as f64,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+
+    let token = utils::random_token(32);
+    sqlx::query!(
+        "insert into undo_actions (token, list_id, action, todo_ids) values ($1, $2, $3, $4)",
+        token,
+        list_id,
+        action as _,
+        todo_ids,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(token)
+}
+
+// Remove todos that were deleted more than `retention_days` ago for good, returning how many
+pub async fn purge_deleted(
+    retention_days: i32,
+    pool: &PgPool,
+) -> Result<u64, (StatusCode, String)> {
+    let result = sqlx::query!(
+        "delete from todos where deleted_at < now() - make_interval(days => $1)",
+        retention_days,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(result.rows_affected())
+}
+
+pub async fn undo(list_id: i32, token: &str, pool: &PgPool) -> Result<(), (StatusCode, String)> {
+    let mut tx = pool.begin().await.map_err(utils::internal_error)?;
+    let undo = sqlx::query!(
+        r#"delete from undo_actions
+           where token = $1 and list_id = $2 and created_at >= now() - make_interval(secs => $3)
+           returning action as "action: UndoAction", todo_ids"#,
+        token,
+        list_id,
+        UNDO_WINDOW_SECONDS as f64,
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(utils::internal_error)?
+    .ok_or((StatusCode::GONE, String::from("It's too late to undo that")))?;
+
+    match undo.action {
+        UndoAction::Delete => sqlx::query!(
+            "update todos set deleted_at = null where list_id = $1 and id = ANY($2)",
+            list_id,
+            &undo.todo_ids,
+        ),
+        UndoAction::Archive => sqlx::query!(
+            "update todos set archived_at = null where list_id = $1 and id = ANY($2)",
+            list_id,
+            &undo.todo_ids,
+        ),
+    }
+    .execute(&mut *tx)
+    .await
+    .map_err(utils::internal_error)?;
+
+    tx.commit().await.map_err(utils::internal_error)?;
+    Ok(())
+}
+
+// Mark the given todos done like set_done, all in one transaction. Blocked todos are left open
+// and their descriptions returned, unless what blocks them is done along with them.
+pub async fn complete_todos(
+    list_id: i32,
+    ids: &[i32],
+    pool: &PgPool,
+) -> Result<Vec<String>, (StatusCode, String)> {
+    let mut tx = pool.begin().await.map_err(utils::internal_error)?;
+    // Blocked by an open todo that isn't done along with them, or by a selected todo that is
+    // blocked itself
+    let blocked = sqlx::query_scalar!(
+        r#"with recursive selected as (
+             select id from todos where list_id = $1 and id = ANY($2)
+           ), blocked (id) as (
+             select todo_dependencies.todo_id from todo_dependencies
+             join todos as blockers on blockers.id = todo_dependencies.blocker_id
+             where todo_dependencies.todo_id in (select id from selected)
+               and blockers.id not in (select id from selected) and not blockers.done
+               and blockers.archived_at is null and blockers.deleted_at is null
+             union
+             select todo_dependencies.todo_id from todo_dependencies
+             join blocked on blocked.id = todo_dependencies.blocker_id
+             join todos as blockers on blockers.id = blocked.id
+             where todo_dependencies.todo_id in (select id from selected) and not blockers.done
+           )
+           select id as "id!" from blocked"#,
+        list_id,
+        ids,
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(utils::internal_error)?;
+    for &todo_id in ids.iter().filter(|id| !blocked.contains(id)) {
+        mark_done(list_id, todo_id, true, &mut tx).await?;
+    }
+    let skipped = sqlx::query_scalar!(
+        "select description from todos where list_id = $1 and id = ANY($2) order by position desc",
+        list_id,
+        &blocked,
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(utils::internal_error)?;
+    tx.commit().await.map_err(utils::internal_error)?;
+    Ok(skipped)
+}
+
+// Add a todo to the top of the list. When the request carries an idempotency key that was
+// already used for this list, nothing is added and false is returned: it's a retry or a double
+// submit. Keys are forgotten after IDEMPOTENCY_KEY_TTL_HOURS.
+pub async fn create_todo(
+    list_id: i32,
+    description: &str,
+    due_at: Option<Date>,
+    priority: Priority,
+    idempotency_key: Option<&str>,
+    pool: &PgPool,
+) -> Result<bool, (StatusCode, String)> {
+    let mut tx = pool.begin().await.map_err(utils::internal_error)?;
+    if let Some(key) = idempotency_key {
+        sqlx::query!(
+            "delete from idempotency_keys where created_at < now() - make_interval(hours => $1)",
+            IDEMPOTENCY_KEY_TTL_HOURS,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(utils::internal_error)?;
+        // A concurrent request with the same key waits here until the first one commits
+        let recorded = sqlx::query!(
+            "insert into idempotency_keys (list_id, key) values ($1, $2) on conflict do nothing",
+            list_id,
+            key,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(utils::internal_error)?;
+        if recorded.rows_affected() == 0 {
+            return Ok(false);
+        }
+    }
+    sqlx::query!(
+        "INSERT INTO todos (list_id,description,position,due_at,priority) VALUES ($1,$2,((select coalesce(max(position), 0) from todos where list_id = $1) + 1),$3,$4);",
+        list_id,
+        description,
+        due_at,
+        priority as _,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(utils::internal_error)?;
+    tx.commit().await.map_err(utils::internal_error)?;
+    Ok(true)
+}
+
+// Add an emailed todo to the top of the list. Providers send an email again when they aren't sure
+// it arrived, so one that has been seen before, by its Message-Id, is left out and false returned.
+pub async fn create_emailed_todo(
+    list_id: i32,
+    email: &InboundEmail,
+    pool: &PgPool,
+) -> Result<bool, (StatusCode, String)> {
+    let mut tx = pool.begin().await.map_err(utils::internal_error)?;
+    if let Some(message_id) = &email.message_id {
+        sqlx::query!(
+            "delete from idempotency_keys where created_at < now() - make_interval(hours => $1)",
+            IDEMPOTENCY_KEY_TTL_HOURS,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(utils::internal_error)?;
+        let recorded = sqlx::query!(
+            "insert into idempotency_keys (list_id, key) values ($1, 'email:' || $2) on conflict do nothing",
+            list_id,
+            message_id,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(utils::internal_error)?;
+        if recorded.rows_affected() == 0 {
+            return Ok(false);
+        }
+    }
+    let todo_id = sqlx::query_scalar!(
+        "insert into todos (list_id, description, notes, position, email_from, email_subject)
+         values ($1, $2, $3, (select coalesce(max(position), 0) from todos where list_id = $1) + 1, $4, $5)
+         returning id",
+        list_id,
+        email.description,
+        email.notes,
+        email.from,
+        email.subject,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(utils::internal_error)?;
+    for attachment in &email.attachments {
+        sqlx::query!(
+            "insert into todo_attachments (todo_id, file_name, data) values ($1, $2, $3)",
+            todo_id,
+            attachment.file_name,
+            attachment.data,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(utils::internal_error)?;
+    }
+    tx.commit().await.map_err(utils::internal_error)?;
+    Ok(true)
+}
+
+// The files that came with an emailed todo, without their contents
+pub async fn get_attachments(
+    list_id: i32,
+    todo_id: i32,
+    pool: &PgPool,
+) -> Result<Vec<TodoAttachment>, (StatusCode, String)> {
+    sqlx::query_as!(
+        TodoAttachment,
+        "select todo_attachments.id, todo_attachments.file_name
+         from todo_attachments join todos on todos.id = todo_attachments.todo_id
+         where todos.id = $1 and todos.list_id = $2
+         ORDER BY todo_attachments.id",
+        todo_id,
+        list_id,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// The file name and contents of an attachment
+pub async fn get_attachment(
+    list_id: i32,
+    todo_id: i32,
+    attachment_id: i32,
+    pool: &PgPool,
+) -> Result<(String, Vec<u8>), (StatusCode, String)> {
+    sqlx::query!(
+        "select todo_attachments.file_name, todo_attachments.data
+         from todo_attachments join todos on todos.id = todo_attachments.todo_id
+         where todo_attachments.id = $1 and todos.id = $2 and todos.list_id = $3",
+        attachment_id,
+        todo_id,
+        list_id,
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(utils::internal_error)?
+    .map(|attachment| (attachment.file_name, attachment.data))
+    .ok_or((StatusCode::NOT_FOUND, String::from("Not found")))
+}
+
+// Where emailed todos go when INBOUND_EMAIL_LIST_ID isn't set
+pub async fn get_first_list_id(pool: &PgPool) -> Result<Option<i32>, (StatusCode, String)> {
+    sqlx::query_scalar!("select id from lists ORDER BY id limit 1")
+        .fetch_optional(pool)
+        .await
+        .map_err(utils::internal_error)
+}
+
+// The lists as CalDAV calendars, or just the one with `list_id`. The ctag changes whenever a
+// todo in the list is added, changed or removed.
+pub async fn get_caldav_lists(
+    list_id: Option<i32>,
+    pool: &PgPool,
+) -> Result<Vec<CalDavList>, (StatusCode, String)> {
+    sqlx::query_as!(
+        CalDavList,
+        r#"select lists.id, lists.name,
+             (select coalesce(extract(epoch from max(todos.updated_at)), 0)::text || '-'
+                || count(*) filter (where todos.deleted_at is null and todos.archived_at is null)
+              from todos where todos.list_id = lists.id) as "ctag!"
+           from lists where ($1::integer is null or lists.id = $1) ORDER BY lists.id"#,
+        list_id,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// The todos of a list as CalDAV resources, or just the one called `name`
+pub async fn get_caldav_todos(
+    list_id: i32,
+    name: Option<&str>,
+    pool: &PgPool,
+) -> Result<Vec<CalDavTodo>, (StatusCode, String)> {
+    sqlx::query_as!(
+        CalDavTodo,
+        r#"select id, coalesce(caldav_name, 'todo-' || id || '.ics') as "name!",
+             coalesce(caldav_uid, 'todo-' || id || '@todo-axum-htmx') as "uid!",
+             description, notes, done, completed_at, due_at, priority as "priority: Priority",
+             created_at, updated_at
+           from todos
+           where list_id = $1 and deleted_at is null and archived_at is null
+             and ($2::text is null or coalesce(caldav_name, 'todo-' || id || '.ics') = $2)
+           ORDER BY position DESC"#,
+        list_id,
+        name,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// Save what a CalDAV client put at `name`: a change to the todo already there, or a new one.
+// Checking or unchecking it goes as in set_done, in the same transaction, so a todo that is
+// still blocked isn't saved at all.
+pub async fn save_caldav_todo(
+    list_id: i32,
+    name: &str,
+    todo: Option<&CalDavTodo>,
+    vtodo: &VTodo,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let mut tx = pool.begin().await.map_err(utils::internal_error)?;
+    let (todo_id, done) = match todo {
+        Some(todo) => {
+            update_caldav_todo(list_id, todo.id, vtodo, &mut tx).await?;
+            (todo.id, todo.done)
+        }
+        None => (
+            create_caldav_todo(list_id, name, vtodo, &mut tx).await?,
+            false,
+        ),
+    };
+    if vtodo.done != done && !check_todo(list_id, todo_id, vtodo.done, &mut tx).await? {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("Can't complete a todo while the todos blocking it are still open"),
+        ));
+    }
+    tx.commit().await.map_err(utils::internal_error)?;
+    Ok(())
+}
+
+// Add a todo a CalDAV client made to the top of the list, under the name and UID it chose
+async fn create_caldav_todo(
+    list_id: i32,
+    name: &str,
+    vtodo: &VTodo,
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<i32, (StatusCode, String)> {
+    sqlx::query_scalar!(
+        "insert into todos (list_id, description, notes, position, due_at, priority, caldav_name, caldav_uid)
+         values ($1, $2, $3, (select coalesce(max(position), 0) from todos where list_id = $1) + 1, $4, $5, $6, $7)
+         returning id",
+        list_id,
+        vtodo.description,
+        vtodo.notes,
+        vtodo.due_at,
+        vtodo.priority as _,
+        name,
+        vtodo.uid,
+    )
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// As with the edit form, the description and notes being replaced are kept as a revision and a
+// new due date means reminding again
+async fn update_caldav_todo(
+    list_id: i32,
+    todo_id: i32,
+    vtodo: &VTodo,
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        "with revision as (
+           insert into todo_revisions (todo_id, description, notes)
+           select id, description, notes from todos
+           where id = $5 and list_id = $6 and (description <> $1 or notes <> $2)
+         )
+         update todos set description = $1, notes = $2, due_at = $3, priority = $4,
+           reminded_at = case when due_at is distinct from $3 then null else reminded_at end
+         where id = $5 and list_id = $6",
+        vtodo.description,
+        vtodo.notes,
+        vtodo.due_at,
+        vtodo.priority as _,
+        todo_id,
+        list_id,
+    )
+    .execute(&mut **tx)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+pub async fn get_telegram_chats(pool: &PgPool) -> Result<Vec<TelegramChat>, (StatusCode, String)> {
+    sqlx::query_as!(
+        TelegramChat,
+        "select chat_id, name, linked_at from telegram_chats ORDER BY linked_at"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+pub async fn is_telegram_chat_linked(
+    chat_id: i64,
+    pool: &PgPool,
+) -> Result<bool, (StatusCode, String)> {
+    sqlx::query_scalar!(
+        r#"select exists (select 1 from telegram_chats where chat_id = $1) as "linked!""#,
+        chat_id,
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// A code for linking a chat, which expires after `ttl_minutes`
+pub async fn create_telegram_link_code(
+    code: &str,
+    ttl_minutes: i32,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let mut tx = pool.begin().await.map_err(utils::internal_error)?;
+    sqlx::query!(
+        "delete from telegram_link_codes where created_at < now() - make_interval(mins => $1)",
+        ttl_minutes,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(utils::internal_error)?;
+    sqlx::query!("insert into telegram_link_codes (code) values ($1)", code)
+        .execute(&mut *tx)
+        .await
+        .map_err(utils::internal_error)?;
+    tx.commit().await.map_err(utils::internal_error)?;
+    Ok(())
+}
+
+// Link a chat with a code that hasn't expired, using the code up. Linking again updates the name.
+// False when there's no such code.
+pub async fn link_telegram_chat(
+    chat_id: i64,
+    name: &str,
+    code: &str,
+    ttl_minutes: i32,
+    pool: &PgPool,
+) -> Result<bool, (StatusCode, String)> {
+    let result = sqlx::query!(
+        "with used as (
+           delete from telegram_link_codes
+           where code = $3 and created_at > now() - make_interval(mins => $4)
+           returning code
+         )
+         insert into telegram_chats (chat_id, name) select $1, $2 from used
+         on conflict (chat_id) do update set name = excluded.name, linked_at = now()",
+        chat_id,
+        name,
+        code,
+        ttl_minutes,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn unlink_telegram_chat(chat_id: i64, pool: &PgPool) -> Result<(), (StatusCode, String)> {
+    sqlx::query!("delete from telegram_chats where chat_id = $1", chat_id)
         .execute(pool)
         .await
         .map_err(utils::internal_error)?;
     Ok(())
 }
 
-// Given a vec of (position, id), set the position for each todo by id
-pub async fn set_positions(
-    position_data: Vec<(i32, i32)>,
+// Add todos to the top of the list in one go, the first description ending up on top
+pub async fn create_todos(
+    list_id: i32,
+    descriptions: &[String],
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        "insert into todos (list_id, description, position)
+         select $1, description,
+                (select coalesce(max(position), 0) from todos where list_id = $1)
+                  + cardinality($2::text[]) - ordinality + 1
+         from unnest($2::text[]) with ordinality as pasted (description, ordinality)",
+        list_id,
+        descriptions,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+// Add imported todos to the top of the list, keeping their order. Either all of them are added
+// or none are.
+pub async fn import_todos(
+    list_id: i32,
+    todos: &[ImportedTodo],
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let mut tx = pool.begin().await.map_err(utils::internal_error)?;
+    insert_imported(list_id, todos, &mut tx).await?;
+    tx.commit().await.map_err(utils::internal_error)?;
+    Ok(())
+}
+
+// Create the imported lists with their todos, all or nothing. Returns the new lists' ids.
+pub async fn import_lists(
+    lists: &[ImportedList],
+    pool: &PgPool,
+) -> Result<Vec<i32>, (StatusCode, String)> {
+    let mut tx = pool.begin().await.map_err(utils::internal_error)?;
+    let mut list_ids = Vec::new();
+    for list in lists {
+        let list_id = sqlx::query_scalar!(
+            "insert into lists (name) values ($1) returning id",
+            list.name
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(utils::internal_error)?;
+        insert_imported(list_id, &list.todos, &mut tx).await?;
+        list_ids.push(list_id);
+    }
+    tx.commit().await.map_err(utils::internal_error)?;
+    Ok(list_ids)
+}
+
+// Add todos to the top of a list, in the order given
+async fn insert_imported(
+    list_id: i32,
+    todos: &[ImportedTodo],
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<(), (StatusCode, String)> {
+    let max = sqlx::query_scalar!(
+        r#"select coalesce(max(position), 0) as "max!" from todos where list_id = $1"#,
+        list_id,
+    )
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(utils::internal_error)?;
+    for (i, todo) in todos.iter().enumerate() {
+        // Done todos without a known completion day count as done now
+        let todo_id = sqlx::query_scalar!(
+            "insert into todos (list_id, description, notes, priority, due_at, recurrence, position, done, completed_at)
+             values ($1, $2, $3, $4, $5, $6, $7, $8,
+                     case when $8 then coalesce($9::date::timestamptz, now()) end)
+             returning id",
+            list_id,
+            todo.description,
+            todo.notes,
+            todo.priority as _,
+            todo.due_at,
+            todo.recurrence as _,
+            max + (todos.len() - i) as f64,
+            todo.done,
+            todo.completed_on,
+        )
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(utils::internal_error)?;
+        if !todo.tags.is_empty() {
+            sqlx::query!(
+                "with tag as (
+                   insert into tags (name) select unnest($1::text[])
+                   on conflict (name) do update set name = excluded.name
+                   returning id
+                 )
+                 insert into todo_tags (todo_id, tag_id) select $2, tag.id from tag
+                 on conflict do nothing",
+                &todo.tags,
+                todo_id,
+            )
+            .execute(&mut **tx)
+            .await
+            .map_err(utils::internal_error)?;
+        }
+    }
+    Ok(())
+}
+
+// The todos of every list for todo.txt, list by list and top to bottom
+pub async fn get_todotxt_todos(pool: &PgPool) -> Result<Vec<TodoTxtTodo>, (StatusCode, String)> {
+    sqlx::query_as!(
+        TodoTxtTodo,
+        r#"select lists.name as list_name, todos.description, todos.done, todos.completed_at, todos.created_at,
+                todos.priority as "priority: Priority", todos.due_at,
+                array(select tags.name from todo_tags join tags on tags.id = todo_tags.tag_id
+                      where todo_tags.todo_id = todos.id order by tags.name) as "tags!"
+           from todos join lists on lists.id = todos.list_id
+           where todos.archived_at is null and todos.deleted_at is null
+           ORDER BY lists.id, todos.pinned desc, todos.position desc"#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// Newest first
+pub async fn get_revisions(
+    list_id: i32,
+    todo_id: i32,
+    pool: &PgPool,
+) -> Result<Vec<TodoRevision>, (StatusCode, String)> {
+    sqlx::query_as!(
+        TodoRevision,
+        "select todo_revisions.id, todo_revisions.description, todo_revisions.notes, todo_revisions.created_at
+         from todo_revisions join todos on todos.id = todo_revisions.todo_id
+         where todo_revisions.todo_id = $1 and todos.list_id = $2
+         ORDER BY todo_revisions.created_at desc, todo_revisions.id desc",
+        todo_id,
+        list_id,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// Put an earlier description and notes back. The ones being replaced become a revision
+// themselves, so restoring can be undone from the history too.
+pub async fn restore_revision(
+    list_id: i32,
+    todo_id: i32,
+    revision_id: i32,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let mut tx = pool.begin().await.map_err(utils::internal_error)?;
+    let revision = sqlx::query!(
+        "select todo_revisions.description, todo_revisions.notes
+         from todo_revisions join todos on todos.id = todo_revisions.todo_id
+         where todo_revisions.id = $1 and todo_revisions.todo_id = $2 and todos.list_id = $3",
+        revision_id,
+        todo_id,
+        list_id,
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(utils::internal_error)?
+    .ok_or((StatusCode::NOT_FOUND, String::from("Revision not found")))?;
+    sqlx::query!(
+        "insert into todo_revisions (todo_id, description, notes)
+         select id, description, notes from todos
+         where id = $1 and (description <> $2 or notes <> $3)",
+        todo_id,
+        revision.description,
+        revision.notes,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(utils::internal_error)?;
+    sqlx::query!(
+        "update todos set description = $2, notes = $3 where id = $1",
+        todo_id,
+        revision.description,
+        revision.notes,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(utils::internal_error)?;
+    tx.commit().await.map_err(utils::internal_error)?;
+    Ok(())
+}
+
+// Pending todos due on or before `today`, overdue ones first, then the most urgent
+pub async fn get_agenda(
+    today: Date,
+    pool: &PgPool,
+) -> Result<Vec<AgendaTodo>, (StatusCode, String)> {
+    sqlx::query_as!(
+        AgendaTodo,
+        r#"select todos.id, todos.list_id, lists.name as list_name, todos.description,
+             todos.priority as "priority: Priority", todos.due_at as "due_at!"
+           from todos join lists on lists.id = todos.list_id
+           where not todos.done and todos.due_at <= $1
+             and todos.archived_at is null and todos.deleted_at is null
+           ORDER BY todos.due_at < $1 desc, todos.priority desc, todos.due_at, todos.pinned desc, todos.id"#,
+        today,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+// How many days in a row, up to today, at least one todo has been completed.
+// A streak that ran until yesterday still counts while today has no completions yet.
+// The part up to yesterday is worked out once a day, and worked out again when the
+// todos_completion_streaks trigger forgets it because a todo of an earlier day changed.
+pub async fn get_streak(today: Date, pool: &PgPool) -> Result<i64, (StatusCode, String)> {
+    let cached = sqlx::query_scalar!("select days from completion_streaks where day = $1", today)
+        .fetch_optional(pool)
+        .await
+        .map_err(utils::internal_error)?;
+    let until_yesterday = match cached {
+        Some(days) => days,
+        None => {
+            // Consecutive days share an island: day minus its rank is the same for all of them
+            sqlx::query_scalar!(
+                r#"with days as (
+                     select distinct (completed_at at time zone 'utc')::date as day from todos
+                     where completed_at is not null and deleted_at is null
+                       and (completed_at at time zone 'utc')::date < $1),
+                   islands as (
+                     select day, day - (row_number() over (ORDER BY day))::integer as island from days),
+                   streak as (
+                     select count(*)::integer as days from islands
+                     where island = (select island from islands where day = $1 - 1))
+                   insert into completion_streaks (day, days) select $1, days from streak
+                   on conflict (day) do update set days = excluded.days
+                   returning days"#,
+                today,
+            )
+            .fetch_one(pool)
+            .await
+            .map_err(utils::internal_error)?
+        }
+    };
+    let completed_today = sqlx::query_scalar!(
+        r#"select exists(
+             select 1 from todos
+             where (completed_at at time zone 'utc')::date = $1 and deleted_at is null) as "exists!""#,
+        today,
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(i64::from(until_yesterday) + i64::from(completed_today))
+}
+
+// The (id, name) of every list, with all their current todos in each list's own order
+pub async fn get_board(
+    pool: &PgPool,
+) -> Result<(Vec<(i32, String)>, Vec<BoardTodo>), (StatusCode, String)> {
+    let lists = sqlx::query!("select id, name from lists ORDER BY id")
+        .fetch_all(pool)
+        .await
+        .map_err(utils::internal_error)?
+        .into_iter()
+        .map(|list| (list.id, list.name))
+        .collect();
+    let todos = sqlx::query_as!(
+        BoardTodo,
+        "select id, list_id, description, done, pinned from todos
+         where archived_at is null and deleted_at is null
+         ORDER BY list_id, pinned desc, position desc",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok((lists, todos))
+}
+
+pub async fn get_list_id(todo_id: i32, pool: &PgPool) -> Result<i32, (StatusCode, String)> {
+    sqlx::query_scalar!(
+        "select list_id from todos where id = $1 and archived_at is null and deleted_at is null",
+        todo_id,
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(utils::internal_error)?
+    .ok_or((StatusCode::NOT_FOUND, String::from("Todo not found")))
+}
+
+// Move the given todos to the top of another list, keeping their order.
+// Used by bulk actions and by dragging a todo onto a list in the sidebar.
+pub async fn move_todos(
+    list_id: i32,
+    ids: &[i32],
+    target_list_id: i32,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let mut tx = pool.begin().await.map_err(utils::internal_error)?;
+    // the list can't be deleted until the todos are in it
+    sqlx::query_scalar!(
+        "select id from lists where id = $1 for share",
+        target_list_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(utils::internal_error)?
+    .ok_or((StatusCode::NOT_FOUND, String::from("List not found")))?;
+    sqlx::query!(
+        "update todos set list_id = $3, position = base.max + moved.rn
+         from (select coalesce(max(position), 0) as max from todos where list_id = $3) as base,
+              (select id, row_number() over (ORDER BY position) as rn from todos
+               where list_id = $1 and id = ANY($2) and deleted_at is null) as moved
+         where todos.id = moved.id",
+        list_id,
+        ids,
+        target_list_id,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(utils::internal_error)?;
+    // Todos only wait on todos in the same list, see add_blocker. One left waiting on a todo in
+    // another list could never be checked off there, and wouldn't show what it's waiting on.
+    sqlx::query!(
+        "delete from todo_dependencies using todos, todos as blockers
+         where todo_dependencies.todo_id = todos.id and todo_dependencies.blocker_id = blockers.id
+           and todos.list_id <> blockers.list_id and (todos.id = ANY($1) or blockers.id = ANY($1))",
+        ids,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(utils::internal_error)?;
+    tx.commit().await.map_err(utils::internal_error)?;
+    Ok(())
+}
+
+// Check or uncheck a todo. Todos can't be checked while they are blocked.
+// Completing a recurring todo adds its next occurrence to the top of the list, and the completed
+// one stops recurring so that unchecking and checking it again doesn't add another.
+pub async fn set_done(
+    list_id: i32,
+    todo_id: i32,
+    done: bool,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let mut tx = pool.begin().await.map_err(utils::internal_error)?;
+    if !check_todo(list_id, todo_id, done, &mut tx).await? {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("Can't complete a todo while the todos blocking it are still open"),
+        ));
+    }
+    tx.commit().await.map_err(utils::internal_error)?;
+    Ok(())
+}
+
+// set_done within a transaction. Returns false, changing nothing, when the todo is blocked.
+async fn check_todo(
+    list_id: i32,
+    todo_id: i32,
+    done: bool,
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<bool, (StatusCode, String)> {
+    if done {
+        let row = sqlx::query!(
+            r#"select exists (
+                 select 1 from todo_dependencies join todos as blockers on blockers.id = todo_dependencies.blocker_id
+                 where todo_dependencies.todo_id = $1 and not blockers.done
+                   and blockers.archived_at is null and blockers.deleted_at is null
+               ) as "blocked!""#,
+            todo_id,
+        )
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(utils::internal_error)?;
+        if row.blocked {
+            return Ok(false);
+        }
+    }
+    mark_done(list_id, todo_id, done, tx).await?;
+    Ok(true)
+}
+
+// check_todo without looking at what blocks the todo. Checking off a recurring todo adds its
+// next occurrence.
+async fn mark_done(
+    list_id: i32,
+    todo_id: i32,
+    done: bool,
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<(), (StatusCode, String)> {
+    let todo = sqlx::query!(
+        r#"update todos set done = $1 where id = $2 and list_id = $3
+           returning description, notes, due_at, priority as "priority: Priority", recurrence as "recurrence: Recurrence",
+                     remind_days_before, estimate_minutes, pinned, color as "color: Color", recurrence_day"#,
+        done,
+        todo_id,
+        list_id,
+    )
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(utils::internal_error)?;
+
+    if let Some(todo) = todo.filter(|_| done) {
+        if let Some(recurrence) = todo.recurrence {
+            let due_at = todo
+                .due_at
+                .unwrap_or_else(|| OffsetDateTime::now_utc().date());
+            // the day of the month of the first occurrence, unless the due date was moved since
+            let day = todo
+                .recurrence_day
+                .and_then(|day| u8::try_from(day).ok())
+                .unwrap_or(due_at.day());
+            let next = sqlx::query!(
+                "insert into todos (list_id, description, notes, position, due_at, priority, recurrence, remind_days_before, pinned, color, estimate_minutes, recurrence_day)
+                 values ($1, $2, $3, (select coalesce(max(position), 0) from todos where list_id = $1) + 1, $4, $5, $6, $7, $8, $9, $10, $11)
+                 returning id",
+                list_id,
+                todo.description,
+                todo.notes,
+                recurrence.next_due_at(due_at, day),
+                todo.priority as _,
+                recurrence as _,
+                todo.remind_days_before,
+                todo.pinned,
+                todo.color as _,
+                todo.estimate_minutes,
+                i16::from(day),
+            )
+            .fetch_one(&mut **tx)
+            .await
+            .map_err(utils::internal_error)?;
+            sqlx::query!(
+                "insert into todo_tags (todo_id, tag_id) select $1, tag_id from todo_tags where todo_id = $2",
+                next.id,
+                todo_id,
+            )
+            .execute(&mut **tx)
+            .await
+            .map_err(utils::internal_error)?;
+            sqlx::query!("update todos set recurrence = null where id = $1", todo_id)
+                .execute(&mut **tx)
+                .await
+                .map_err(utils::internal_error)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Tag a todo, creating the tag if nobody has used it before
+pub async fn add_tag(
+    list_id: i32,
+    todo_id: i32,
+    name: &str,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        "with tag as (
+           insert into tags (name) values ($1)
+           on conflict (name) do update set name = excluded.name
+           returning id
+         )
+         insert into todo_tags (todo_id, tag_id)
+         select todos.id, tag.id from todos, tag where todos.id = $2 and todos.list_id = $3
+         on conflict do nothing",
+        name,
+        todo_id,
+        list_id,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+pub async fn tag_todos(
+    list_id: i32,
+    ids: &[i32],
+    name: &str,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        "with tag as (
+           insert into tags (name) values ($1)
+           on conflict (name) do update set name = excluded.name
+           returning id
+         )
+         insert into todo_tags (todo_id, tag_id)
+         select todos.id, tag.id from todos, tag where todos.id = ANY($2) and todos.list_id = $3
+         on conflict do nothing",
+        name,
+        ids,
+        list_id,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+pub async fn remove_tag(
+    list_id: i32,
+    todo_id: i32,
+    name: &str,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        "delete from todo_tags using tags, todos
+         where todo_tags.tag_id = tags.id and todo_tags.todo_id = todos.id
+           and todos.id = $1 and todos.list_id = $2 and tags.name = $3",
+        todo_id,
+        list_id,
+        name,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+pub async fn set_color(
+    list_id: i32,
+    todo_id: i32,
+    color: Option<Color>,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        "update todos set color = $1 where id = $2 and list_id = $3",
+        color as _,
+        todo_id,
+        list_id,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+// The (id, description) of the open todos in the list that could be added as blockers of this one
+pub async fn get_blocker_candidates(
+    list_id: i32,
+    todo_id: i32,
+    pool: &PgPool,
+) -> Result<Vec<(i32, String)>, (StatusCode, String)> {
+    let rows = sqlx::query!(
+        "select id, description from todos
+         where list_id = $1 and id <> $2 and not done and archived_at is null and deleted_at is null
+           and id not in (select blocker_id from todo_dependencies where todo_id = $2)
+         ORDER BY pinned desc, position desc",
+        list_id,
+        todo_id,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.id, row.description))
+        .collect())
+}
+
+// Mark `todo_id` as blocked by `blocker_id`. Both have to be in the list,
+// and the blocker can't already be waiting on the todo, directly or through other todos.
+pub async fn add_blocker(
+    list_id: i32,
+    todo_id: i32,
+    blocker_id: i32,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let row = sqlx::query!(
+        r#"with recursive waiting_on as (
+             select blocker_id from todo_dependencies where todo_id = $2
+             union
+             select todo_dependencies.blocker_id from todo_dependencies
+             join waiting_on on todo_dependencies.todo_id = waiting_on.blocker_id
+           )
+           select exists (select 1 from waiting_on where blocker_id = $1) as "cycle!""#,
+        todo_id,
+        blocker_id,
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    if todo_id == blocker_id || row.cycle {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("A todo can't end up waiting on itself"),
+        ));
+    }
+
+    sqlx::query!(
+        "insert into todo_dependencies (todo_id, blocker_id)
+         select todos.id, blockers.id from todos, todos as blockers
+         where todos.id = $1 and todos.list_id = $3 and blockers.id = $2 and blockers.list_id = $3
+         on conflict do nothing",
+        todo_id,
+        blocker_id,
+        list_id,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+pub async fn remove_blocker(
+    list_id: i32,
+    todo_id: i32,
+    blocker_id: i32,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        "delete from todo_dependencies using todos
+         where todo_dependencies.todo_id = todos.id and todos.id = $1 and todos.list_id = $2
+           and todo_dependencies.blocker_id = $3",
+        todo_id,
+        list_id,
+        blocker_id,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+// Pin an unpinned todo or unpin a pinned one
+pub async fn toggle_pinned(
+    list_id: i32,
+    todo_id: i32,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        "update todos set pinned = not pinned where id = $1 and list_id = $2",
+        todo_id,
+        list_id,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+// Swap places with the next todo up (or down) the list that the filter lets through.
+// Pinned todos only swap with pinned todos, and unpinned with unpinned.
+// Returns the id of that todo, or None when the todo is already at the top (or bottom).
+pub async fn swap_with_neighbour(
+    list_id: i32,
+    todo_id: i32,
+    up: bool,
+    filter: &TodoFilter,
+    pool: &PgPool,
+) -> Result<Option<i32>, (StatusCode, String)> {
+    let today = OffsetDateTime::now_utc().date();
+    let rows = sqlx::query!(
+        r#"with moving as (
+             select id, position, pinned from todos
+             where id = $1 and list_id = $2 and archived_at is null and deleted_at is null
+           ),
+           neighbour as (
+             select todos.id, todos.position from todos, moving
+             where todos.list_id = $2 and todos.archived_at is null and todos.deleted_at is null
+               and todos.pinned = moving.pinned
+               and (case when $3 then todos.position > moving.position else todos.position < moving.position end)
+               and ($4::text = 'all' or todos.done = ($4::text = 'completed'))
+               and ($5::bool is not true or (not todos.done and todos.due_at < $6))
+               and ($7::text is null or exists (
+                    select 1 from todo_tags join tags on tags.id = todo_tags.tag_id
+                    where todo_tags.todo_id = todos.id and tags.name = $7))
+               and ($8::todo_color is null or todos.color = $8)
+               and ($9::bool is not true or todos.completed_at >= $10)
+             ORDER BY case when $3 then todos.position end asc,
+                      case when not $3 then todos.position end desc
+             limit 1
+           )
+           update todos
+           set position = case when todos.id = moving.id then neighbour.position else moving.position end
+           from moving, neighbour
+           where todos.id in (moving.id, neighbour.id)
+           returning neighbour.id as "neighbour_id!""#,
+        todo_id,
+        list_id,
+        up,
+        filter.status.as_str(),
+        filter.overdue,
+        today,
+        filter.tag,
+        filter.color as _,
+        filter.completed_today,
+        today.midnight().assume_utc(),
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(rows.first().map(|row| row.neighbour_id))
+}
+
+// Show the given todos in this order (top to bottom), only updating the ones that moved.
+// Todos that aren't in the list are ignored.
+pub async fn reorder(
+    list_id: i32,
+    order: &[i32],
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let positions = match reposition_groups(list_id, order, pool).await? {
+        Some(positions) => positions,
+        None => {
+            rebalance_positions(list_id, pool).await?;
+            reposition_groups(list_id, order, pool).await?.ok_or((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                String::from("Could not find room to reorder the todos"),
+            ))?
+        }
+    };
+    set_positions(list_id, positions, pool).await
+}
+
+// Pinned todos are always shown above the others, so the pinned and unpinned todos
+// are each put in order on their own. See reposition.
+async fn reposition_groups(
+    list_id: i32,
+    order: &[i32],
+    pool: &PgPool,
+) -> Result<Option<Vec<(f64, i32)>>, (StatusCode, String)> {
+    let rows = sqlx::query!(
+        "select id, position, pinned from todos where list_id = $1 and id = ANY($2)",
+        list_id,
+        order,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    let pinned_ids: HashSet<i32> = rows
+        .iter()
+        .filter(|row| row.pinned)
+        .map(|row| row.id)
+        .collect();
+    let current: HashMap<i32, f64> = rows.into_iter().map(|row| (row.id, row.position)).collect();
+
+    let (pinned, unpinned): (Vec<i32>, Vec<i32>) =
+        order.iter().partition(|id| pinned_ids.contains(id));
+    Ok(reposition(&pinned, &current)
+        .zip(reposition(&unpinned, &current))
+        .map(|(mut positions, unpinned_positions)| {
+            positions.extend(unpinned_positions);
+            positions
+        }))
+}
+
+// Renumber the positions in a list 1, 2, 3... keeping the order, once repeatedly slotting todos
+// in between the same neighbours has used up the room between them
+async fn rebalance_positions(list_id: i32, pool: &PgPool) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        "update todos set position = ranked.rn
+         from (select id, row_number() over (ORDER BY position, id) as rn from todos where list_id = $1) as ranked
+         where todos.id = ranked.id",
+        list_id,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+// Renumber every list that has two neighbouring todos closer together than `min_gap`
+// (or at the same position) like rebalance_positions does, returning the ids of those lists
+pub async fn rebalance_crowded_lists(
+    min_gap: f64,
+    pool: &PgPool,
+) -> Result<Vec<i32>, (StatusCode, String)> {
+    sqlx::query_scalar!(
+        r#"with ranked as (
+             select id, list_id, row_number() over (PARTITION BY list_id ORDER BY position, id) as rn,
+                    position - lag(position) over (PARTITION BY list_id ORDER BY position, id) as gap
+             from todos),
+           crowded as (select distinct list_id from ranked where gap < $1)
+           update todos set position = ranked.rn
+           from ranked join crowded using (list_id)
+           where todos.id = ranked.id and todos.position <> ranked.rn
+           returning todos.list_id as "list_id!""#,
+        min_gap,
+    )
+    .fetch_all(pool)
+    .await
+    .map(|mut list_ids| {
+        list_ids.sort_unstable();
+        list_ids.dedup();
+        list_ids
+    })
+    .map_err(utils::internal_error)
+}
+
+// Given a vec of (position, id), set the position for each todo in the list by id
+async fn set_positions(
+    list_id: i32,
+    position_data: Vec<(f64, i32)>,
     pool: &PgPool,
 ) -> Result<(), (StatusCode, String)> {
     let positions = position_data
@@ -42,13 +1517,176 @@ pub async fn set_positions(
     sqlx::query!(
         "update todos as original
          set position=new.position
-         from (select unnest($1::int4[]) as position, unnest($2::int4[]) as id) as new
-         where original.id=new.id;",
+         from (select unnest($1::float8[]) as position, unnest($2::int4[]) as id) as new
+         where original.id=new.id and original.list_id=$3;",
         &positions[..],
         &ids[..],
+        list_id,
     )
     .execute(pool)
     .await
     .map_err(utils::internal_error)?;
     Ok(())
 }
+
+// Pending todos that are due within their reminder window and haven't been reminded about yet.
+// Todos without their own number of days use `window_days`.
+pub async fn get_due_todos(
+    window_days: i32,
+    pool: &PgPool,
+) -> Result<Vec<DueTodo>, (StatusCode, String)> {
+    let today = OffsetDateTime::now_utc().date();
+    sqlx::query_as!(
+        DueTodo,
+        r#"select todos.id, lists.name as list_name, todos.description, todos.due_at as "due_at!"
+           from todos join lists on lists.id = todos.list_id
+           where not todos.done and todos.archived_at is null and todos.deleted_at is null
+             and todos.reminded_at is null and todos.due_at is not null
+             and todos.due_at - coalesce(todos.remind_days_before, $1) <= $2
+           ORDER BY todos.due_at asc, lists.name asc, todos.position desc"#,
+        window_days,
+        today,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+pub async fn mark_reminded(ids: &[i32], pool: &PgPool) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        "update todos set reminded_at = now() where id = ANY($1)",
+        ids
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn new_list(name: &str, pool: &PgPool) -> i32 {
+        sqlx::query_scalar("insert into lists (name) values ($1) returning id")
+            .bind(name)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn new_todo(list_id: i32, description: &str, pool: &PgPool) -> i32 {
+        sqlx::query_scalar(
+            "insert into todos (list_id, description, position) values ($1, $2, 1) returning id",
+        )
+        .bind(list_id)
+        .bind(description)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn only_archived_todos_are_restored(pool: PgPool) {
+        let home = new_list("Home", &pool).await;
+        let todo = new_todo(home, "Buy paint", &pool).await;
+
+        let error = restore(home, todo, &pool).await.unwrap_err();
+        assert_eq!(error.0, StatusCode::NOT_FOUND);
+
+        sqlx::query("update todos set done = true, archived_at = now() where id = $1")
+            .bind(todo)
+            .execute(&pool)
+            .await
+            .unwrap();
+        restore(home, todo, &pool).await.unwrap();
+        assert!(restore(home, todo, &pool).await.is_err());
+    }
+
+    #[sqlx::test]
+    async fn moving_a_todo_drops_what_it_waits_on_and_what_waits_on_it(pool: PgPool) {
+        let home = new_list("Home", &pool).await;
+        let work = new_list("Work", &pool).await;
+        let buy_paint = new_todo(home, "Buy paint", &pool).await;
+        let paint_fence = new_todo(home, "Paint the fence", &pool).await;
+        let invite_friends = new_todo(home, "Invite friends", &pool).await;
+        add_blocker(home, paint_fence, buy_paint, &pool)
+            .await
+            .unwrap();
+        add_blocker(home, invite_friends, paint_fence, &pool)
+            .await
+            .unwrap();
+
+        move_todos(home, &[paint_fence], work, &pool).await.unwrap();
+
+        let moved = get_todo(work, paint_fence, &pool).await.unwrap();
+        assert!(moved.blocker_ids.is_empty());
+        let left_behind = get_todo(home, invite_friends, &pool).await.unwrap();
+        assert!(left_behind.blocker_ids.is_empty());
+        set_done(work, paint_fence, true, &pool).await.unwrap();
+    }
+
+    #[sqlx::test]
+    async fn moving_todos_together_keeps_their_dependencies(pool: PgPool) {
+        let home = new_list("Home", &pool).await;
+        let work = new_list("Work", &pool).await;
+        let buy_paint = new_todo(home, "Buy paint", &pool).await;
+        let paint_fence = new_todo(home, "Paint the fence", &pool).await;
+        add_blocker(home, paint_fence, buy_paint, &pool)
+            .await
+            .unwrap();
+
+        move_todos(home, &[buy_paint, paint_fence], work, &pool)
+            .await
+            .unwrap();
+
+        let moved = get_todo(work, paint_fence, &pool).await.unwrap();
+        assert_eq!(moved.blocker_ids, [buy_paint]);
+    }
+
+    #[sqlx::test]
+    async fn moving_todos_to_a_missing_list_is_not_found(pool: PgPool) {
+        let home = new_list("Home", &pool).await;
+        let todo = new_todo(home, "Buy paint", &pool).await;
+
+        let error = move_todos(home, &[todo], home + 1, &pool)
+            .await
+            .unwrap_err();
+        assert_eq!(error.0, StatusCode::NOT_FOUND);
+    }
+
+    #[sqlx::test]
+    async fn completing_todos_together_skips_those_still_blocked(pool: PgPool) {
+        let home = new_list("Home", &pool).await;
+        let buy_paint = new_todo(home, "Buy paint", &pool).await;
+        let paint_fence = new_todo(home, "Paint the fence", &pool).await;
+        let find_brush = new_todo(home, "Find a brush", &pool).await;
+        let paint_shed = new_todo(home, "Paint the shed", &pool).await;
+        let clean_brush = new_todo(home, "Clean the brush", &pool).await;
+        add_blocker(home, paint_fence, buy_paint, &pool)
+            .await
+            .unwrap();
+        add_blocker(home, paint_shed, find_brush, &pool)
+            .await
+            .unwrap();
+        add_blocker(home, clean_brush, paint_shed, &pool)
+            .await
+            .unwrap();
+
+        let skipped = complete_todos(
+            home,
+            &[paint_fence, buy_paint, paint_shed, clean_brush],
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(skipped.len(), 2);
+        assert!(skipped.contains(&String::from("Paint the shed")));
+        assert!(skipped.contains(&String::from("Clean the brush")));
+        assert!(get_todo(home, buy_paint, &pool).await.unwrap().done);
+        assert!(get_todo(home, paint_fence, &pool).await.unwrap().done);
+        assert!(!get_todo(home, paint_shed, &pool).await.unwrap().done);
+        assert!(!get_todo(home, clean_brush, &pool).await.unwrap().done);
+    }
+}
@@ -0,0 +1,578 @@
+use std::fmt;
+
+use axum::Json;
+use serde::Deserialize;
+use time::{
+    format_description::{well_known::Rfc3339, FormatItem},
+    macros::format_description,
+    Date, OffsetDateTime,
+};
+
+use super::{routes::validate_description, Priority, Recurrence};
+
+// A task read from an import file, ready to be inserted
+#[derive(Debug, Clone)]
+pub struct ImportedTodo {
+    pub description: String,
+    pub notes: String,
+    pub priority: Priority,
+    pub due_at: Option<Date>,
+    pub recurrence: Option<Recurrence>,
+    pub done: bool,
+    // the day it was done, when known
+    pub completed_on: Option<Date>,
+    pub tags: Vec<String>,
+}
+
+// What happened to one line of the export, shown in the summary after importing
+#[derive(Debug, Clone)]
+pub struct ImportRow {
+    pub line: u64,
+    pub content: String,
+    pub outcome: ImportOutcome,
+}
+
+#[derive(Debug, Clone)]
+pub enum ImportOutcome {
+    Imported,
+    Skipped(String),
+    Failed(String),
+}
+
+// A list to create along with its todos, for the imports that bring in whole lists
+#[derive(Debug, Clone)]
+pub struct ImportedList {
+    pub name: String,
+    pub todos: Vec<ImportedTodo>,
+}
+
+// The apps whole lists can be imported from, see list_import. Named in /import/:source paths.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListSource {
+    Trello,
+    MicrosoftTodo,
+}
+
+impl ListSource {
+    /// Read an uploaded export into the lists it holds, along with one summary row per task.
+    pub fn parse(self, file: &[u8]) -> Result<(Vec<ImportedList>, Vec<ImportRow>), String> {
+        let (lists, rows) = match self {
+            ListSource::Trello => parse_trello(file)?,
+            ListSource::MicrosoftTodo => parse_microsoft_todo(file)?,
+        };
+        if lists.is_empty() {
+            return Err(String::from("There are no lists in this export"));
+        }
+        Ok((lists, rows))
+    }
+}
+
+impl fmt::Display for ListSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ListSource::Trello => "trello",
+            ListSource::MicrosoftTodo => "microsoft_todo",
+        })
+    }
+}
+
+// Todoist writes dates the way they were typed, in the exporting user's language.
+// These cover the English forms it uses for explicit dates.
+const DATE_FORMATS: &[&[FormatItem<'static>]] = &[
+    format_description!("[year]-[month]-[day]"),
+    format_description!("[day padding:none] [month repr:short] [year]"),
+    format_description!("[month repr:short] [day padding:none] [year]"),
+];
+
+/// Read a Todoist CSV export (TYPE, CONTENT, DESCRIPTION, PRIORITY, INDENT, ..., DATE columns).
+/// Returns the tasks to insert, in file order, along with one summary row per non-empty line.
+/// Todoist subtasks (INDENT > 1) are flattened: they keep their place right after their parent.
+pub fn parse_todoist(csv: &[u8]) -> Result<(Vec<ImportedTodo>, Vec<ImportRow>), String> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(csv);
+    let headers = reader
+        .headers()
+        .map_err(|err| format!("Could not read the CSV header: {err}"))?
+        .clone();
+    let column = |name: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let (Some(type_col), Some(content_col)) = (column("TYPE"), column("CONTENT")) else {
+        return Err(String::from(
+            "This doesn't look like a Todoist export: the TYPE and CONTENT columns are missing",
+        ));
+    };
+    let description_col = column("DESCRIPTION");
+    let priority_col = column("PRIORITY");
+    let date_col = column("DATE");
+
+    let today = OffsetDateTime::now_utc().date();
+    let mut todos: Vec<ImportedTodo> = Vec::new();
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|err| format!("Could not read the CSV: {err}"))?;
+        let line = record.position().map_or(0, |position| position.line());
+        let field = |col: Option<usize>| col.and_then(|col| record.get(col)).unwrap_or("").trim();
+        let content = field(Some(content_col)).to_string();
+
+        let outcome = match field(Some(type_col)).to_ascii_lowercase().as_str() {
+            // Todoist separates sections with blank lines
+            "" if content.is_empty() => continue,
+            "task" if content.is_empty() => {
+                ImportOutcome::Failed(String::from("The task has no content"))
+            }
+            "task" => match parse_date(field(date_col), today) {
+                Ok((due_at, recurrence)) => {
+                    todos.push(ImportedTodo {
+                        description: content.clone(),
+                        notes: field(description_col).to_string(),
+                        priority: parse_priority(field(priority_col)),
+                        due_at,
+                        recurrence,
+                        done: false,
+                        completed_on: None,
+                        tags: vec![],
+                    });
+                    ImportOutcome::Imported
+                }
+                Err(err) => ImportOutcome::Failed(err),
+            },
+            // Comments are kept in the notes of the task above them
+            "note" => match todos.last_mut() {
+                Some(todo) => {
+                    if !todo.notes.is_empty() {
+                        todo.notes.push_str("\n\n");
+                    }
+                    todo.notes.push_str(&content);
+                    continue;
+                }
+                None => ImportOutcome::Skipped(String::from("A comment without a task")),
+            },
+            "section" => ImportOutcome::Skipped(String::from("Sections aren't imported")),
+            other => ImportOutcome::Skipped(format!("Unknown row type '{other}'")),
+        };
+        rows.push(ImportRow {
+            line,
+            content,
+            outcome,
+        });
+    }
+    Ok((todos, rows))
+}
+
+// Todoist exports p1 (the most urgent) as 4 and the default p4 as 1
+fn parse_priority(priority: &str) -> Priority {
+    match priority {
+        "4" => Priority::Urgent,
+        "3" => Priority::High,
+        _ => Priority::Normal,
+    }
+}
+
+fn parse_date(date: &str, today: Date) -> Result<(Option<Date>, Option<Recurrence>), String> {
+    let lower = date.to_ascii_lowercase();
+    match lower.as_str() {
+        "" => return Ok((None, None)),
+        "today" => return Ok((Some(today), None)),
+        "tomorrow" => return Ok((Some(today.next_day().unwrap_or(today)), None)),
+        "every day" | "daily" => return Ok((Some(today), Some(Recurrence::Daily))),
+        "every week" | "weekly" => return Ok((Some(today), Some(Recurrence::Weekly))),
+        "every month" | "monthly" => return Ok((Some(today), Some(Recurrence::Monthly))),
+        _ => {}
+    }
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| Date::parse(date, format).ok())
+        .map(|due_at| (Some(due_at), None))
+        .ok_or_else(|| format!("Couldn't understand the due date '{date}'"))
+}
+
+// The parts of a Trello board's JSON export (Menu > Print, export and share > Export as JSON)
+// that become a list
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloBoard {
+    name: String,
+    #[serde(default)]
+    lists: Vec<TrelloList>,
+    #[serde(default)]
+    cards: Vec<TrelloCard>,
+    #[serde(default)]
+    checklists: Vec<TrelloChecklist>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloList {
+    id: String,
+    #[serde(default)]
+    closed: bool,
+    #[serde(default)]
+    pos: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloCard {
+    id: String,
+    name: String,
+    #[serde(default)]
+    desc: String,
+    id_list: String,
+    #[serde(default)]
+    closed: bool,
+    #[serde(default)]
+    pos: f64,
+    // an RFC 3339 timestamp
+    due: Option<String>,
+    #[serde(default)]
+    due_complete: bool,
+    #[serde(default)]
+    labels: Vec<TrelloLabel>,
+}
+
+#[derive(Deserialize)]
+struct TrelloLabel {
+    #[serde(default)]
+    name: String,
+    color: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloChecklist {
+    id_card: String,
+    name: String,
+    #[serde(default)]
+    pos: f64,
+    #[serde(default)]
+    check_items: Vec<TrelloCheckItem>,
+}
+
+#[derive(Deserialize)]
+struct TrelloCheckItem {
+    name: String,
+    // "complete" or "incomplete"
+    state: String,
+    #[serde(default)]
+    pos: f64,
+}
+
+/// Read a Trello board's JSON export into a list named after the board, with one summary row per
+/// card, numbered in board order. Cards are ordered column by column, labels become tags and
+/// checklists are written into the notes as task lists, as todos have no subtasks. Archived
+/// cards and columns are skipped.
+fn parse_trello(json: &[u8]) -> Result<(Vec<ImportedList>, Vec<ImportRow>), String> {
+    let Json(board) = Json::<TrelloBoard>::from_bytes(json).map_err(|err| {
+        format!(
+            "This doesn't look like a Trello export: {}",
+            err.body_text()
+        )
+    })?;
+    let name = board.name.trim();
+    if name.is_empty() {
+        return Err(String::from("The Trello board has no name"));
+    }
+
+    let mut cards: Vec<(Option<&TrelloList>, &TrelloCard)> = board
+        .cards
+        .iter()
+        .map(|card| {
+            (
+                board.lists.iter().find(|list| list.id == card.id_list),
+                card,
+            )
+        })
+        .collect();
+    cards.sort_by(|(a_list, a), (b_list, b)| {
+        let column = |list: &Option<&TrelloList>| list.map_or(f64::MAX, |list| list.pos);
+        column(a_list)
+            .total_cmp(&column(b_list))
+            .then(a.pos.total_cmp(&b.pos))
+    });
+
+    let mut todos = Vec::new();
+    let mut rows = Vec::new();
+    for (index, (list, card)) in cards.into_iter().enumerate() {
+        let content = card.name.trim().to_string();
+        let outcome = if card.closed {
+            ImportOutcome::Skipped(String::from("The card is archived"))
+        } else if list.is_none_or(|list| list.closed) {
+            ImportOutcome::Skipped(String::from("The card's list is archived"))
+        } else if content.is_empty() {
+            ImportOutcome::Failed(String::from("The card has no name"))
+        } else if let Err(err) = validate_description(&content) {
+            ImportOutcome::Failed(err)
+        } else {
+            match trello_due(card.due.as_deref()) {
+                Ok(due_at) => {
+                    todos.push(ImportedTodo {
+                        description: content.clone(),
+                        notes: trello_notes(card, &board.checklists),
+                        priority: Priority::Normal,
+                        due_at,
+                        recurrence: None,
+                        done: card.due_complete,
+                        completed_on: None,
+                        tags: trello_tags(&card.labels),
+                    });
+                    ImportOutcome::Imported
+                }
+                Err(err) => ImportOutcome::Failed(err),
+            }
+        };
+        rows.push(ImportRow {
+            line: index as u64 + 1,
+            content,
+            outcome,
+        });
+    }
+    let list = ImportedList {
+        name: name.to_string(),
+        todos,
+    };
+    Ok((vec![list], rows))
+}
+
+fn trello_due(due: Option<&str>) -> Result<Option<Date>, String> {
+    due.map(|due| {
+        OffsetDateTime::parse(due, &Rfc3339)
+            .map(OffsetDateTime::date)
+            .map_err(|_| format!("Couldn't understand the due date '{due}'"))
+    })
+    .transpose()
+}
+
+// Unnamed labels are known by their color
+fn trello_tags(labels: &[TrelloLabel]) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for label in labels {
+        let tag = match label.name.trim() {
+            "" => label.color.as_deref().unwrap_or_default(),
+            name => name,
+        };
+        if !tag.is_empty() && !tags.iter().any(|existing| existing == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+// The card's description followed by its checklists
+fn trello_notes(card: &TrelloCard, checklists: &[TrelloChecklist]) -> String {
+    let mut card_checklists: Vec<&TrelloChecklist> = checklists
+        .iter()
+        .filter(|checklist| checklist.id_card == card.id)
+        .collect();
+    card_checklists.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+
+    let mut notes = card.desc.trim().to_string();
+    for checklist in card_checklists {
+        let mut items: Vec<&TrelloCheckItem> = checklist.check_items.iter().collect();
+        items.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+        push_checklist(
+            &mut notes,
+            &checklist.name,
+            items
+                .into_iter()
+                .map(|item| (item.state == "complete", item.name.as_str())),
+        );
+    }
+    notes
+}
+
+// Write a checklist at the end of the notes as a titled markdown task list
+fn push_checklist<'a>(
+    notes: &mut String,
+    title: &str,
+    items: impl IntoIterator<Item = (bool, &'a str)>,
+) {
+    if !notes.is_empty() {
+        notes.push_str("\n\n");
+    }
+    notes.push_str(&format!("**{}**\n", title.trim()));
+    for (checked, name) in items {
+        let check = if checked { 'x' } else { ' ' };
+        notes.push_str(&format!("\n- [{check}] {}", name.trim()));
+    }
+}
+
+// A Microsoft To Do export: the Graph API's GET /me/todo/lists response, with each list's tasks
+// (GET /me/todo/lists/{id}/tasks) added to it as `tasks`
+#[derive(Deserialize)]
+struct MicrosoftTodoExport {
+    value: Vec<MicrosoftTodoList>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MicrosoftTodoList {
+    display_name: String,
+    #[serde(default)]
+    tasks: Vec<MicrosoftTodoTask>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MicrosoftTodoTask {
+    title: String,
+    // "notStarted", "inProgress", "completed", ...
+    #[serde(default)]
+    status: String,
+    // "low", "normal" or "high"
+    #[serde(default)]
+    importance: String,
+    body: Option<MicrosoftTodoBody>,
+    due_date_time: Option<MicrosoftTodoDateTime>,
+    completed_date_time: Option<MicrosoftTodoDateTime>,
+    recurrence: Option<MicrosoftTodoRecurrence>,
+    #[serde(default)]
+    categories: Vec<String>,
+    #[serde(default)]
+    checklist_items: Vec<MicrosoftTodoChecklistItem>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MicrosoftTodoBody {
+    #[serde(default)]
+    content: String,
+    // "text" or "html"
+    #[serde(default)]
+    content_type: String,
+}
+
+// e.g. "2026-10-17T00:00:00.0000000", in the time zone given alongside it
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MicrosoftTodoDateTime {
+    date_time: String,
+}
+
+#[derive(Deserialize)]
+struct MicrosoftTodoRecurrence {
+    pattern: MicrosoftTodoPattern,
+}
+
+#[derive(Deserialize)]
+struct MicrosoftTodoPattern {
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MicrosoftTodoChecklistItem {
+    display_name: String,
+    #[serde(default)]
+    is_checked: bool,
+}
+
+const GRAPH_DATE: &[FormatItem<'static>] = format_description!("[year]-[month]-[day]");
+
+/// Read a Microsoft To Do export into its lists, with one summary row per task, numbered across
+/// the whole file. Categories become tags and steps are written into the notes as a task list.
+fn parse_microsoft_todo(json: &[u8]) -> Result<(Vec<ImportedList>, Vec<ImportRow>), String> {
+    let Json(export) = Json::<MicrosoftTodoExport>::from_bytes(json).map_err(|err| {
+        format!(
+            "This doesn't look like a Microsoft To Do export: {}",
+            err.body_text()
+        )
+    })?;
+
+    let mut lists = Vec::new();
+    let mut rows = Vec::new();
+    for list in export.value {
+        let name = list.display_name.trim().to_string();
+        let mut todos = Vec::new();
+        for task in &list.tasks {
+            let content = task.title.trim().to_string();
+            let outcome = if name.is_empty() {
+                ImportOutcome::Skipped(String::from("The task's list has no name"))
+            } else if content.is_empty() {
+                ImportOutcome::Failed(String::from("The task has no title"))
+            } else if let Err(err) = validate_description(&content) {
+                ImportOutcome::Failed(err)
+            } else {
+                match microsoft_todo_task(task) {
+                    Ok(todo) => {
+                        todos.push(todo);
+                        ImportOutcome::Imported
+                    }
+                    Err(err) => ImportOutcome::Failed(err),
+                }
+            };
+            rows.push(ImportRow {
+                line: rows.len() as u64 + 1,
+                content: format!("{name} / {content}"),
+                outcome,
+            });
+        }
+        if !name.is_empty() {
+            lists.push(ImportedList { name, todos });
+        }
+    }
+    Ok((lists, rows))
+}
+
+fn microsoft_todo_task(task: &MicrosoftTodoTask) -> Result<ImportedTodo, String> {
+    let mut notes = match &task.body {
+        // html bodies are Outlook's markup around the text, which isn't worth keeping
+        Some(body) if body.content_type.eq_ignore_ascii_case("text") => {
+            body.content.trim().to_string()
+        }
+        _ => String::new(),
+    };
+    if !task.checklist_items.is_empty() {
+        push_checklist(
+            &mut notes,
+            "Steps",
+            task.checklist_items
+                .iter()
+                .map(|item| (item.is_checked, item.display_name.as_str())),
+        );
+    }
+    let mut tags: Vec<String> = Vec::new();
+    for category in &task.categories {
+        let category = category.trim();
+        if !category.is_empty() && !tags.iter().any(|tag| tag == category) {
+            tags.push(category.to_string());
+        }
+    }
+    Ok(ImportedTodo {
+        description: task.title.trim().to_string(),
+        notes,
+        priority: match task.importance.as_str() {
+            "high" => Priority::High,
+            "low" => Priority::Low,
+            _ => Priority::Normal,
+        },
+        due_at: graph_date(task.due_date_time.as_ref(), "due date")?,
+        // yearly tasks have nothing to map to
+        recurrence: task.recurrence.as_ref().and_then(|recurrence| {
+            match recurrence.pattern.kind.as_str() {
+                "daily" => Some(Recurrence::Daily),
+                "weekly" => Some(Recurrence::Weekly),
+                "absoluteMonthly" | "relativeMonthly" => Some(Recurrence::Monthly),
+                _ => None,
+            }
+        }),
+        done: task.status == "completed",
+        completed_on: graph_date(task.completed_date_time.as_ref(), "completion date")?,
+        tags,
+    })
+}
+
+// The day part of a Graph dateTimeTimeZone
+fn graph_date(
+    date_time: Option<&MicrosoftTodoDateTime>,
+    what: &str,
+) -> Result<Option<Date>, String> {
+    date_time
+        .map(|date_time| {
+            date_time
+                .date_time
+                .get(..10)
+                .and_then(|day| Date::parse(day, GRAPH_DATE).ok())
+                .ok_or_else(|| format!("Couldn't understand the {what} '{}'", date_time.date_time))
+        })
+        .transpose()
+}
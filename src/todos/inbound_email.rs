@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{DefaultBodyLimit, FromRequest, Multipart, Query, Request, State},
+    http::{header, StatusCode},
+    routing::post,
+    Extension, Router,
+};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::{config::InboundEmailConfig, utils};
+
+use super::{db, routes::MAX_DESCRIPTION_CHARS};
+
+// Mailgun and SendGrid take emails of up to 25MB, attachments included
+const MAX_EMAIL_BYTES: usize = 25 * 1024 * 1024;
+
+// The webhook mail providers post emails to, only there when INBOUND_EMAIL_TOKEN is set
+// Point the inbound route of Mailgun (or SendGrid's Inbound Parse) for the address of your choice
+// at /inbound/email?token=<INBOUND_EMAIL_TOKEN>
+pub fn routes(pool: &Pool<Postgres>, config: InboundEmailConfig) -> Router {
+    let Some(token) = config.token else {
+        return Router::new();
+    };
+    Router::new()
+        .route("/inbound/email", post(receive))
+        .layer(DefaultBodyLimit::max(MAX_EMAIL_BYTES))
+        .layer(Extension(Inbound {
+            token,
+            list_id: config.list_id,
+        }))
+        .with_state(pool.clone())
+}
+
+#[derive(Clone)]
+struct Inbound {
+    token: String,
+    list_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct InboundParams {
+    token: String,
+}
+
+/// An email turned into a todo: the subject is the description and the text the notes
+pub struct InboundEmail {
+    pub message_id: Option<String>,
+    pub from: String,
+    pub subject: String,
+    pub description: String,
+    pub notes: String,
+    pub attachments: Vec<EmailAttachment>,
+}
+
+/// A file attached to an email, kept with the todo
+pub struct EmailAttachment {
+    pub file_name: String,
+    pub data: Vec<u8>,
+}
+
+// post /inbound/email?token=...
+// Answers 406 for emails that can't become a todo, which tells Mailgun not to retry them
+async fn receive(
+    State(pool): State<PgPool>,
+    Extension(inbound): Extension<Inbound>,
+    Query(params): Query<InboundParams>,
+    request: Request,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if !utils::tokens_match(&inbound.token, &params.token) {
+        return Err((StatusCode::NOT_FOUND, String::from("Not found")));
+    }
+    let (fields, attachments) = read_fields(request).await?;
+    let email =
+        parse_email(&fields, attachments).map_err(|err| (StatusCode::NOT_ACCEPTABLE, err))?;
+
+    let list_id = match inbound.list_id {
+        Some(list_id) => list_id,
+        None => db::get_first_list_id(&pool).await?.ok_or((
+            StatusCode::NOT_ACCEPTABLE,
+            String::from("There's no list to add the todo to"),
+        ))?,
+    };
+    db::create_emailed_todo(list_id, &email, &pool).await?;
+    Ok(StatusCode::OK)
+}
+
+// The form fields of the post, and any attachments. Mailgun only sends multipart/form-data
+// when there are attachments.
+async fn read_fields(
+    request: Request,
+) -> Result<(HashMap<String, String>, Vec<EmailAttachment>), (StatusCode, String)> {
+    let is_multipart = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("multipart/form-data"));
+    if !is_multipart {
+        let Form(fields) = Form::<HashMap<String, String>>::from_request(request, &())
+            .await
+            .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+        return Ok((fields, Vec::new()));
+    }
+
+    let mut multipart = Multipart::from_request(request, &())
+        .await
+        .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+    let mut fields = HashMap::new();
+    let mut attachments = Vec::new();
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?
+    {
+        if let Some(file_name) = field.file_name().map(String::from) {
+            let data = field
+                .bytes()
+                .await
+                .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+            attachments.push(EmailAttachment {
+                file_name,
+                data: data.to_vec(),
+            });
+            continue;
+        }
+        let name = field.name().unwrap_or_default().to_string();
+        let value = field
+            .text()
+            .await
+            .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+        fields.insert(name, value);
+    }
+    Ok((fields, attachments))
+}
+
+// Field names are Mailgun's, with SendGrid's as a fallback. The text without quoted replies
+// and signatures is preferred when the provider works it out.
+fn parse_email(
+    fields: &HashMap<String, String>,
+    attachments: Vec<EmailAttachment>,
+) -> Result<InboundEmail, String> {
+    let field = |names: &[&str]| {
+        names
+            .iter()
+            .filter_map(|name| fields.get(*name))
+            .map(|value| value.trim())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default()
+    };
+    let from = match field(&["from", "sender"]) {
+        "" => "an unknown sender",
+        from => from,
+    };
+    let subject = field(&["subject"]);
+    let text = field(&["stripped-text", "body-plain", "text"]);
+
+    // An email without a subject is named after its first line
+    let title = match subject {
+        "" => text.lines().map(str::trim).find(|line| !line.is_empty()),
+        subject => Some(subject),
+    }
+    .ok_or_else(|| String::from("The email has no subject or text"))?;
+    let description: String = title
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(MAX_DESCRIPTION_CHARS)
+        .collect();
+
+    Ok(InboundEmail {
+        message_id: Some(field(&["Message-Id", "message-id"]))
+            .filter(|message_id| !message_id.is_empty())
+            .map(String::from),
+        from: from.to_string(),
+        subject: subject.to_string(),
+        description,
+        notes: text.to_string(),
+        attachments,
+    })
+}
@@ -0,0 +1,62 @@
+use axum::{
+    extract::{Multipart, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::post,
+    Router,
+};
+
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::utils::HtmlTemplate;
+
+use super::{db, import::ListSource, routes::uploaded_file, templates};
+
+// Imports that create whole lists, nested under /import
+// An export is previewed first, then posted again to create its lists
+pub fn routes(pool: &Pool<Postgres>) -> Router {
+    Router::new()
+        .route("/:source", post(import))
+        .route("/:source/preview", post(preview))
+        .with_state(pool.clone())
+}
+
+// post /import/:source/preview
+// Shows what importing the export in the `file` field would create, without saving anything
+async fn preview(
+    Path(source): Path<ListSource>,
+    multipart: Multipart,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let file = uploaded_file(multipart, "Choose an export to import").await?;
+    let (lists, rows) = source
+        .parse(&file)
+        .map_err(|err| (StatusCode::UNPROCESSABLE_ENTITY, err))?;
+    Ok(HtmlTemplate(templates::ImportPreviewTemplate {
+        source,
+        lists,
+        rows,
+    }))
+}
+
+// post /import/:source
+// Creates the lists and opens the first of them
+async fn import(
+    Path(source): Path<ListSource>,
+    State(pool): State<PgPool>,
+    multipart: Multipart,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let file = uploaded_file(multipart, "Choose an export to import").await?;
+    let (lists, _) = source
+        .parse(&file)
+        .map_err(|err| (StatusCode::UNPROCESSABLE_ENTITY, err))?;
+    let list_ids = db::import_lists(&lists, &pool).await?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "HX-Redirect",
+        format!("/lists/{}", list_ids[0])
+            .parse()
+            .expect("should be able to create a HX-Redirect header"),
+    );
+    Ok(headers)
+}
@@ -1,11 +1,768 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+};
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::{request::Parts, Uri},
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::{Deserialize, Serialize};
+use time::{util::days_in_year_month, Date, Duration, OffsetDateTime};
+use utoipa::{IntoParams, ToSchema};
+
+pub mod agenda;
+pub mod board;
+pub mod caldav;
 mod db;
+mod import;
+pub mod inbound_email;
+pub mod list_import;
+pub mod rebalance;
+pub mod reminders;
 pub mod routes;
+pub mod slack;
+pub mod telegram;
 mod templates;
+pub mod todotxt;
+pub mod trash;
 
 #[derive(Debug, Clone)]
 struct Todo {
     id: i32,
+    list_id: i32,
+    done: bool,
+    // when it was last checked, see the todos_completed_at trigger
+    completed_at: Option<OffsetDateTime>,
+    description: String,
+    notes: String,
+    due_at: Option<Date>,
+    priority: Priority,
+    recurrence: Option<Recurrence>,
+    // overrides REMINDER_WINDOW_DAYS, see reminders
+    remind_days_before: Option<i32>,
+    estimate_minutes: Option<i32>,
+    // shown above all the unpinned todos, whatever the sort
+    pinned: bool,
+    color: Option<Color>,
+    tags: Vec<String>,
+    // the open todos this one is waiting on, in the same order in both
+    blocker_ids: Vec<i32>,
+    blocker_descriptions: Vec<String>,
+    // the custom fields this todo has a value for, in the same order in both
+    custom_field_names: Vec<String>,
+    custom_field_values: Vec<String>,
+    created_at: OffsetDateTime,
+    // kept up to date by the todos_updated_at trigger, reordering doesn't count
+    updated_at: OffsetDateTime,
+    // bumped by the todos_version trigger when a field of the edit form changes
+    version: i32,
+    // for todos that were emailed in, see inbound_email
+    email_from: Option<String>,
+    email_subject: Option<String>,
+}
+
+// A file that came with an emailed todo, see inbound_email
+#[derive(Debug, Clone)]
+struct TodoAttachment {
+    id: i32,
+    file_name: String,
+}
+
+// A list as a CalDAV calendar, see caldav
+#[derive(Debug, Clone)]
+struct CalDavList {
+    id: i32,
+    name: String,
+    // changes with any of the list's todos, so clients know when to look for changes
+    ctag: String,
+}
+
+// A todo as a CalDAV resource holding a VTODO, see caldav
+#[derive(Debug, Clone)]
+struct CalDavTodo {
+    id: i32,
+    // the last segment of its URL, e.g. todo-12.ics
+    name: String,
+    uid: String,
+    description: String,
+    notes: String,
+    done: bool,
+    completed_at: Option<OffsetDateTime>,
+    due_at: Option<Date>,
+    priority: Priority,
+    created_at: OffsetDateTime,
+    updated_at: OffsetDateTime,
+}
+
+// A chat that may use the Telegram bot, see telegram
+#[derive(Debug, Clone)]
+struct TelegramChat {
+    chat_id: i64,
+    // the group's title, or the name of the person
+    name: String,
+    linked_at: OffsetDateTime,
+}
+
+// A todo for requests that want JSON, see utils::WantsJson
+#[derive(Debug, Serialize, ToSchema)]
+struct TodoJson {
+    id: i32,
+    list_id: i32,
+    description: String,
+    notes: String,
+    done: bool,
+    #[serde(with = "time::serde::rfc3339::option")]
+    completed_at: Option<OffsetDateTime>,
+    // yyyy-mm-dd
+    due_at: Option<String>,
+    priority: String,
+    recurrence: Option<String>,
+    remind_days_before: Option<i32>,
+    estimate_minutes: Option<i32>,
+    pinned: bool,
+    color: Option<String>,
+    tags: Vec<String>,
+    blocked_by: Vec<i32>,
+    // custom field name to value
+    custom_fields: BTreeMap<String, String>,
+    #[serde(with = "time::serde::rfc3339")]
+    created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    updated_at: OffsetDateTime,
+    // send it back when updating, see update
+    version: i32,
+}
+
+impl From<Todo> for TodoJson {
+    fn from(todo: Todo) -> Self {
+        TodoJson {
+            id: todo.id,
+            list_id: todo.list_id,
+            description: todo.description,
+            notes: todo.notes,
+            done: todo.done,
+            completed_at: todo.completed_at,
+            due_at: todo.due_at.map(|due_at| due_at.to_string()),
+            priority: todo.priority.to_string(),
+            recurrence: todo.recurrence.map(|recurrence| recurrence.to_string()),
+            remind_days_before: todo.remind_days_before,
+            estimate_minutes: todo.estimate_minutes,
+            pinned: todo.pinned,
+            color: todo.color.map(|color| color.to_string()),
+            tags: todo.tags,
+            blocked_by: todo.blocker_ids,
+            custom_fields: todo
+                .custom_field_names
+                .into_iter()
+                .zip(todo.custom_field_values)
+                .collect(),
+            created_at: todo.created_at,
+            updated_at: todo.updated_at,
+            version: todo.version,
+        }
+    }
+}
+
+// A completed todo that has been put away, see /lists/:list_id/todos/archive
+#[derive(Debug, Clone)]
+struct ArchivedTodo {
+    id: i32,
+    description: String,
+    archived_at: OffsetDateTime,
+}
+
+// An earlier description and notes of a todo, see /lists/:list_id/todos/:id/history
+#[derive(Debug, Clone)]
+struct TodoRevision {
+    id: i32,
+    description: String,
+    notes: String,
+    // when this version was replaced
+    created_at: OffsetDateTime,
+}
+
+// A pending todo from any list that is due today or overdue, see agenda
+#[derive(Debug, Clone)]
+struct AgendaTodo {
+    id: i32,
+    list_id: i32,
+    list_name: String,
+    description: String,
+    priority: Priority,
+    due_at: Date,
+}
+
+// A todo as a line of todo.txt, see todotxt
+#[derive(Debug, Clone)]
+struct TodoTxtTodo {
+    list_name: String,
+    description: String,
     done: bool,
+    completed_at: Option<OffsetDateTime>,
+    created_at: OffsetDateTime,
+    priority: Priority,
+    due_at: Option<Date>,
+    tags: Vec<String>,
+}
+
+// A card on the board, see board
+#[derive(Debug, Clone)]
+struct BoardTodo {
+    id: i32,
+    list_id: i32,
     description: String,
-    position: i32,
+    done: bool,
+    pinned: bool,
+}
+
+// A pending todo that is about to come due, see reminders
+#[derive(Debug, Clone)]
+struct DueTodo {
+    id: i32,
+    list_name: String,
+    description: String,
+    due_at: Date,
+}
+
+// How long after deleting or archiving todos the change can still be undone
+const UNDO_WINDOW_SECONDS: i64 = 15;
+
+// How long a create request's idempotency key is remembered, see db::create_todo
+const IDEMPOTENCY_KEY_TTL_HOURS: i32 = 24;
+
+// A destructive change that can be reversed for a little while, see db::record_undo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "undo_action", rename_all = "lowercase")]
+enum UndoAction {
+    Delete,
+    Archive,
+}
+
+// Declared from lowest to highest, matching the order of the `todo_priority` postgres enum
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, sqlx::Type, ToSchema,
+)]
+#[sqlx(type_name = "todo_priority", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+            Priority::Urgent => "urgent",
+        })
+    }
+}
+
+// A label shown as the todo's left border. Matches the `todo_color` postgres enum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "todo_color", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+enum Color {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+}
+
+impl Color {
+    // In the order the color picker shows them
+    const PALETTE: [Color; 6] = [
+        Color::Red,
+        Color::Orange,
+        Color::Yellow,
+        Color::Green,
+        Color::Blue,
+        Color::Purple,
+    ];
+
+    // The color picker sends an empty string to clear the color
+    fn from_param(param: &str) -> Option<Self> {
+        Color::PALETTE
+            .into_iter()
+            .find(|color| color.to_string() == param)
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Color::Red => "red",
+            Color::Orange => "orange",
+            Color::Yellow => "yellow",
+            Color::Green => "green",
+            Color::Blue => "blue",
+            Color::Purple => "purple",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "todo_recurrence", rename_all = "lowercase")]
+enum Recurrence {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Recurrence {
+    // The recurrence select sends an empty string for "never"
+    fn from_param(param: &str) -> Option<Self> {
+        match param {
+            "daily" => Some(Recurrence::Daily),
+            "weekly" => Some(Recurrence::Weekly),
+            "monthly" => Some(Recurrence::Monthly),
+            _ => None,
+        }
+    }
+
+    // When the occurrence after one due on `due_at` is due.
+    // Monthly todos are due on `day` of the month, falling back to the last day of shorter months.
+    // `day` is the day the first occurrence was due on, so that the 31st stays the 31st after February.
+    fn next_due_at(&self, due_at: Date, day: u8) -> Date {
+        match self {
+            Recurrence::Daily => due_at + Duration::days(1),
+            Recurrence::Weekly => due_at + Duration::weeks(1),
+            Recurrence::Monthly => {
+                let month = due_at.month().next();
+                let year = if month == time::Month::January {
+                    due_at.year() + 1
+                } else {
+                    due_at.year()
+                };
+                let day = day.min(days_in_year_month(year, month));
+                Date::from_calendar_date(year, month, day)
+                    .expect("should be able to build a date within the month")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Recurrence::Daily => "daily",
+            Recurrence::Weekly => "weekly",
+            Recurrence::Monthly => "monthly",
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum TodoSort {
+    #[default]
+    Position,
+    DueAt,
+    Priority,
+    // newest first
+    CreatedAt,
+    // most recently changed first
+    UpdatedAt,
+    // by description, A to Z
+    Alphabetical,
+}
+
+impl TodoSort {
+    // The value the todos queries compare against to pick an ORDER BY
+    fn as_str(&self) -> &'static str {
+        match self {
+            TodoSort::Position => "position",
+            TodoSort::DueAt => "due_at",
+            TodoSort::Priority => "priority",
+            TodoSort::CreatedAt => "created_at",
+            TodoSort::UpdatedAt => "updated_at",
+            TodoSort::Alphabetical => "alphabetical",
+        }
+    }
+
+    fn from_param(param: &str) -> Option<Self> {
+        match param {
+            "position" => Some(TodoSort::Position),
+            "due_at" => Some(TodoSort::DueAt),
+            "priority" => Some(TodoSort::Priority),
+            "created_at" => Some(TodoSort::CreatedAt),
+            "updated_at" => Some(TodoSort::UpdatedAt),
+            "alphabetical" => Some(TodoSort::Alphabetical),
+            _ => None,
+        }
+    }
+
+    // Each sort and its label, in the order they are offered
+    const ALL: [(TodoSort, &'static str); 6] = [
+        (TodoSort::Position, "my order"),
+        (TodoSort::DueAt, "due date"),
+        (TodoSort::Priority, "priority"),
+        (TodoSort::Alphabetical, "alphabetical"),
+        (TodoSort::UpdatedAt, "recently changed"),
+        (TodoSort::CreatedAt, "newest"),
+    ];
+}
+
+// The last sort picked is remembered in this cookie and used whenever the URL doesn't say
+const SORT_COOKIE: &str = "todo_sort";
+
+// Just the sort from a query string, to tell whether it was given at all
+#[derive(Debug, Default, Deserialize)]
+struct SortParam {
+    sort: Option<TodoSort>,
+}
+
+fn saved_sort(jar: &CookieJar) -> Option<TodoSort> {
+    jar.get(SORT_COOKIE)
+        .and_then(|cookie| TodoSort::from_param(cookie.value()))
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum TodoStatus {
+    #[default]
+    All,
+    Active,
+    Completed,
+}
+
+impl TodoStatus {
+    // The value the todos queries compare against to pick which todos to show
+    fn as_str(&self) -> &'static str {
+        match self {
+            TodoStatus::All => "all",
+            TodoStatus::Active => "active",
+            TodoStatus::Completed => "completed",
+        }
+    }
+}
+
+// Query params for narrowing down and ordering the list of todos
+#[derive(Debug, Default, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct TodoFilter {
+    #[serde(default)]
+    status: TodoStatus,
+    #[serde(default)]
+    sort: TodoSort,
+    // only pending todos whose due date has passed
+    #[serde(default)]
+    overdue: bool,
+    // only todos checked off since midnight (UTC)
+    #[serde(default)]
+    completed_today: bool,
+    // only todos with this tag
+    tag: Option<String>,
+    // only todos with this color label
+    color: Option<Color>,
+}
+
+// How many todos there are in each state, across every page
+#[derive(Debug, Default, Clone, Copy)]
+struct TodoCounts {
+    active: i64,
+    done: i64,
+    // total estimate of the active todos that have one
+    active_estimate_minutes: i64,
+}
+
+// How many todos are rendered at a time; scrolling to the end of the list loads the next page
+const PAGE_SIZE: i64 = 50;
+
+// A window into the list of todos
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct Page {
+    offset: i64,
+    limit: i64,
+}
+
+impl Page {
+    fn first() -> Self {
+        Page {
+            offset: 0,
+            limit: PAGE_SIZE,
+        }
+    }
+
+    // The page after this one, unless this page came back short
+    fn next(&self, fetched: usize) -> Option<Self> {
+        if (fetched as i64) < self.limit {
+            return None;
+        }
+        Some(Page {
+            offset: self.offset + self.limit,
+            limit: self.limit,
+        })
+    }
+}
+
+// The filter the user is currently looking at.
+// The list endpoint pushes its query params into the address bar and htmx sends the address back
+// in the HX-Current-URL header, so anything that re-renders the list can keep the same filter.
+struct CurrentFilter(TodoFilter);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for CurrentFilter
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let (mut filter, SortParam { sort }) = parts
+            .headers
+            .get("HX-Current-URL")
+            .and_then(|url| url.to_str().ok())
+            .and_then(|url| url.parse::<Uri>().ok())
+            .and_then(|uri| {
+                let Query(filter) = Query::<TodoFilter>::try_from_uri(&uri).ok()?;
+                let Query(sort) = Query::<SortParam>::try_from_uri(&uri).ok()?;
+                Some((filter, sort))
+            })
+            .unwrap_or_default();
+        if sort.is_none() {
+            filter.sort = saved_sort(&CookieJar::from_headers(&parts.headers)).unwrap_or_default();
+        }
+        Ok(CurrentFilter(filter))
+    }
+}
+
+// A duration in minutes the way estimates are shown, e.g. "1h 30m"
+fn format_minutes(minutes: i64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{minutes}m"),
+        (hours, 0) => format!("{hours}h"),
+        (hours, minutes) => format!("{hours}h {minutes}m"),
+    }
+}
+
+// Neighbouring todos closer together than this get the whole list renumbered,
+// see db::rebalance_positions
+const MIN_POSITION_GAP: f64 = 1e-9;
+
+// The new (position, id) of each todo that has to move so that the todos are shown in `order`
+// (top to bottom), given the current position of each of them.
+// The longest run of todos that are already in order stays put and every other todo is slotted in
+// between its new neighbours, so dragging a single todo only moves that one.
+// Returns None when there's no room left between two neighbours.
+fn reposition(order: &[i32], current: &HashMap<i32, f64>) -> Option<Vec<(f64, i32)>> {
+    let mut seen = HashSet::new();
+    let order: Vec<(i32, f64)> = order
+        .iter()
+        .filter(|id| seen.insert(**id))
+        .filter_map(|id| current.get(id).map(|position| (*id, *position)))
+        .collect();
+    let positions: Vec<f64> = order.iter().map(|(_, position)| *position).collect();
+    let keep = longest_descending_run(&positions);
+
+    let top = positions.iter().copied().fold(f64::MIN, f64::max) + 1.0;
+    let bottom = positions.iter().copied().fold(f64::MAX, f64::min) - 1.0;
+    let mut moves = vec![];
+    let mut above = top;
+    let mut between = vec![];
+    for (index, (id, position)) in order.into_iter().enumerate() {
+        if keep[index] {
+            moves.extend(spread(&between, above, position)?);
+            between.clear();
+            above = position;
+        } else {
+            between.push(id);
+        }
+    }
+    moves.extend(spread(&between, above, bottom)?);
+    Some(moves)
+}
+
+// Evenly spaced positions for `ids`, from just below `above` down to just above `below`
+fn spread(ids: &[i32], above: f64, below: f64) -> Option<Vec<(f64, i32)>> {
+    let step = (above - below) / (ids.len() + 1) as f64;
+    if !ids.is_empty() && step < MIN_POSITION_GAP {
+        return None;
+    }
+    Some(
+        ids.iter()
+            .enumerate()
+            .map(|(index, id)| (above - step * (index + 1) as f64, *id))
+            .collect(),
+    )
+}
+
+// Marks the values making up the longest strictly descending subsequence of `values`
+fn longest_descending_run(values: &[f64]) -> Vec<bool> {
+    // tails[n] is the index of the last value of the best run of length n + 1 found so far,
+    // where the best run is the one ending on the highest value
+    let mut tails: Vec<usize> = vec![];
+    let mut previous: Vec<Option<usize>> = vec![None; values.len()];
+    for (index, value) in values.iter().enumerate() {
+        let length = tails.partition_point(|&tail| values[tail] > *value);
+        previous[index] = length.checked_sub(1).map(|n| tails[n]);
+        if length == tails.len() {
+            tails.push(index);
+        } else {
+            tails[length] = index;
+        }
+    }
+
+    let mut keep = vec![false; values.len()];
+    let mut index = tails.last().copied();
+    while let Some(i) = index {
+        keep[i] = true;
+        index = previous[i];
+    }
+    keep
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use super::*;
+
+    fn positions(positions: &[(i32, f64)]) -> HashMap<i32, f64> {
+        positions.iter().copied().collect()
+    }
+
+    #[test]
+    fn moves_only_the_dragged_todo_between_its_neighbours() {
+        let current = positions(&[(1, 3.0), (2, 2.0), (3, 1.0)]);
+        assert_eq!(reposition(&[1, 3, 2], &current), Some(vec![(2.5, 3)]));
+    }
+
+    #[test]
+    fn moves_a_todo_above_the_top_one() {
+        let current = positions(&[(1, 3.0), (2, 2.0), (3, 1.0)]);
+        assert_eq!(reposition(&[3, 1, 2], &current), Some(vec![(3.5, 3)]));
+    }
+
+    #[test]
+    fn moves_nothing_when_the_order_is_unchanged() {
+        let current = positions(&[(1, 3.0), (2, 2.0), (3, 1.0)]);
+        assert_eq!(reposition(&[1, 2, 3], &current), Some(vec![]));
+    }
+
+    #[test]
+    fn ignores_repeated_and_unknown_ids() {
+        let current = positions(&[(1, 3.0), (2, 2.0), (3, 1.0)]);
+        assert_eq!(
+            reposition(&[1, 3, 1, 42, 2], &current),
+            Some(vec![(2.5, 3)])
+        );
+    }
+
+    #[test]
+    fn asks_for_a_rebalance_when_neighbours_are_too_close() {
+        let current = positions(&[(1, 1.0 + 1e-10), (2, 1.0), (3, 0.0)]);
+        assert_eq!(reposition(&[1, 3, 2], &current), None);
+    }
+
+    #[test]
+    fn spreads_evenly_between_neighbours() {
+        assert_eq!(spread(&[7, 8], 3.0, 0.0), Some(vec![(2.0, 7), (1.0, 8)]));
+    }
+
+    #[test]
+    fn spreads_nothing_without_needing_room() {
+        assert_eq!(spread(&[], 1.0, 1.0), Some(vec![]));
+    }
+
+    #[test]
+    fn refuses_to_spread_below_the_minimum_gap() {
+        assert_eq!(spread(&[7], 1.0, 1.0 - MIN_POSITION_GAP), None);
+    }
+
+    #[test]
+    fn keeps_all_of_a_descending_run() {
+        assert_eq!(
+            longest_descending_run(&[5.0, 4.0, 3.0, 2.0, 1.0]),
+            vec![true; 5]
+        );
+    }
+
+    #[test]
+    fn keeps_one_of_an_ascending_run() {
+        let keep = longest_descending_run(&[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(keep.iter().filter(|keep| **keep).count(), 1);
+    }
+
+    #[test]
+    fn keeps_the_longest_descending_run() {
+        assert_eq!(
+            longest_descending_run(&[5.0, 1.0, 4.0, 3.0, 0.0]),
+            vec![true, false, true, true, true]
+        );
+    }
+
+    #[test]
+    fn keeps_nothing_of_nothing() {
+        assert!(longest_descending_run(&[]).is_empty());
+    }
+
+    #[test]
+    fn monthly_todos_due_on_the_31st_fall_back_to_the_end_of_february() {
+        assert_eq!(
+            Recurrence::Monthly.next_due_at(date!(2026 - 01 - 31), 31),
+            date!(2026 - 02 - 28)
+        );
+        assert_eq!(
+            Recurrence::Monthly.next_due_at(date!(2028 - 01 - 31), 31),
+            date!(2028 - 02 - 29)
+        );
+    }
+
+    #[test]
+    fn monthly_todos_due_on_the_31st_go_back_to_it_after_shorter_months() {
+        let mut due_at = date!(2026 - 01 - 31);
+        let mut due_ats = Vec::new();
+        for _ in 0..4 {
+            due_at = Recurrence::Monthly.next_due_at(due_at, 31);
+            due_ats.push(due_at);
+        }
+        assert_eq!(
+            due_ats,
+            [
+                date!(2026 - 02 - 28),
+                date!(2026 - 03 - 31),
+                date!(2026 - 04 - 30),
+                date!(2026 - 05 - 31),
+            ]
+        );
+    }
+
+    #[test]
+    fn monthly_todos_due_on_a_leap_day_keep_the_day_of_the_month() {
+        assert_eq!(
+            Recurrence::Monthly.next_due_at(date!(2028 - 02 - 29), 29),
+            date!(2028 - 03 - 29)
+        );
+    }
+
+    #[test]
+    fn monthly_todos_roll_over_into_the_next_year() {
+        assert_eq!(
+            Recurrence::Monthly.next_due_at(date!(2026 - 12 - 31), 31),
+            date!(2027 - 01 - 31)
+        );
+    }
+
+    #[test]
+    fn daily_and_weekly_todos_count_the_leap_day() {
+        assert_eq!(
+            Recurrence::Daily.next_due_at(date!(2028 - 02 - 28), 28),
+            date!(2028 - 02 - 29)
+        );
+        assert_eq!(
+            Recurrence::Weekly.next_due_at(date!(2028 - 02 - 26), 26),
+            date!(2028 - 03 - 04)
+        );
+    }
 }
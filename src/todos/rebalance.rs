@@ -0,0 +1,27 @@
+use sqlx::PgPool;
+use tokio::time;
+
+use crate::config::RebalanceConfig;
+
+use super::db;
+
+// Each drag slots a todo halfway between its neighbours, so dragging into the same spot over and
+// over halves the room there. Lists with less room than this somewhere get renumbered, well before
+// reordering runs out of room (see MIN_POSITION_GAP). Todos at the same position count too.
+const CROWDED_GAP: f64 = 1e-3;
+
+/// Periodically renumber the positions of lists that have todos squeezed close together,
+/// keeping their order, so that manual ordering never runs out of room.
+pub fn spawn(config: RebalanceConfig, pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = time::interval(config.interval);
+        loop {
+            interval.tick().await;
+            match db::rebalance_crowded_lists(CROWDED_GAP, &pool).await {
+                Ok(list_ids) if list_ids.is_empty() => {}
+                Ok(list_ids) => tracing::info!(?list_ids, "renumbered crowded lists"),
+                Err((_, err)) => tracing::error!(%err, "could not renumber crowded lists"),
+            }
+        }
+    });
+}
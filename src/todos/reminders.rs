@@ -0,0 +1,72 @@
+use askama::Template;
+use sqlx::PgPool;
+use tokio::time;
+
+use crate::{
+    config::ReminderConfig,
+    mailer::{Email, Mailer},
+    notifications::{Notification, Notifier},
+};
+
+use super::{db, DueTodo};
+
+#[derive(Template)]
+#[template(path = "reminder_email.txt")]
+struct ReminderEmailTemplate<'a> {
+    todos: &'a [DueTodo],
+}
+
+/// Periodically email REMINDER_EMAIL about pending todos that are coming due.
+/// Each todo is only included once, until its due date or reminder is changed.
+/// Open pages are told as well.
+pub fn spawn(config: ReminderConfig, mailer: Mailer, notifier: Notifier, pool: PgPool) {
+    let Some(to) = config.to else {
+        tracing::info!("REMINDER_EMAIL is not set, not sending reminders");
+        return;
+    };
+    tokio::spawn(async move {
+        let mut interval = time::interval(config.interval);
+        loop {
+            interval.tick().await;
+            if let Err(err) =
+                send_reminders(&to, config.window_days, &mailer, &notifier, &pool).await
+            {
+                tracing::error!(%err, "could not send reminders");
+            }
+        }
+    });
+}
+
+async fn send_reminders(
+    to: &str,
+    window_days: i32,
+    mailer: &Mailer,
+    notifier: &Notifier,
+    pool: &PgPool,
+) -> Result<(), String> {
+    let todos = db::get_due_todos(window_days, pool)
+        .await
+        .map_err(|(_, err)| err)?;
+    if todos.is_empty() {
+        return Ok(());
+    }
+
+    let subject = match todos.len() {
+        1 => String::from("1 todo is coming due"),
+        count => format!("{count} todos are coming due"),
+    };
+    let descriptions: Vec<&str> = todos.iter().map(|todo| todo.description.as_str()).collect();
+    let message = format!("{subject}: {}", descriptions.join(", "));
+    let email = Email::from_template(to, subject, &ReminderEmailTemplate { todos: &todos })
+        .map_err(|err| err.to_string())?;
+    mailer.send(email).await.map_err(|err| err.to_string())?;
+    notifier.send(Notification::everyone(&message));
+
+    // Only marked once the email is out, so a failed send is retried on the next tick
+    let ids: Vec<i32> = todos.iter().map(|todo| todo.id).collect();
+    db::mark_reminded(&ids, pool)
+        .await
+        .map_err(|(_, err)| err)?;
+    tracing::info!(count = ids.len(), "sent reminders");
+    Ok(())
+}
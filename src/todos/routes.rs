@@ -1,52 +1,231 @@
+use std::collections::HashMap;
+
 use axum::{
-    extract::{Path, State},
-    http::{HeaderMap, StatusCode},
-    response::IntoResponse,
+    body::Bytes,
+    extract::{Multipart, Path, Query, RawQuery, Request, State},
+    http::{header, HeaderMap, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::{delete, get, post, put},
-    Router,
+    Extension, Json, Router,
 };
 
-use axum_extra::extract::Form;
+use axum_extra::extract::{
+    cookie::{Cookie, CookieJar, SameSite},
+    Form,
+};
 
 use serde::Deserialize;
-use sqlx::{postgres::PgArguments, query::Query, PgPool, Pool, Postgres};
+use sqlx::{PgPool, Pool, Postgres};
+use time::{format_description::FormatItem, macros::format_description, Date};
+use utoipa::ToSchema;
 
+use crate::notifications::{Notification, Notifier};
+use crate::tokens;
 use crate::utils;
-use crate::utils::HtmlTemplate;
+use crate::utils::{ConfirmDialogTemplate, HtmlFragments, HtmlTemplate, WantsJson};
 
-use super::{db, templates};
+use super::{
+    db, import, saved_sort, templates, todotxt, Color, CurrentFilter, Page, Priority, Recurrence,
+    SortParam, TodoFilter, TodoJson, UndoAction, SORT_COOKIE, UNDO_WINDOW_SECONDS,
+};
 
-// todos routes, nested under /todos
+// todos routes, nested under /lists/:list_id/todos
 pub fn routes(pool: &Pool<Postgres>) -> Router {
     Router::new()
         .route("/", get(list).post(create))
+        .route("/page", get(page))
         .route("/:id", put(update).delete(destroy))
         .route("/move_complete_to_bottom", post(move_complete_to_bottom))
         .route("/delete_completed", delete(delete_completed))
+        .route("/delete_completed/confirm", get(confirm_delete_completed))
+        .route("/archive_completed", post(archive_completed))
+        .route("/archive", get(archive))
+        .route("/:id/restore", post(restore))
+        .route("/undo/:token", post(undo))
+        .route("/bulk", post(bulk))
+        .route("/bulk_create", post(bulk_create))
         .route("/ordering", post(update_order))
+        .route("/import/todoist", post(import_todoist))
+        .route("/import/todotxt", post(import_todotxt))
         .route("/:id/edit", get(edit))
+        .route("/:id/move-up", post(move_up))
+        .route("/:id/move-down", post(move_down))
+        .route("/:id/pin", post(toggle_pinned))
+        .route("/:id/history", get(history))
+        .route("/:id/attachments", get(attachments))
+        .route("/:id/attachments/:attachment_id", get(download_attachment))
+        .route("/:id/history/:revision_id/restore", post(restore_revision))
+        .route("/:id/color", get(color_picker).put(set_color))
+        .route("/:id/blockers", get(blocker_picker).post(add_blocker))
+        .route("/:id/blockers/:blocker_id", delete(remove_blocker))
+        .route("/:id/tags", post(add_tag))
+        .route("/:id/tags/:name", delete(remove_tag))
+        .route_layer(middleware::from_fn(notify_list_changed))
+        // scripts using the JSON API authenticate with a personal access token
+        .route_layer(middleware::from_fn_with_state(
+            pool.clone(),
+            tokens::check_scope,
+        ))
         .with_state(pool.clone())
 }
 
-#[derive(Deserialize)]
+// Once a change to the list has gone through, tell the other pages showing it. The tab that
+// made the change says so with X-Tab-Id and isn't told.
+async fn notify_list_changed(
+    Extension(notifier): Extension<Notifier>,
+    Path(params): Path<HashMap<String, String>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let changes = !matches!(*request.method(), Method::GET | Method::HEAD);
+    let origin = request
+        .headers()
+        .get("X-Tab-Id")
+        .and_then(|tab| tab.to_str().ok())
+        .map(String::from);
+    let response = next.run(request).await;
+    let unchanged = response.extensions().get::<Unchanged>().is_some();
+    if changes && !unchanged && response.status().is_success() {
+        if let Some(list_id) = params.get("list_id").and_then(|id| id.parse().ok()) {
+            notifier.send(Notification::list_changed(list_id, origin));
+        }
+    }
+    response
+}
+
+// Marks a successful response to a request that turned out not to change anything, so nobody
+// is told about it
+#[derive(Clone, Copy)]
+struct Unchanged;
+
+#[derive(Deserialize, ToSchema)]
 struct TodoCreateParams {
     description: String,
+    due_at: Option<String>,
+    #[serde(default)]
+    priority: Priority,
+    // a fresh one comes with every render of the form, see create
+    idempotency_key: Option<String>,
+}
+
+// Longer than this and it belongs in the notes
+pub(super) const MAX_DESCRIPTION_CHARS: usize = 500;
+
+impl TodoCreateParams {
+    fn description(&self) -> Result<&str, String> {
+        validate_description(&self.description)
+    }
+}
+
+// The error is shown to the user next to the input. Every way of saving a description goes through this.
+pub(super) fn validate_description(description: &str) -> Result<&str, String> {
+    let description = description.trim();
+    if description.is_empty() {
+        return Err(String::from("Type what needs doing first"));
+    }
+    let length = description.chars().count();
+    if length > MAX_DESCRIPTION_CHARS {
+        return Err(format!(
+            "That's {length} characters, todos can be at most {MAX_DESCRIPTION_CHARS}. Put the details in the notes."
+        ));
+    }
+    Ok(description)
+}
+
+// A 422 with the message, swapped into the error element with the given id next to the input
+fn description_error(id: String, message: String, json: bool) -> Response {
+    if json {
+        return utils::json_error(StatusCode::UNPROCESSABLE_ENTITY, message);
+    }
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "HX-Retarget",
+        format!("#{id}")
+            .parse()
+            .expect("should be able to create a HX-Retarget header"),
+    );
+    headers.insert(
+        "HX-Reswap",
+        "outerHTML"
+            .parse()
+            .expect("should be able to create a HX-Reswap header"),
+    );
+    let template = templates::TodoCreateErrorTemplate { id, message };
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        headers,
+        HtmlTemplate(template),
+    )
+        .into_response()
 }
 
-// post /todos
+// post /lists/:list_id/todos
+// An invalid todo isn't inserted. The error replaces the one next to the input instead,
+// and as the form isn't reset the typed text stays there to be fixed.
+// A retried or double submitted request carries the same idempotency key, from the
+// `Idempotency-Key` header or the form, and gets the list back without adding the todo again.
+#[utoipa::path(
+    post,
+    tag = "todos",
+    path = "/lists/{list_id}/todos",
+    params(("list_id" = i32, Path)),
+    request_body(content = TodoCreateParams, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 201, description = "The list's todos, with the new one", body = [TodoJson]),
+        (status = 200, description = "The list's todos, the todo with this `Idempotency-Key` was already added", body = [TodoJson]),
+        (status = 422, description = "The description is empty or too long", body = utils::JsonError),
+    ),
+)]
 async fn create(
+    Path(list_id): Path<i32>,
     State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
+    WantsJson(json): WantsJson,
+    request_headers: HeaderMap,
     Form(params): Form<TodoCreateParams>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    sqlx::query!(
-        "INSERT INTO todos (description,position) VALUES ($1,((select max(position) from todos) + 1));",
-        params.description,
+) -> Result<Response, (StatusCode, String)> {
+    let description = match params.description() {
+        Ok(description) => description.to_string(),
+        Err(message) => {
+            return Ok(description_error(
+                String::from("create-todo-error"),
+                message,
+                json,
+            ))
+        }
+    };
+    let due_at = parse_due_at(params.due_at)?;
+    let idempotency_key = request_headers
+        .get("Idempotency-Key")
+        .and_then(|key| key.to_str().ok())
+        .map(String::from)
+        .or(params.idempotency_key)
+        .filter(|key| !key.is_empty());
+    let created = db::create_todo(
+        list_id,
+        &description,
+        due_at,
+        params.priority,
+        idempotency_key.as_deref(),
+        &pool,
     )
-    .execute(&pool)
-    .await
-    .map_err(utils::internal_error)?;
+    .await?;
+    if json {
+        let todos = todos_json(list_id, &filter, &pool).await?;
+        if !created {
+            return Ok((StatusCode::OK, Extension(Unchanged), todos).into_response());
+        }
+        return Ok((StatusCode::CREATED, todos).into_response());
+    }
 
-    let template = templates::render_all_todos(&pool).await?;
+    let template = templates::render_all_todos(list_id, &filter, &pool).await?;
+    let todos = with_counter(list_id, template, &pool).await?;
+    // A retry of a todo that was already added only gets the list again. The form it came from
+    // was reset after the first try, and may have been filled in since.
+    if !created {
+        return Ok((Extension(Unchanged), todos).into_response());
+    }
 
     let mut headers = HeaderMap::new();
     headers.insert(
@@ -55,94 +234,560 @@ async fn create(
             .parse()
             .expect("should be able to create a HX-Trigger header"),
     );
-    Ok((headers, HtmlTemplate(template)))
+    Ok((headers, todos).into_response())
 }
 
-// get /
-pub async fn index() -> impl IntoResponse {
-    let template = templates::render_index();
-    HtmlTemplate(template)
+#[derive(Deserialize)]
+struct TodoBulkCreateParams {
+    descriptions: String,
 }
 
-// get /todos
-async fn list(State(pool): State<PgPool>) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let inner_template = templates::render_all_todos(&pool).await?;
+// post /lists/:list_id/todos/bulk_create
+// One todo per non-empty line of the pasted text, in the same order at the top of the list
+async fn bulk_create(
+    Path(list_id): Path<i32>,
+    State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
+    Form(params): Form<TodoBulkCreateParams>,
+) -> Result<Response, (StatusCode, String)> {
+    let error_id = || String::from("bulk-create-error");
+    let mut descriptions = Vec::new();
+    for (index, line) in params.descriptions.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match validate_description(line) {
+            Ok(description) => descriptions.push(description.to_string()),
+            Err(message) => {
+                let message = format!("Line {}: {message}", index + 1);
+                return Ok(description_error(error_id(), message, false));
+            }
+        }
+    }
+    if descriptions.is_empty() {
+        let message = String::from("Paste at least one todo, one per line");
+        return Ok(description_error(error_id(), message, false));
+    }
+    db::create_todos(list_id, &descriptions, &pool).await?;
+
+    let template = templates::render_all_todos(list_id, &filter, &pool).await?;
+    Ok(with_counter(list_id, template, &pool)
+        .await?
+        .into_response())
+}
+
+// get /lists/:list_id/todos
+// Requests that want JSON get every matching todo rather than the first page
+#[utoipa::path(
+    get,
+    tag = "todos",
+    path = "/lists/{list_id}/todos",
+    params(("list_id" = i32, Path), TodoFilter),
+    responses((status = 200, description = "The list's todos", body = [TodoJson])),
+)]
+async fn list(
+    Path(list_id): Path<i32>,
+    State(pool): State<PgPool>,
+    Query(mut filter): Query<TodoFilter>,
+    Query(SortParam { sort }): Query<SortParam>,
+    RawQuery(query): RawQuery,
+    WantsJson(json): WantsJson,
+    jar: CookieJar,
+) -> Result<Response, (StatusCode, String)> {
+    // Picking a sort remembers it for next time, otherwise the last one picked is used
+    let jar = match sort {
+        Some(sort) => jar.add(
+            Cookie::build((SORT_COOKIE, sort.as_str()))
+                .path("/")
+                .permanent()
+                .same_site(SameSite::Lax),
+        ),
+        None => {
+            filter.sort = saved_sort(&jar).unwrap_or_default();
+            jar
+        }
+    };
+    if json {
+        return Ok((jar, todos_json(list_id, &filter, &pool).await?).into_response());
+    }
+    let inner_template = templates::render_all_todos(list_id, &filter, &pool).await?;
     let template = templates::TodosUlTemplate {
         todos: inner_template,
     };
+    let counter = templates::render_counter(list_id, &pool).await?;
+
+    // Put the filter in the address bar so that it survives reloads and later updates (see CurrentFilter)
+    let push_url = match query.filter(|q| !q.is_empty()) {
+        Some(query) => format!("/lists/{list_id}?{query}"),
+        None => format!("/lists/{list_id}"),
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "HX-Push-Url",
+        push_url
+            .parse()
+            .expect("should be able to create a HX-Push-Url header"),
+    );
+    Ok((
+        jar,
+        headers,
+        HtmlFragments::new().with(template).with(counter),
+    )
+        .into_response())
+}
+
+// get /lists/:list_id/todos/page
+async fn page(
+    Path(list_id): Path<i32>,
+    State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
+    Query(page): Query<Page>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let template = templates::render_page(list_id, &filter, page, &pool).await?;
     Ok(HtmlTemplate(template))
 }
 
-// get /todos/:id/edit
+// get /lists/:list_id/todos/:id/edit
 async fn edit(
-    Path(editable_id): Path<i32>,
+    Path((list_id, editable_id)): Path<(i32, i32)>,
     pool: State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let todos = db::get_todos(&pool).await?;
-    let template = templates::render_todos(todos, Some(editable_id));
+    let todos = db::get_todos(list_id, &filter, Some(Page::first()), &pool).await?;
+    let counts = db::count_todos(list_id, &filter, &pool).await?;
+    let mut template = templates::render_todos(list_id, filter, todos, Some(editable_id));
+    template.counts = counts;
     Ok(HtmlTemplate(template))
 }
 
-// post /todos/move_complete_to_bottom
+// post /lists/:list_id/todos/move_complete_to_bottom
 async fn move_complete_to_bottom(
+    Path(list_id): Path<i32>,
     State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let mut todos = db::get_todos(&pool).await?;
-    todos.sort_by(|a, b| a.position.cmp(&b.position));
-    let (mut completed, mut pending): (Vec<_>, Vec<_>) = todos.into_iter().partition(|t| t.done);
-    completed.append(&mut pending);
-    let positions = completed
+    // get_todos returns the todos top to bottom
+    let todos = db::get_todos(list_id, &TodoFilter::default(), None, &pool).await?;
+    let (completed, pending): (Vec<_>, Vec<_>) = todos.into_iter().partition(|t| t.done);
+    let order = pending
         .iter()
-        .enumerate()
-        .map(|(position, todo)| (position as i32, todo.id))
+        .chain(completed.iter())
+        .map(|todo| todo.id)
         .collect::<Vec<_>>();
-    db::set_positions(positions, &pool).await?;
-    let template = templates::render_all_todos(&pool).await?;
+    db::reorder(list_id, &order, &pool).await?;
+    let template = templates::render_all_todos(list_id, &filter, &pool).await?;
     Ok(HtmlTemplate(template))
 }
 
-// post /todos/delete_completed
+// post /lists/:list_id/todos/delete_completed
 async fn delete_completed(
+    Path(list_id): Path<i32>,
     State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let todos = db::get_todos(&pool).await?;
-    let (completed, pending): (Vec<_>, Vec<_>) = todos.into_iter().partition(|t| t.done);
+    let todos = db::get_todos(list_id, &TodoFilter::default(), None, &pool).await?;
+    let completed = todos
+        .into_iter()
+        .filter(|t| t.done)
+        .map(|t| t.id)
+        .collect::<Vec<_>>();
 
     // Delete the completed ones
-    db::delete_todos(completed, &pool).await?;
+    let deleted = db::delete_todos(list_id, &completed, &pool).await?;
+
+    let template = templates::render_all_todos(list_id, &filter, &pool).await?;
+    let message = format!("Deleted {} completed todos", deleted.len());
+    offer_undo(
+        list_id,
+        UndoAction::Delete,
+        &deleted,
+        message,
+        template,
+        &pool,
+    )
+    .await
+}
+
+// get /lists/:list_id/todos/delete_completed/confirm
+async fn confirm_delete_completed(
+    Path(list_id): Path<i32>,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let counts = db::count_todos(list_id, &TodoFilter::default(), &pool).await?;
+    let message = match counts.done {
+        1 => String::from("Delete 1 completed todo?"),
+        count => format!("Delete {count} completed todos?"),
+    };
+    Ok(HtmlTemplate(ConfirmDialogTemplate {
+        message,
+        confirm_label: String::from("Delete"),
+        method: "delete",
+        url: format!("/lists/{list_id}/todos/delete_completed"),
+        target: String::from("#todos"),
+    }))
+}
 
-    let template = templates::render_todos(pending, None);
+// post /lists/:list_id/todos/archive_completed
+async fn archive_completed(
+    Path(list_id): Path<i32>,
+    State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let archived = db::archive_completed(list_id, &pool).await?;
+
+    let template = templates::render_all_todos(list_id, &filter, &pool).await?;
+    let message = format!("Archived {} completed todos", archived.len());
+    offer_undo(
+        list_id,
+        UndoAction::Archive,
+        &archived,
+        message,
+        template,
+        &pool,
+    )
+    .await
+}
+
+// get /lists/:list_id/todos/archive
+async fn archive(
+    Path(list_id): Path<i32>,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let template = templates::render_archive(list_id, &pool).await?;
+    Ok(HtmlTemplate(template))
+}
+
+// post /lists/:list_id/todos/:id/restore
+async fn restore(
+    Path((list_id, todo_id)): Path<(i32, i32)>,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    db::restore(list_id, todo_id, &pool).await?;
+
+    let template = templates::render_archive(list_id, &pool).await?;
     Ok(HtmlTemplate(template))
 }
 
 #[derive(Deserialize)]
 struct TodoOrderingParams {
+    // empty when the only todo on screen is dragged onto another list
+    #[serde(default)]
     order: Vec<String>,
+    // set when a todo is dropped onto another list in the sidebar
+    moved: Option<i32>,
+    target_list_id: Option<i32>,
 }
 
-// post /todos/ordering
+// post /lists/:list_id/todos/ordering
+// The list the todo was dropped onto isn't on this page, so the pages showing it are told about
+// the change the same way as about this list's, see notify_list_changed
 async fn update_order(
+    Path(list_id): Path<i32>,
     State(pool): State<PgPool>,
+    Extension(notifier): Extension<Notifier>,
+    CurrentFilter(filter): CurrentFilter,
     Form(params): Form<TodoOrderingParams>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     println!("order params: {:?}", params.order);
-    let positions: Vec<(i32, i32)> = params
+    if let (Some(todo_id), Some(target_list_id)) = (params.moved, params.target_list_id) {
+        if target_list_id != list_id {
+            db::move_todos(list_id, &[todo_id], target_list_id, &pool).await?;
+            notifier.send(Notification::list_changed(target_list_id, None));
+        }
+    }
+
+    let ids = params
         .order
         .iter()
-        .rev()
-        .enumerate()
-        .map(|(pos, id)| (pos as i32, id.parse().unwrap_or(0)))
-        .collect::<Vec<_>>();
-    db::set_positions(positions, &pool).await?;
+        .filter_map(|id| id.parse().ok())
+        .collect::<Vec<i32>>();
+    // Only the todos on screen are reordered (the list may be filtered or not fully loaded),
+    // and those that were dragged get slotted in between their new neighbours
+    db::reorder(list_id, &ids, &pool).await?;
+
+    let template = templates::render_all_todos(list_id, &filter, &pool).await?;
+    with_counter(list_id, template, &pool).await
+}
+
+// post /lists/:list_id/todos/import/todoist
+// Takes a Todoist CSV export as the `file` field of a multipart form
+async fn import_todoist(
+    Path(list_id): Path<i32>,
+    State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
+    multipart: Multipart,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let csv = uploaded_file(multipart, "Choose a Todoist CSV export to import").await?;
+    let (todos, rows) =
+        import::parse_todoist(&csv).map_err(|err| (StatusCode::UNPROCESSABLE_ENTITY, err))?;
+    db::import_todos(list_id, &todos, &pool).await?;
+
+    let template = templates::render_all_todos(list_id, &filter, &pool).await?;
+    let summary = templates::TodosImportSummaryTemplate {
+        imported: todos.len(),
+        rows,
+    };
+    Ok(with_counter(list_id, template, &pool).await?.with(summary))
+}
+
+// post /lists/:list_id/todos/import/todotxt
+// Takes a todo.txt file as the `file` field of a multipart form
+async fn import_todotxt(
+    Path(list_id): Path<i32>,
+    State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
+    multipart: Multipart,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let file = uploaded_file(multipart, "Choose a todo.txt file to import").await?;
+    let text = std::str::from_utf8(&file).map_err(|_| {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("todo.txt files should be UTF-8 text"),
+        )
+    })?;
+    let (todos, rows) = todotxt::parse(text);
+    db::import_todos(list_id, &todos, &pool).await?;
+
+    let template = templates::render_all_todos(list_id, &filter, &pool).await?;
+    let summary = templates::TodosImportSummaryTemplate {
+        imported: todos.len(),
+        rows,
+    };
+    Ok(with_counter(list_id, template, &pool).await?.with(summary))
+}
+
+// The contents of the `file` field of a multipart form, `missing` is the error when there's none
+pub(super) async fn uploaded_file(
+    mut multipart: Multipart,
+    missing: &str,
+) -> Result<Bytes, (StatusCode, String)> {
+    let mut file = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?
+    {
+        if field.name() == Some("file") {
+            let bytes = field
+                .bytes()
+                .await
+                .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+            file = Some(bytes);
+        }
+    }
+    file.filter(|file| !file.is_empty())
+        .ok_or((StatusCode::UNPROCESSABLE_ENTITY, String::from(missing)))
+}
+
+// get /lists/:list_id/todos/:id/history
+async fn history(
+    Path((list_id, todo_id)): Path<(i32, i32)>,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let revisions = db::get_revisions(list_id, todo_id, &pool).await?;
+    Ok(HtmlTemplate(templates::TodoHistoryTemplate {
+        list_id,
+        id: todo_id,
+        revisions,
+    }))
+}
+
+// get /lists/:list_id/todos/:id/attachments
+// The files an emailed todo came with, loaded into its edit form
+async fn attachments(
+    Path((list_id, todo_id)): Path<(i32, i32)>,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let attachments = db::get_attachments(list_id, todo_id, &pool).await?;
+    Ok(HtmlTemplate(templates::TodoAttachmentsTemplate {
+        list_id,
+        id: todo_id,
+        attachments,
+    }))
+}
 
-    let template = templates::render_all_todos(&pool).await?;
+// get /lists/:list_id/todos/:id/attachments/:attachment_id
+// Always downloaded rather than shown, so that an HTML attachment can't run as a page of the app
+async fn download_attachment(
+    Path((list_id, todo_id, attachment_id)): Path<(i32, i32, i32)>,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let (file_name, data) = db::get_attachment(list_id, todo_id, attachment_id, &pool).await?;
+    // header values are visible ASCII, and the name sits between quotes
+    let file_name: String = file_name
+        .chars()
+        .map(|c| match c {
+            ' '..='~' if c != '"' && c != '\\' => c,
+            _ => '_',
+        })
+        .collect();
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        "application/octet-stream"
+            .parse()
+            .expect("should be able to create a Content-Type header"),
+    );
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        format!("attachment; filename=\"{file_name}\"")
+            .parse()
+            .expect("should be able to create a Content-Disposition header"),
+    );
+    Ok((headers, data))
+}
+
+// post /lists/:list_id/todos/:id/history/:revision_id/restore
+async fn restore_revision(
+    Path((list_id, todo_id, revision_id)): Path<(i32, i32, i32)>,
+    State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    db::restore_revision(list_id, todo_id, revision_id, &pool).await?;
+    let template = templates::render_all_todos(list_id, &filter, &pool).await?;
     Ok(HtmlTemplate(template))
 }
 
+// post /lists/:list_id/todos/:id/pin
+async fn toggle_pinned(
+    Path((list_id, todo_id)): Path<(i32, i32)>,
+    State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    db::toggle_pinned(list_id, todo_id, &pool).await?;
+
+    let template = templates::render_all_todos(list_id, &filter, &pool).await?;
+    Ok(HtmlTemplate(template))
+}
+
+// get /lists/:list_id/todos/:id/color
+async fn color_picker(
+    Path((list_id, todo_id)): Path<(i32, i32)>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    Ok(HtmlTemplate(templates::TodoColorPickerTemplate {
+        list_id,
+        id: todo_id,
+    }))
+}
+
 #[derive(Debug, Deserialize)]
+struct TodoColorParams {
+    color: String,
+}
+
+// put /lists/:list_id/todos/:id/color
+async fn set_color(
+    Path((list_id, todo_id)): Path<(i32, i32)>,
+    State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
+    Form(params): Form<TodoColorParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let color = Color::from_param(&params.color);
+    db::set_color(list_id, todo_id, color, &pool).await?;
+
+    let template = templates::render_all_todos(list_id, &filter, &pool).await?;
+    Ok(HtmlTemplate(template))
+}
+
+// post /lists/:list_id/todos/:id/move-up
+async fn move_up(
+    Path((list_id, todo_id)): Path<(i32, i32)>,
+    State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    move_todo(list_id, todo_id, true, &filter, &pool).await
+}
+
+// post /lists/:list_id/todos/:id/move-down
+async fn move_down(
+    Path((list_id, todo_id)): Path<(i32, i32)>,
+    State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    move_todo(list_id, todo_id, false, &filter, &pool).await
+}
+
+// Responds with just the two todos that swapped places, or the todo on its own if it
+// was already at the top (or bottom)
+async fn move_todo(
+    list_id: i32,
+    todo_id: i32,
+    up: bool,
+    filter: &TodoFilter,
+    pool: &PgPool,
+) -> Result<Response, (StatusCode, String)> {
+    match db::swap_with_neighbour(list_id, todo_id, up, filter, pool).await? {
+        Some(neighbour_id) => {
+            let template =
+                templates::render_swapped(list_id, todo_id, neighbour_id, up, pool).await?;
+            Ok(HtmlTemplate(template).into_response())
+        }
+        None => {
+            let template: templates::TodoLiTemplate =
+                db::get_todo(list_id, todo_id, pool).await?.into();
+            Ok(HtmlTemplate(template).into_response())
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 struct TodoUpdateParams {
     done: Option<String>,
     description: Option<String>,
+    notes: Option<String>,
+    due_at: Option<String>,
+    priority: Option<Priority>,
+    recurrence: Option<String>,
+    remind_days_before: Option<String>,
+    estimate_minutes: Option<String>,
+    // the version the edit form was rendered from
+    version: Option<i32>,
+}
+
+const DATE_FORMAT: &[FormatItem<'static>] = format_description!("[year]-[month]-[day]");
+
+// Date inputs send an empty string when no date is picked
+fn parse_due_at(due_at: Option<String>) -> Result<Option<Date>, (StatusCode, String)> {
+    match due_at.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(due_at) => Date::parse(due_at, DATE_FORMAT).map(Some).map_err(|err| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Invalid due date: {err}"),
+            )
+        }),
+    }
+}
+
+// Number inputs also send an empty string when left blank
+fn parse_remind_days_before(days: Option<String>) -> Result<Option<i32>, (StatusCode, String)> {
+    match days.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(days) => days
+            .parse()
+            .ok()
+            .filter(|days: &i32| *days >= 0)
+            .map(Some)
+            .ok_or((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Invalid number of days before the due date: {days}"),
+            )),
+    }
+}
+
+fn parse_estimate_minutes(minutes: Option<String>) -> Result<Option<i32>, (StatusCode, String)> {
+    match minutes.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(minutes) => minutes
+            .parse()
+            .ok()
+            .filter(|minutes: &i32| *minutes > 0)
+            .map(Some)
+            .ok_or((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Invalid estimate in minutes: {minutes}"),
+            )),
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -170,48 +815,476 @@ impl From<String> for CheckBox {
     }
 }
 
-// put /todos/:id
+// put /lists/:list_id/todos/:id
+#[utoipa::path(
+    put,
+    tag = "todos",
+    path = "/lists/{list_id}/todos/{id}",
+    params(("list_id" = i32, Path), ("id" = i32, Path)),
+    request_body(content = TodoUpdateParams, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "The list's todos, with the change", body = [TodoJson]),
+        (status = 409, description = "The todo changed since `version`", body = utils::JsonError),
+        (status = 422, description = "The description is empty or too long", body = utils::JsonError),
+    ),
+)]
 async fn update(
-    Path(todo_id): Path<i32>,
+    Path((list_id, todo_id)): Path<(i32, i32)>,
     State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
+    WantsJson(json): WantsJson,
     Form(params): Form<TodoUpdateParams>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<Response, (StatusCode, String)> {
     let check_box: CheckBox = params.done.unwrap_or(String::from("Off")).into();
     let check_box: bool = check_box.into();
-    let query: Query<'_, Postgres, PgArguments>;
 
-    // Right now, updates come from either the edit form (which just ships up a description)
-    // or clicking the checkbox (which just ships up the check_box)
+    // Right now, updates come from either the edit form (which ships up a description, notes,
+    // due date, priority, recurrence and reminder) or clicking the checkbox (which just ships up the check_box)
     // So we only set one or the other
     if let Some(description) = params.description {
-        query = sqlx::query!(
-            "Update todos set description = $1 where id = $2",
+        let description = match validate_description(&description) {
+            Ok(description) => description,
+            Err(message) => {
+                return Ok(description_error(
+                    format!("todo-{todo_id}-error"),
+                    message,
+                    json,
+                ))
+            }
+        };
+        let due_at = parse_due_at(params.due_at)?;
+        let remind_days_before = parse_remind_days_before(params.remind_days_before)?;
+        let estimate_minutes = parse_estimate_minutes(params.estimate_minutes)?;
+        let priority = params.priority.unwrap_or_default();
+        let recurrence = params
+            .recurrence
+            .as_deref()
+            .and_then(Recurrence::from_param);
+        // Moving the due date or the reminder means the todo is worth reminding about again.
+        // The description and notes being replaced are kept as a revision, see history.
+        // A form without a version (from before versions existed) saves unconditionally.
+        let result = sqlx::query!(
+            "with revision as (
+               insert into todo_revisions (todo_id, description, notes)
+               select id, description, notes from todos
+               where id = $6 and list_id = $7 and ($10::integer is null or version = $10)
+                 and (description <> $1 or notes <> $2)
+             )
+             Update todos set description = $1, notes = $2, due_at = $3, priority = $4, recurrence = $5, remind_days_before = $8,
+               estimate_minutes = $9,
+               reminded_at = case when due_at is distinct from $3 or remind_days_before is distinct from $8 then null else reminded_at end
+             where id = $6 and list_id = $7 and ($10::integer is null or version = $10)",
             description,
+            params.notes.unwrap_or_default(),
+            due_at,
+            priority as _,
+            recurrence as _,
             todo_id,
+            list_id,
+            remind_days_before,
+            estimate_minutes,
+            params.version,
         )
+        .execute(&pool)
+        .await
+        .map_err(utils::internal_error)?;
+        if result.rows_affected() == 0 {
+            // Either the todo is gone (a 404 from get_todo) or it was changed since the form was opened.
+            // The form stays as it is so that nothing typed is lost, with a way to load the latest version.
+            db::get_todo(list_id, todo_id, &pool).await?;
+            if json {
+                return Ok(utils::json_error(
+                    StatusCode::CONFLICT,
+                    "The todo was changed since this version, fetch it again and retry",
+                ));
+            }
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                "HX-Retarget",
+                format!("#todo-{todo_id}-conflict")
+                    .parse()
+                    .expect("should be able to create a HX-Retarget header"),
+            );
+            headers.insert(
+                "HX-Reswap",
+                "innerHTML"
+                    .parse()
+                    .expect("should be able to create a HX-Reswap header"),
+            );
+            let template = templates::TodoConflictTemplate {
+                list_id,
+                id: todo_id,
+            };
+            return Ok((StatusCode::CONFLICT, headers, HtmlTemplate(template)).into_response());
+        }
     } else {
-        query = sqlx::query!(
-            "UPDATE todos set done = $1 where id = $2",
-            check_box,
-            todo_id,
-        )
+        db::set_done(list_id, todo_id, check_box, &pool).await?;
+    }
+    if json {
+        return Ok(todos_json(list_id, &filter, &pool).await?.into_response());
     }
-    query.execute(&pool).await.map_err(utils::internal_error)?;
 
-    let template = templates::render_all_todos(&pool).await?;
-    Ok(HtmlTemplate(template))
+    let template = templates::render_all_todos(list_id, &filter, &pool).await?;
+    Ok(with_counter(list_id, template, &pool)
+        .await?
+        .into_response())
 }
 
-// delete /todos/:id
+// delete /lists/:list_id/todos/:id
+#[utoipa::path(
+    delete,
+    tag = "todos",
+    path = "/lists/{list_id}/todos/{id}",
+    params(("list_id" = i32, Path), ("id" = i32, Path)),
+    responses((status = 200, description = "The list's todos, without the deleted one", body = [TodoJson])),
+)]
 async fn destroy(
-    Path(todo_id): Path<i32>,
+    Path((list_id, todo_id)): Path<(i32, i32)>,
+    State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
+    WantsJson(json): WantsJson,
+) -> Result<Response, (StatusCode, String)> {
+    let deleted = db::delete_todos(list_id, &[todo_id], &pool).await?;
+    if json {
+        return Ok(todos_json(list_id, &filter, &pool).await?.into_response());
+    }
+
+    let template = templates::render_all_todos(list_id, &filter, &pool).await?;
+    let message = String::from("Deleted a todo");
+    let response = offer_undo(
+        list_id,
+        UndoAction::Delete,
+        &deleted,
+        message,
+        template,
+        &pool,
+    )
+    .await?;
+    Ok(response.into_response())
+}
+
+// post /lists/:list_id/todos/undo/:token
+async fn undo(
+    Path((list_id, token)): Path<(i32, String)>,
     State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    sqlx::query!("DELETE FROM todos where id = $1", todo_id)
-        .execute(&pool)
-        .await
-        .map_err(utils::internal_error)?;
+    db::undo(list_id, &token, &pool).await?;
+
+    let template = templates::render_all_todos(list_id, &filter, &pool).await?;
+    with_counter(list_id, template, &pool).await
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum BulkAction {
+    Complete,
+    Delete,
+    Move,
+    Tag,
+}
+
+#[derive(Debug, Deserialize)]
+struct TodoBulkParams {
+    action: BulkAction,
+    // the ids of the todos whose selection box is checked
+    #[serde(default)]
+    selected: Vec<i32>,
+    target_list_id: Option<i32>,
+    tag: Option<String>,
+}
+
+// post /lists/:list_id/todos/bulk
+async fn bulk(
+    Path(list_id): Path<i32>,
+    State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
+    Form(params): Form<TodoBulkParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let ids = params.selected;
+    let mut deleted = vec![];
+    let mut notice = None;
+    match params.action {
+        BulkAction::Complete => {
+            let skipped = db::complete_todos(list_id, &ids, &pool).await?;
+            if !skipped.is_empty() {
+                notice = Some(format!(
+                    "Still blocked, so not completed: {}",
+                    skipped.join(", ")
+                ));
+            }
+        }
+        BulkAction::Delete => deleted = db::delete_todos(list_id, &ids, &pool).await?,
+        BulkAction::Move => {
+            let target_list_id = params.target_list_id.ok_or((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                String::from("Pick a list to move the todos to"),
+            ))?;
+            db::move_todos(list_id, &ids, target_list_id, &pool).await?
+        }
+        BulkAction::Tag => {
+            let tag = params.tag.as_deref().map(str::trim).unwrap_or_default();
+            if tag.is_empty() {
+                return Err((
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    String::from("Tags can't be blank"),
+                ));
+            }
+            db::tag_todos(list_id, &ids, tag, &pool).await?
+        }
+    }
+
+    let mut template = templates::render_all_todos(list_id, &filter, &pool).await?;
+    template.notice = notice;
+    let message = format!("Deleted {} todos", deleted.len());
+    offer_undo(
+        list_id,
+        UndoAction::Delete,
+        &deleted,
+        message,
+        template,
+        &pool,
+    )
+    .await
+}
+
+// The list's todos as they are after a change, for requests that want JSON instead of the
+// re-rendered list
+async fn todos_json(
+    list_id: i32,
+    filter: &TodoFilter,
+    pool: &PgPool,
+) -> Result<Json<Vec<TodoJson>>, (StatusCode, String)> {
+    let todos = db::get_todos(list_id, filter, None, pool).await?;
+    Ok(Json(todos.into_iter().map(TodoJson::from).collect()))
+}
+
+// Record the change so that it can be undone and show the undo toast with the list.
+// The HX-Trigger lets the page hide the toast once the undo window has passed.
+async fn offer_undo(
+    list_id: i32,
+    action: UndoAction,
+    todo_ids: &[i32],
+    message: String,
+    mut template: templates::TodosInnerTemplate,
+    pool: &PgPool,
+) -> Result<(HeaderMap, HtmlFragments), (StatusCode, String)> {
+    let mut headers = HeaderMap::new();
+    if todo_ids.is_empty() {
+        return Ok((headers, with_counter(list_id, template, pool).await?));
+    }
+
+    let token = db::record_undo(list_id, action, todo_ids, pool).await?;
+    headers.insert(
+        "HX-Trigger",
+        format!(r#"{{"undoAvailable": {{"token": "{token}", "seconds": {UNDO_WINDOW_SECONDS}}}}}"#)
+            .parse()
+            .expect("should be able to create a HX-Trigger header"),
+    );
+    template.undo = Some(templates::UndoToast { token, message });
+    Ok((headers, with_counter(list_id, template, pool).await?))
+}
+
+// The list followed by the "N items left" counter in the header, for changes that may
+// have added, removed, checked or unchecked todos
+async fn with_counter(
+    list_id: i32,
+    template: templates::TodosInnerTemplate,
+    pool: &PgPool,
+) -> Result<HtmlFragments, (StatusCode, String)> {
+    let counter = templates::render_counter(list_id, pool).await?;
+    Ok(HtmlFragments::new().with(template).with(counter))
+}
+
+// get /lists/:list_id/todos/:id/blockers
+async fn blocker_picker(
+    Path((list_id, todo_id)): Path<(i32, i32)>,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let candidates = db::get_blocker_candidates(list_id, todo_id, &pool).await?;
+    Ok(HtmlTemplate(templates::TodoBlockerPickerTemplate {
+        list_id,
+        id: todo_id,
+        candidates,
+    }))
+}
+
+#[derive(Deserialize)]
+struct TodoBlockerParams {
+    blocker_id: i32,
+}
+
+// post /lists/:list_id/todos/:id/blockers
+async fn add_blocker(
+    Path((list_id, todo_id)): Path<(i32, i32)>,
+    State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
+    Form(params): Form<TodoBlockerParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    db::add_blocker(list_id, todo_id, params.blocker_id, &pool).await?;
+
+    let template = templates::render_all_todos(list_id, &filter, &pool).await?;
+    Ok(HtmlTemplate(template))
+}
+
+// delete /lists/:list_id/todos/:id/blockers/:blocker_id
+async fn remove_blocker(
+    Path((list_id, todo_id, blocker_id)): Path<(i32, i32, i32)>,
+    State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    db::remove_blocker(list_id, todo_id, blocker_id, &pool).await?;
+
+    let template = templates::render_all_todos(list_id, &filter, &pool).await?;
+    Ok(HtmlTemplate(template))
+}
+
+#[derive(Deserialize)]
+struct TodoTagParams {
+    tag: String,
+}
 
-    let template = templates::render_all_todos(&pool).await?;
+// post /lists/:list_id/todos/:id/tags
+async fn add_tag(
+    Path((list_id, todo_id)): Path<(i32, i32)>,
+    State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
+    Form(params): Form<TodoTagParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let tag = params.tag.trim();
+    if tag.is_empty() {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("Tags can't be blank"),
+        ));
+    }
+    db::add_tag(list_id, todo_id, tag, &pool).await?;
+
+    let template = templates::render_all_todos(list_id, &filter, &pool).await?;
+    Ok(HtmlTemplate(template))
+}
+
+// delete /lists/:list_id/todos/:id/tags/:name
+async fn remove_tag(
+    Path((list_id, todo_id, tag)): Path<(i32, i32, String)>,
+    State(pool): State<PgPool>,
+    CurrentFilter(filter): CurrentFilter,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    db::remove_tag(list_id, todo_id, &tag, &pool).await?;
+
+    let template = templates::render_all_todos(list_id, &filter, &pool).await?;
     Ok(HtmlTemplate(template))
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn add_todo(list_id: i32, idempotency_key: &str) -> Request {
+        Request::post(format!("/lists/{list_id}/todos"))
+            .header("HX-Request", "true")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .header("Idempotency-Key", idempotency_key)
+            .body(Body::from("description=Buy+paint"))
+            .unwrap()
+    }
+
+    async fn new_list(pool: &PgPool) -> i32 {
+        sqlx::query_scalar("insert into lists (name) values ('Home') returning id")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn count_todos(list_id: i32, pool: &PgPool) -> i64 {
+        sqlx::query_scalar("select count(*) from todos where list_id = $1")
+            .bind(list_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn retrying_an_added_todo_changes_nothing(pool: PgPool) {
+        let list_id = new_list(&pool).await;
+        let notifier = Notifier::new();
+        let mut notifications = notifier.subscribe();
+        let app = Router::new()
+            .nest("/lists/:list_id/todos", routes(&pool))
+            .layer(Extension(notifier));
+
+        let first = app.clone().oneshot(add_todo(list_id, "abc")).await.unwrap();
+        let retry = app.oneshot(add_todo(list_id, "abc")).await.unwrap();
+
+        assert_eq!(first.status(), StatusCode::OK);
+        assert!(first.headers().contains_key("HX-Trigger"));
+        assert_eq!(retry.status(), StatusCode::OK);
+        assert!(!retry.headers().contains_key("HX-Trigger"));
+        assert!(notifications.try_recv().is_ok());
+        assert!(notifications.try_recv().is_err());
+        assert_eq!(count_todos(list_id, &pool).await, 1);
+    }
+
+    #[sqlx::test]
+    async fn the_same_key_adds_a_todo_to_another_list(pool: PgPool) {
+        let home = new_list(&pool).await;
+        let work = new_list(&pool).await;
+        let app = Router::new()
+            .nest("/lists/:list_id/todos", routes(&pool))
+            .layer(Extension(Notifier::new()));
+
+        app.clone().oneshot(add_todo(home, "abc")).await.unwrap();
+        let other_list = app.oneshot(add_todo(work, "abc")).await.unwrap();
+
+        assert!(other_list.headers().contains_key("HX-Trigger"));
+        assert_eq!(count_todos(home, &pool).await, 1);
+        assert_eq!(count_todos(work, &pool).await, 1);
+    }
+
+    #[sqlx::test]
+    async fn plain_requests_get_html(pool: PgPool) {
+        let list_id = new_list(&pool).await;
+        let app = Router::new()
+            .nest("/lists/:list_id/todos", routes(&pool))
+            .layer(Extension(Notifier::new()));
+
+        let response = app
+            .oneshot(
+                Request::get(format!("/lists/{list_id}/todos"))
+                    .header(header::ACCEPT, "*/*")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html"));
+    }
+
+    #[sqlx::test]
+    async fn json_requests_need_a_token(pool: PgPool) {
+        let list_id = new_list(&pool).await;
+        let app = Router::new()
+            .nest("/lists/:list_id/todos", routes(&pool))
+            .layer(Extension(Notifier::new()));
+
+        let response = app
+            .oneshot(
+                Request::get(format!("/lists/{list_id}/todos"))
+                    .header(header::ACCEPT, "application/json")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,444 @@
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Extension, Json, Router,
+};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::{PgPool, Pool, Postgres};
+use time::OffsetDateTime;
+
+use crate::{
+    config::SlackConfig,
+    notifications::{Notification, Notifier},
+    utils,
+};
+
+use super::{db, routes::validate_description, Priority};
+
+// Slack signs each request, see https://api.slack.com/authentication/verifying-requests-from-slack
+const TIMESTAMP_HEADER: &str = "X-Slack-Request-Timestamp";
+const SIGNATURE_HEADER: &str = "X-Slack-Signature";
+// Older requests are refused, so that a captured one can't be replayed later
+const MAX_REQUEST_AGE_SECONDS: i64 = 5 * 60;
+
+// Buttons only answer through the response_url Slack sends with them
+const RESPONSE_URL_PREFIX: &str = "https://hooks.slack.com/";
+
+// The slash command and the buttons in its replies, only there when SLACK_SIGNING_SECRET is set.
+// Set the app's slash command Request URL and its Interactivity Request URL to this.
+pub fn webhook_routes(pool: &Pool<Postgres>, config: SlackConfig, notifier: Notifier) -> Router {
+    let Some(signing_secret) = config.signing_secret else {
+        return Router::new();
+    };
+    Router::new()
+        .route("/integrations/slack", post(receive))
+        .layer(Extension(SlackApp {
+            signing_secret,
+            list_id: config.list_id,
+            client: reqwest::Client::new(),
+        }))
+        .layer(Extension(notifier))
+        .with_state(pool.clone())
+}
+
+#[derive(Clone)]
+struct SlackApp {
+    signing_secret: String,
+    list_id: Option<i32>,
+    client: reqwest::Client,
+}
+
+// Slash commands and button presses are both posted as forms. A command has the text typed
+// after it, a button press has a `payload` of JSON.
+#[derive(Deserialize)]
+struct Submission {
+    text: Option<String>,
+    payload: Option<String>,
+}
+
+// The parts of a block_actions payload the app looks at
+// (https://api.slack.com/reference/interaction-payloads/block-actions)
+#[derive(Deserialize)]
+struct Interaction {
+    #[serde(default)]
+    actions: Vec<Action>,
+    response_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Action {
+    action_id: String,
+    value: Option<String>,
+}
+
+// A reply only the person who used the command sees
+#[derive(Serialize)]
+struct Reply {
+    response_type: &'static str,
+    // replaces the message a button was pressed in
+    replace_original: bool,
+    text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    blocks: Vec<Block>,
+}
+
+impl Reply {
+    fn text(text: String) -> Self {
+        Reply {
+            response_type: "ephemeral",
+            replace_original: false,
+            text: escape(&text),
+            blocks: Vec::new(),
+        }
+    }
+}
+
+// A section of Block Kit (https://api.slack.com/reference/block-kit/blocks#section),
+// with a Done button for todos
+#[derive(Serialize)]
+struct Block {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    text: Text,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    accessory: Option<Button>,
+}
+
+#[derive(Serialize)]
+struct Text {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    text: String,
+}
+
+#[derive(Serialize)]
+struct Button {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    text: Text,
+    action_id: &'static str,
+    value: String,
+}
+
+impl Block {
+    fn section(text: String, accessory: Option<Button>) -> Self {
+        Block {
+            kind: "section",
+            text: Text {
+                kind: "mrkdwn",
+                text,
+            },
+            accessory,
+        }
+    }
+}
+
+// post /integrations/slack
+async fn receive(
+    State(pool): State<PgPool>,
+    Extension(app): Extension<SlackApp>,
+    Extension(notifier): Extension<Notifier>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, (StatusCode, String)> {
+    if !is_signed(
+        &app.signing_secret,
+        &headers,
+        &body,
+        OffsetDateTime::now_utc(),
+    ) {
+        return Err((StatusCode::UNAUTHORIZED, String::from("Invalid signature")));
+    }
+    let submission: Submission = serde_urlencoded::from_bytes(&body)
+        .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+
+    if let Some(payload) = submission.payload {
+        let Json(interaction) = Json::<Interaction>::from_bytes(payload.as_bytes())
+            .map_err(|err| (StatusCode::BAD_REQUEST, err.body_text()))?;
+        press(&app, interaction, &notifier, &pool).await?;
+        return Ok(StatusCode::OK.into_response());
+    }
+
+    let text = submission.text.unwrap_or_default();
+    let reply = match parse_command(text.trim()) {
+        ("add", description) => Reply::text(add(&app, description, &notifier, &pool).await?),
+        ("today", _) => today(None, &pool).await?,
+        ("done", argument) => {
+            let message = match argument.parse::<i32>() {
+                Ok(todo_id) => done(todo_id, &notifier, &pool).await?,
+                Err(_) => String::from("Send /todo done with the number of the todo."),
+            };
+            Reply::text(message)
+        }
+        _ => Reply::text(String::from(HELP)),
+    };
+    Ok(Json(reply).into_response())
+}
+
+const HELP: &str = "/todo add Buy milk adds a todo
+/todo today lists what's due today and overdue, with buttons to check them off
+/todo done 12 checks off todo 12";
+
+// Slack's signature is an HMAC-SHA256 of "v0:<timestamp>:<body>" with the signing secret
+fn is_signed(secret: &str, headers: &HeaderMap, body: &[u8], now: OffsetDateTime) -> bool {
+    let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+    let (Some(timestamp), Some(signature)) = (header(TIMESTAMP_HEADER), header(SIGNATURE_HEADER))
+    else {
+        return false;
+    };
+    let Ok(sent_at) = timestamp.parse::<i64>() else {
+        return false;
+    };
+    if (now.unix_timestamp() - sent_at).abs() > MAX_REQUEST_AGE_SECONDS {
+        return false;
+    }
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take a key of any size");
+    mac.update(format!("v0:{timestamp}:").as_bytes());
+    mac.update(body);
+    let expected: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    utils::tokens_match(&format!("v0={expected}"), signature)
+}
+
+// The subcommand and what follows it, e.g. ("done", "12") for "/todo done 12". Text that doesn't
+// start with a subcommand is a todo to add, and no text at all asks for help.
+fn parse_command(text: &str) -> (&str, &str) {
+    let (word, argument) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    match word.to_ascii_lowercase().as_str() {
+        "add" => ("add", argument.trim()),
+        "today" => ("today", ""),
+        "done" => ("done", argument.trim()),
+        "" | "help" => ("help", ""),
+        _ => ("add", text),
+    }
+}
+
+// A Done button pressed in the reply to /todo today. The reply is replaced with what's left.
+async fn press(
+    app: &SlackApp,
+    interaction: Interaction,
+    notifier: &Notifier,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let Some(todo_id) = interaction
+        .actions
+        .iter()
+        .find(|action| action.action_id == "done")
+        .and_then(|action| action.value.as_deref()?.parse::<i32>().ok())
+    else {
+        return Ok(());
+    };
+    let message = done(todo_id, notifier, pool).await?;
+    let Some(response_url) = interaction
+        .response_url
+        .filter(|url| url.starts_with(RESPONSE_URL_PREFIX))
+    else {
+        return Ok(());
+    };
+    let mut reply = today(Some(message), pool).await?;
+    reply.replace_original = true;
+    // Slack wants an answer to the press within 3 seconds, the new message can follow it
+    let client = app.client.clone();
+    tokio::spawn(async move {
+        let sent = client
+            .post(&response_url)
+            .json(&reply)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+        if let Err(err) = sent {
+            tracing::error!(%err, "could not update the Slack message");
+        }
+    });
+    Ok(())
+}
+
+async fn add(
+    app: &SlackApp,
+    text: &str,
+    notifier: &Notifier,
+    pool: &PgPool,
+) -> Result<String, (StatusCode, String)> {
+    let description = match validate_description(text) {
+        Ok(description) => description,
+        Err(message) => return Ok(message),
+    };
+    let list_id = match app.list_id {
+        Some(list_id) => list_id,
+        None => match db::get_first_list_id(pool).await? {
+            Some(list_id) => list_id,
+            None => return Ok(String::from("There's no list to add the todo to.")),
+        },
+    };
+    db::create_todo(list_id, description, None, Priority::default(), None, pool).await?;
+    notifier.send(Notification::list_changed(list_id, None));
+    Ok(format!("Added: {description}"))
+}
+
+// What's overdue and due today across every list, like the agenda page, each with a Done
+// button. `message` goes above them, e.g. what was just checked off.
+async fn today(message: Option<String>, pool: &PgPool) -> Result<Reply, (StatusCode, String)> {
+    let today = OffsetDateTime::now_utc().date();
+    let agenda = db::get_agenda(today, pool).await?;
+    let summary = if agenda.is_empty() {
+        String::from("Nothing is due today.")
+    } else {
+        format!("{} due today or overdue", agenda.len())
+    };
+    let mut blocks: Vec<Block> = message
+        .iter()
+        .map(|message| Block::section(escape(message), None))
+        .collect();
+    blocks.push(Block::section(escape(&summary), None));
+    blocks.extend(agenda.iter().map(|todo| {
+        let overdue = if todo.due_at < today { ", overdue" } else { "" };
+        Block::section(
+            format!(
+                "{} _({}{overdue})_",
+                escape(&todo.description),
+                escape(&todo.list_name)
+            ),
+            Some(Button {
+                kind: "button",
+                text: Text {
+                    kind: "plain_text",
+                    text: String::from("Done"),
+                },
+                action_id: "done",
+                value: todo.id.to_string(),
+            }),
+        )
+    }));
+    Ok(Reply {
+        response_type: "ephemeral",
+        replace_original: false,
+        // shown in notifications, where blocks aren't
+        text: escape(&message.unwrap_or(summary)),
+        blocks,
+    })
+}
+
+async fn done(
+    todo_id: i32,
+    notifier: &Notifier,
+    pool: &PgPool,
+) -> Result<String, (StatusCode, String)> {
+    let list_id = match db::get_list_id(todo_id, pool).await {
+        Ok(list_id) => list_id,
+        Err((StatusCode::NOT_FOUND, _)) => return Ok(format!("There's no todo {todo_id}.")),
+        Err(err) => return Err(err),
+    };
+    match db::set_done(list_id, todo_id, true, pool).await {
+        Ok(()) => {}
+        // blocked by other todos
+        Err((StatusCode::UNPROCESSABLE_ENTITY, message)) => return Ok(message),
+        Err(err) => return Err(err),
+    }
+    notifier.send(Notification::list_changed(list_id, None));
+    let todo = db::get_todo(list_id, todo_id, pool).await?;
+    Ok(format!("Done: {}", todo.description))
+}
+
+// Slack reads &, < and > in text as markup (https://api.slack.com/reference/surfaces/formatting#escaping)
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    // The example from Slack's documentation on verifying requests
+    const SECRET: &str = "8f742231b10e8888abcd99yyyzzz85a5";
+    const TIMESTAMP: i64 = 1531420618;
+    const BODY: &str = "token=xyzz0WbapA4vBCDEFasx0q6G&team_id=T1DC2JH3J&team_domain=testteamnow&channel_id=G8PSS9T3V&channel_name=foobar&user_id=U2CERLKJA&user_name=roadrunner&command=%2Fwebhook-collect&text=&response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2FT1DC2JH3J%2F397700885554%2F96rGlfmibIGlgcZRskXaIFfN&trigger_id=398738663015.47445629121.803a0bc887a14d10d2c447fce8b6703c";
+    const SIGNATURE: &str = "v0=a2114d57b48eac39b9ad189dd8316235a7b4a8d21a10bd27519666489c69b503";
+
+    fn signed_headers(timestamp: i64, signature: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(TIMESTAMP_HEADER, HeaderValue::from(timestamp));
+        headers.insert(SIGNATURE_HEADER, HeaderValue::from_str(signature).unwrap());
+        headers
+    }
+
+    fn at(timestamp: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(timestamp).unwrap()
+    }
+
+    #[test]
+    fn accepts_a_signed_request() {
+        let headers = signed_headers(TIMESTAMP, SIGNATURE);
+        assert!(is_signed(
+            SECRET,
+            &headers,
+            BODY.as_bytes(),
+            at(TIMESTAMP + 10)
+        ));
+    }
+
+    #[test]
+    fn rejects_a_changed_body_or_the_wrong_secret() {
+        let headers = signed_headers(TIMESTAMP, SIGNATURE);
+        let changed = BODY.replace("text=", "text=add");
+        assert!(!is_signed(
+            SECRET,
+            &headers,
+            changed.as_bytes(),
+            at(TIMESTAMP)
+        ));
+        assert!(!is_signed(
+            "another secret",
+            &headers,
+            BODY.as_bytes(),
+            at(TIMESTAMP)
+        ));
+    }
+
+    #[test]
+    fn rejects_an_old_request() {
+        let headers = signed_headers(TIMESTAMP, SIGNATURE);
+        let later = at(TIMESTAMP + MAX_REQUEST_AGE_SECONDS + 1);
+        assert!(!is_signed(SECRET, &headers, BODY.as_bytes(), later));
+    }
+
+    #[test]
+    fn rejects_a_request_without_a_signature() {
+        let mut headers = signed_headers(TIMESTAMP, SIGNATURE);
+        headers.remove(SIGNATURE_HEADER);
+        assert!(!is_signed(SECRET, &headers, BODY.as_bytes(), at(TIMESTAMP)));
+    }
+
+    #[test]
+    fn reads_subcommands() {
+        assert_eq!(parse_command("add Buy milk"), ("add", "Buy milk"));
+        assert_eq!(parse_command("Today"), ("today", ""));
+        assert_eq!(parse_command("done  12"), ("done", "12"));
+        assert_eq!(parse_command(""), ("help", ""));
+        assert_eq!(parse_command("help"), ("help", ""));
+    }
+
+    #[test]
+    fn adds_text_without_a_subcommand() {
+        assert_eq!(parse_command("Buy milk"), ("add", "Buy milk"));
+        assert_eq!(parse_command("addresses"), ("add", "addresses"));
+    }
+
+    #[test]
+    fn escapes_markup() {
+        assert_eq!(escape("<b> & co"), "&lt;b&gt; &amp; co");
+    }
+}
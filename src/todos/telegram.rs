@@ -0,0 +1,359 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Extension, Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Pool, Postgres};
+use time::OffsetDateTime;
+
+use crate::{
+    config::TelegramConfig,
+    csrf::CsrfToken,
+    notifications::{Notification, Notifier},
+    utils::{self, ConfirmDialogTemplate, HtmlTemplate},
+};
+
+use super::{db, routes::MAX_DESCRIPTION_CHARS, templates, Priority};
+
+// How long a code for linking a chat can be used
+const LINK_CODE_TTL_MINUTES: i32 = 15;
+const LINK_CODE_LENGTH: usize = 8;
+
+// Telegram sends the webhook's secret_token in this header
+const SECRET_HEADER: &str = "X-Telegram-Bot-Api-Secret-Token";
+
+// telegram routes, nested under /telegram
+// The page for linking chats to the bot
+pub fn routes(pool: &Pool<Postgres>) -> Router {
+    Router::new()
+        .route("/", get(show))
+        .route("/code", post(create_code))
+        .route("/chats/:chat_id", delete(unlink))
+        .route("/chats/:chat_id/confirm_delete", get(confirm_unlink))
+        .with_state(pool.clone())
+}
+
+// The webhook the bot's updates are posted to, only there when TELEGRAM_WEBHOOK_SECRET is set
+// Point the bot at it with setWebhook, passing the same secret as secret_token
+pub fn webhook_routes(pool: &Pool<Postgres>, config: TelegramConfig, notifier: Notifier) -> Router {
+    let Some(secret) = config.secret else {
+        return Router::new();
+    };
+    Router::new()
+        .route("/integrations/telegram", post(receive))
+        .layer(Extension(Bot {
+            secret,
+            list_id: config.list_id,
+        }))
+        .layer(Extension(notifier))
+        .with_state(pool.clone())
+}
+
+#[derive(Clone)]
+struct Bot {
+    secret: String,
+    list_id: Option<i32>,
+}
+
+// The parts of an update (https://core.telegram.org/bots/api#update) the bot looks at.
+// Edited messages, reactions and the like have no `message` and are ignored.
+#[derive(Deserialize)]
+struct Update {
+    message: Option<Message>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    chat: Chat,
+    // photos and stickers don't have any
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Chat {
+    id: i64,
+    // groups have a title, private chats the person's names
+    title: Option<String>,
+    first_name: Option<String>,
+    username: Option<String>,
+}
+
+impl Chat {
+    fn name(&self) -> String {
+        self.title
+            .clone()
+            .or_else(|| self.first_name.clone())
+            .or_else(|| self.username.clone())
+            .unwrap_or_else(|| self.id.to_string())
+    }
+}
+
+// A reply, made by answering the webhook with a method call so that the bot's token isn't needed
+#[derive(Serialize)]
+struct SendMessage {
+    method: &'static str,
+    chat_id: i64,
+    text: String,
+}
+
+// get /telegram
+async fn show(
+    State(pool): State<PgPool>,
+    CsrfToken(csrf_token): CsrfToken,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let chats = db::get_telegram_chats(&pool).await?;
+    let chats = templates::render_telegram_chats(chats, None, LINK_CODE_TTL_MINUTES);
+    Ok(HtmlTemplate(templates::render_telegram(csrf_token, chats)))
+}
+
+// post /telegram/code
+// A one-time code to send the bot from the chat to link
+async fn create_code(
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let code = utils::random_token(LINK_CODE_LENGTH);
+    db::create_telegram_link_code(&code, LINK_CODE_TTL_MINUTES, &pool).await?;
+    let chats = db::get_telegram_chats(&pool).await?;
+    Ok(HtmlTemplate(templates::render_telegram_chats(
+        chats,
+        Some(code),
+        LINK_CODE_TTL_MINUTES,
+    )))
+}
+
+// get /telegram/chats/:chat_id/confirm_delete
+async fn confirm_unlink(Path(chat_id): Path<i64>) -> impl IntoResponse {
+    HtmlTemplate(ConfirmDialogTemplate {
+        message: String::from("Unlink this chat? The bot will stop taking its messages."),
+        confirm_label: String::from("Unlink chat"),
+        method: "delete",
+        url: format!("/telegram/chats/{chat_id}"),
+        target: String::from("#telegram-chats"),
+    })
+}
+
+// delete /telegram/chats/:chat_id
+async fn unlink(
+    State(pool): State<PgPool>,
+    Path(chat_id): Path<i64>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    db::unlink_telegram_chat(chat_id, &pool).await?;
+    let chats = db::get_telegram_chats(&pool).await?;
+    Ok(HtmlTemplate(templates::render_telegram_chats(
+        chats,
+        None,
+        LINK_CODE_TTL_MINUTES,
+    )))
+}
+
+// post /integrations/telegram
+// Anything other than a text message is acknowledged and dropped, so that Telegram doesn't retry it
+async fn receive(
+    State(pool): State<PgPool>,
+    Extension(bot): Extension<Bot>,
+    Extension(notifier): Extension<Notifier>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, (StatusCode, String)> {
+    let secret = headers
+        .get(SECRET_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if !utils::tokens_match(&bot.secret, secret) {
+        return Err((StatusCode::NOT_FOUND, String::from("Not found")));
+    }
+    let Json(update) = Json::<Update>::from_bytes(&body)
+        .map_err(|err| (StatusCode::BAD_REQUEST, err.body_text()))?;
+    let Some((chat, text)) = update
+        .message
+        .and_then(|message| Some((message.chat, message.text?)))
+    else {
+        return Ok(StatusCode::OK.into_response());
+    };
+
+    let text = reply(&bot, &chat, text.trim(), &notifier, &pool).await?;
+    Ok(Json(SendMessage {
+        method: "sendMessage",
+        chat_id: chat.id,
+        text,
+    })
+    .into_response())
+}
+
+const HELP: &str = "Send a message to add it as a todo.
+/today lists what's due today and overdue
+/done 12 checks off todo 12";
+
+async fn reply(
+    bot: &Bot,
+    chat: &Chat,
+    text: &str,
+    notifier: &Notifier,
+    pool: &PgPool,
+) -> Result<String, (StatusCode, String)> {
+    let (command, argument) = parse_command(text);
+    if command == Some("start") {
+        if argument.is_empty() {
+            return Ok(String::from(
+                "Send /start with the code from the Telegram page of your todos to link this chat.",
+            ));
+        }
+        let linked =
+            db::link_telegram_chat(chat.id, &chat.name(), argument, LINK_CODE_TTL_MINUTES, pool)
+                .await?;
+        return Ok(if linked {
+            format!("This chat is linked.\n\n{HELP}")
+        } else {
+            String::from("That code is wrong or has expired, make another on the Telegram page.")
+        });
+    }
+    if !db::is_telegram_chat_linked(chat.id, pool).await? {
+        return Ok(String::from(
+            "This chat isn't linked yet, send /start with a code from the Telegram page of your todos.",
+        ));
+    }
+
+    match command {
+        None | Some("add") => add(bot, argument, notifier, pool).await,
+        Some("today") => today(pool).await,
+        Some("done") => done(argument, notifier, pool).await,
+        _ => Ok(String::from(HELP)),
+    }
+}
+
+// The command and what follows it: "/done 12", "/done_12" and "/done@SomeBot 12" are all
+// ("done", "12"). A message that isn't a command has no command, just the text.
+fn parse_command(text: &str) -> (Option<&str>, &str) {
+    let Some(rest) = text.strip_prefix('/') else {
+        return (None, text);
+    };
+    let (word, argument) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let word = word.split('@').next().unwrap_or(word);
+    match word.split_once('_') {
+        Some((command, suffix)) if argument.is_empty() => (Some(command), suffix),
+        _ => (Some(word), argument.trim()),
+    }
+}
+
+async fn add(
+    bot: &Bot,
+    text: &str,
+    notifier: &Notifier,
+    pool: &PgPool,
+) -> Result<String, (StatusCode, String)> {
+    let description: String = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(MAX_DESCRIPTION_CHARS)
+        .collect();
+    if description.is_empty() {
+        return Ok(String::from("Send /add with the todo, or just the todo."));
+    }
+    let list_id = match bot.list_id {
+        Some(list_id) => list_id,
+        None => match db::get_first_list_id(pool).await? {
+            Some(list_id) => list_id,
+            None => return Ok(String::from("There's no list to add the todo to.")),
+        },
+    };
+    db::create_todo(list_id, &description, None, Priority::default(), None, pool).await?;
+    notifier.send(Notification::list_changed(list_id, None));
+    Ok(format!("Added: {description}"))
+}
+
+// What's overdue and due today across every list, like the agenda page
+async fn today(pool: &PgPool) -> Result<String, (StatusCode, String)> {
+    let today = OffsetDateTime::now_utc().date();
+    let agenda = db::get_agenda(today, pool).await?;
+    if agenda.is_empty() {
+        return Ok(String::from("Nothing is due today."));
+    }
+    let lines: Vec<String> = agenda
+        .iter()
+        .map(|todo| {
+            let overdue = if todo.due_at < today { ", overdue" } else { "" };
+            format!(
+                "{} ({}{overdue}) /done_{}",
+                todo.description, todo.list_name, todo.id
+            )
+        })
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+async fn done(
+    argument: &str,
+    notifier: &Notifier,
+    pool: &PgPool,
+) -> Result<String, (StatusCode, String)> {
+    let Ok(todo_id) = argument.parse::<i32>() else {
+        return Ok(String::from(
+            "Send /done with the number of the todo, /today lists them.",
+        ));
+    };
+    let list_id = match db::get_list_id(todo_id, pool).await {
+        Ok(list_id) => list_id,
+        Err((StatusCode::NOT_FOUND, _)) => return Ok(format!("There's no todo {todo_id}.")),
+        Err(err) => return Err(err),
+    };
+    match db::set_done(list_id, todo_id, true, pool).await {
+        Ok(()) => {}
+        // blocked by other todos
+        Err((StatusCode::UNPROCESSABLE_ENTITY, message)) => return Ok(message),
+        Err(err) => return Err(err),
+    }
+    notifier.send(Notification::list_changed(list_id, None));
+    let todo = db::get_todo(list_id, todo_id, pool).await?;
+    Ok(format!("Done: {}", todo.description))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_plain_text_as_it_is() {
+        assert_eq!(parse_command("Buy milk"), (None, "Buy milk"));
+    }
+
+    #[test]
+    fn splits_a_command_from_its_argument() {
+        assert_eq!(parse_command("/add Buy milk"), (Some("add"), "Buy milk"));
+        assert_eq!(parse_command("/done   12 "), (Some("done"), "12"));
+    }
+
+    #[test]
+    fn drops_the_bot_name_in_groups() {
+        assert_eq!(
+            parse_command("/add@TodoBot Buy milk"),
+            (Some("add"), "Buy milk")
+        );
+        assert_eq!(parse_command("/today@TodoBot"), (Some("today"), ""));
+    }
+
+    #[test]
+    fn reads_the_argument_after_an_underscore() {
+        assert_eq!(parse_command("/done_12"), (Some("done"), "12"));
+        assert_eq!(parse_command("/done_12@TodoBot"), (Some("done"), "12"));
+    }
+
+    #[test]
+    fn allows_an_empty_argument() {
+        assert_eq!(parse_command("/add"), (Some("add"), ""));
+        assert_eq!(parse_command("/"), (Some(""), ""));
+    }
+
+    #[test]
+    fn passes_unknown_commands_on() {
+        assert_eq!(
+            parse_command("/frobnicate now"),
+            (Some("frobnicate"), "now")
+        );
+    }
+}
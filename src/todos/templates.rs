@@ -1,9 +1,16 @@
 use std::fmt::{self};
 
-use super::{db, Todo};
+use super::{
+    db, format_minutes,
+    import::{ImportOutcome, ImportRow, ImportedList, ListSource},
+    AgendaTodo, ArchivedTodo, BoardTodo, Color, Page, Priority, Recurrence, TelegramChat, Todo,
+    TodoAttachment, TodoCounts, TodoFilter, TodoRevision, TodoSort, TodoStatus,
+};
+use crate::utils;
 use askama::Template;
 use axum::http::StatusCode;
 use sqlx::PgPool;
+use time::{Date, OffsetDateTime};
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
 enum TodoUiState {
@@ -22,88 +29,489 @@ impl fmt::Display for TodoUiState {
         Ok(())
     }
 }
+#[derive(Template)]
+#[template(path = "todos_ul.html")]
+pub struct TodosUlTemplate {
+    pub todos: TodosInnerTemplate,
+}
+
+#[derive(Template)]
+#[template(path = "todos_inner.html")]
+pub struct TodosInnerTemplate {
+    pub list_id: i32,
+    pub todos: Vec<TodoLiTemplate>,
+    pub editable: bool,
+    // what the list is currently narrowed down to
+    pub filter: TodoFilter,
+    // set when there may be more todos to load after these
+    pub next_page: Option<Page>,
+    // set right after a change that can be undone
+    pub undo: Option<UndoToast>,
+    // set when some of the todos a change was asked for were left alone
+    pub notice: Option<String>,
+    // for the footer, across all pages
+    pub counts: TodoCounts,
+}
+
+impl TodosInnerTemplate {
+    // A new key for each render of the create form, see routes::create
+    fn idempotency_key(&self) -> String {
+        utils::random_token(IDEMPOTENCY_KEY_LENGTH)
+    }
+
+    // e.g. "2h 15m" of pending work, when any of the pending todos are estimated
+    fn active_estimate(&self) -> Option<String> {
+        let minutes = self.counts.active_estimate_minutes;
+        (minutes > 0).then(|| format_minutes(minutes))
+    }
+}
+
+impl TodoLiTemplate {
+    fn estimate(&self) -> Option<String> {
+        self.estimate_minutes
+            .map(|minutes| format_minutes(minutes.into()))
+    }
+}
+
+pub struct UndoToast {
+    pub token: String,
+    pub message: String,
+}
+
+// A todo that has swapped places with its neighbour: the neighbour is removed from the list
+// and the pair is put back in their new order where the moved todo was
+#[derive(Template)]
+#[template(path = "todos_swapped.html")]
+pub struct TodosSwappedTemplate {
+    pub neighbour_id: i32,
+    pub todos: Vec<TodoLiTemplate>,
+}
+
+// Shown in the edit form when the todo changed after the form was opened
+#[derive(Template)]
+#[template(path = "todo_conflict.html")]
+pub struct TodoConflictTemplate {
+    pub list_id: i32,
+    pub id: i32,
+}
+
+const IDEMPOTENCY_KEY_LENGTH: usize = 32;
+
+// Why the todo that was typed in couldn't be saved, replacing the error element with this id
+#[derive(Template)]
+#[template(path = "todo_create_error.html")]
+pub struct TodoCreateErrorTemplate {
+    pub id: String,
+    pub message: String,
+}
+
+// The "N items left" badge in the page header, swapped in out of band whenever the todos change
+#[derive(Template)]
+#[template(path = "todos_counter.html")]
+pub struct TodosCounterTemplate {
+    pending: i64,
+    // days in a row with at least one todo completed, from any list
+    streak: i64,
+}
+
+// Swatches for picking a todo's color, shown in place of the button that asked for them
+#[derive(Template)]
+#[template(path = "todo_color_picker.html")]
+pub struct TodoColorPickerTemplate {
+    pub list_id: i32,
+    pub id: i32,
+}
+
+// The todos that could block this one, shown in place of the button that asked for them
+#[derive(Template)]
+#[template(path = "todo_blocker_picker.html")]
+pub struct TodoBlockerPickerTemplate {
+    pub list_id: i32,
+    pub id: i32,
+    // (id, description)
+    pub candidates: Vec<(i32, String)>,
+}
+
+// What became of each line of an uploaded Todoist export, swapped in out of band next to the list
+#[derive(Template)]
+#[template(path = "todos_import_summary.html")]
+pub struct TodosImportSummaryTemplate {
+    pub imported: usize,
+    pub rows: Vec<ImportRow>,
+}
+
+// What importing an export of whole lists would create, shown before anything is saved
+#[derive(Template)]
+#[template(path = "import_preview.html")]
+pub struct ImportPreviewTemplate {
+    pub source: ListSource,
+    pub lists: Vec<ImportedList>,
+    pub rows: Vec<ImportRow>,
+}
+
+// A WebDAV multistatus answering a CalDAV client's PROPFIND or REPORT, see caldav
+#[derive(Template)]
+#[template(path = "caldav_multistatus.xml")]
+pub struct CalDavMultistatusTemplate {
+    pub responses: Vec<DavResponse>,
+}
+
+// One resource in a multistatus, with the properties for its kind
+pub struct DavResponse {
+    pub href: String,
+    pub kind: DavKind,
+    pub displayname: String,
+    pub ctag: String,
+    pub etag: String,
+    // whether the token can change the calendar
+    pub writable: bool,
+    // only REPORTs include the VTODO itself
+    pub calendar_data: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DavKind {
+    // the signed in principal, which is also the home of the calendars
+    Home,
+    // a list
+    Calendar,
+    Todo,
+    // asked for by a REPORT but not there
+    Missing,
+}
+
+// Earlier versions of a todo, shown in #modal with a way to bring each one back
+#[derive(Template)]
+#[template(path = "todo_history.html")]
+pub struct TodoHistoryTemplate {
+    pub list_id: i32,
+    pub id: i32,
+    pub revisions: Vec<TodoRevision>,
+}
+
+// Links to download the files an emailed todo came with
+#[derive(Template)]
+#[template(path = "todo_attachments.html")]
+pub struct TodoAttachmentsTemplate {
+    pub list_id: i32,
+    pub id: i32,
+    pub attachments: Vec<TodoAttachment>,
+}
+
 #[derive(Template)]
 #[template(path = "base.html")]
 struct BaseTemplate {}
 
+// Everything that needs doing today, across all lists, see agenda
 #[derive(Template)]
-#[template(path = "todos_index.html")]
-pub struct TodosIndexTemplate<'a> {
+#[template(path = "todos_agenda.html")]
+pub struct TodosAgendaTemplate<'a> {
     _parent: &'a BaseTemplate,
+    csrf_token: String,
+    agenda: TodosAgendaInnerTemplate,
+}
+
+pub fn render_agenda<'a>(
+    csrf_token: String,
+    agenda: TodosAgendaInnerTemplate,
+) -> TodosAgendaTemplate<'a> {
+    TodosAgendaTemplate {
+        _parent: &BaseTemplate {},
+        csrf_token,
+        agenda,
+    }
+}
+
+#[derive(Template)]
+#[template(path = "todos_agenda_inner.html")]
+pub struct TodosAgendaInnerTemplate {
+    today: Date,
+    overdue: Vec<AgendaTodo>,
+    due_today: Vec<AgendaTodo>,
 }
 
-// impl<'a> Deref for TodosIndexTemplate<'a> {
-//     type Target = BaseTemplate;
+pub async fn render_agenda_inner(
+    today: Date,
+    pool: &PgPool,
+) -> Result<TodosAgendaInnerTemplate, (StatusCode, String)> {
+    let (overdue, due_today) = db::get_agenda(today, pool)
+        .await?
+        .into_iter()
+        .partition(|todo| todo.due_at < today);
+    Ok(TodosAgendaInnerTemplate {
+        today,
+        overdue,
+        due_today,
+    })
+}
 
-//     fn deref(&self) -> &Self::Target {
-//         self._parent
-//     }
-// }
+// The chats linked to the Telegram bot, and how to link another, see telegram
+#[derive(Template)]
+#[template(path = "telegram_show.html")]
+pub struct TelegramShowTemplate<'a> {
+    _parent: &'a BaseTemplate,
+    csrf_token: String,
+    chats: TelegramChatsTemplate,
+}
 
-pub fn render_index<'a>() -> TodosIndexTemplate<'a> {
-    TodosIndexTemplate {
+pub fn render_telegram<'a>(
+    csrf_token: String,
+    chats: TelegramChatsTemplate,
+) -> TelegramShowTemplate<'a> {
+    TelegramShowTemplate {
         _parent: &BaseTemplate {},
+        csrf_token,
+        chats,
     }
 }
 
+// Swapped in after making a link code or unlinking a chat
 #[derive(Template)]
-#[template(path = "todos_ul.html")]
-pub struct TodosUlTemplate {
-    pub todos: TodosInnerTemplate,
+#[template(path = "telegram_chats.html")]
+pub struct TelegramChatsTemplate {
+    // (chat id, name, when it was linked e.g. "3 days ago")
+    chats: Vec<(i64, String, String)>,
+    // a link code that was just made, shown this once
+    code: Option<String>,
+    code_ttl_minutes: i32,
 }
 
+pub fn render_telegram_chats(
+    chats: Vec<TelegramChat>,
+    code: Option<String>,
+    code_ttl_minutes: i32,
+) -> TelegramChatsTemplate {
+    let now = OffsetDateTime::now_utc();
+    TelegramChatsTemplate {
+        chats: chats
+            .into_iter()
+            .map(|chat| {
+                (
+                    chat.chat_id,
+                    chat.name,
+                    utils::time_ago(chat.linked_at, now),
+                )
+            })
+            .collect(),
+        code,
+        code_ttl_minutes,
+    }
+}
+
+// Every list side by side, see board
 #[derive(Template)]
-#[template(path = "todos_inner.html")]
-pub struct TodosInnerTemplate {
-    pub todos: Vec<TodoLiTemplate>,
-    pub editable: bool,
+#[template(path = "todos_board.html")]
+pub struct TodosBoardTemplate<'a> {
+    _parent: &'a BaseTemplate,
+    csrf_token: String,
+    columns: TodosBoardColumnsTemplate,
 }
 
-impl TodosInnerTemplate {
-    fn done_count(&self) -> usize {
-        self.todos
-            .iter()
-            .filter(|t| t.done)
-            .collect::<Vec<_>>()
-            .len()
+pub fn render_board<'a>(
+    csrf_token: String,
+    columns: TodosBoardColumnsTemplate,
+) -> TodosBoardTemplate<'a> {
+    TodosBoardTemplate {
+        _parent: &BaseTemplate {},
+        csrf_token,
+        columns,
     }
+}
+
+// The board's columns, swapped in again after a card is dropped
+#[derive(Template)]
+#[template(path = "todos_board_columns.html")]
+pub struct TodosBoardColumnsTemplate {
+    // (list id, list name, its todos)
+    columns: Vec<(i32, String, Vec<BoardTodo>)>,
+}
 
-    fn pending_count(&self) -> usize {
-        self.todos
-            .iter()
-            .filter(|t| !t.done)
-            .collect::<Vec<_>>()
-            .len()
+pub async fn render_board_columns(
+    pool: &PgPool,
+) -> Result<TodosBoardColumnsTemplate, (StatusCode, String)> {
+    let (lists, todos) = db::get_board(pool).await?;
+    let mut columns: Vec<(i32, String, Vec<BoardTodo>)> = lists
+        .into_iter()
+        .map(|(id, name)| (id, name, Vec::new()))
+        .collect();
+    for todo in todos {
+        if let Some((_, _, todos)) = columns.iter_mut().find(|(id, _, _)| *id == todo.list_id) {
+            todos.push(todo);
+        }
     }
+    Ok(TodosBoardColumnsTemplate { columns })
+}
+
+// A later page of todos, appended to the list as the user scrolls
+#[derive(Template)]
+#[template(path = "todos_page.html")]
+pub struct TodosPageTemplate {
+    pub list_id: i32,
+    pub todos: Vec<TodoLiTemplate>,
+    pub next_page: Option<Page>,
+}
+
+#[derive(Template)]
+#[template(path = "todos_archive.html")]
+pub struct TodosArchiveTemplate {
+    list_id: i32,
+    todos: Vec<ArchivedTodo>,
+}
+
+pub async fn render_archive(
+    list_id: i32,
+    pool: &PgPool,
+) -> Result<TodosArchiveTemplate, (StatusCode, String)> {
+    let todos = db::get_archived_todos(list_id, pool).await?;
+    Ok(TodosArchiveTemplate { list_id, todos })
 }
 
 #[derive(Template)]
 #[template(path = "todo_li.html")]
 pub struct TodoLiTemplate {
     id: i32,
+    list_id: i32,
     done: bool,
+    // e.g. "2 hours ago", for done todos
+    completed_ago: Option<String>,
+    // e.g. "3 days ago"
+    created_ago: String,
+    // only when it has been changed since it was added
+    updated_ago: Option<String>,
     description: String,
+    notes: String,
+    // notes rendered from markdown and sanitized
+    notes_html: String,
+    due_at: Option<Date>,
+    overdue: bool,
+    priority: Priority,
+    recurrence: Option<Recurrence>,
+    remind_days_before: Option<i32>,
+    estimate_minutes: Option<i32>,
+    pinned: bool,
+    color: Option<Color>,
+    tags: Vec<String>,
+    // (id, description) of the open todos this one is waiting on
+    blockers: Vec<(i32, String)>,
+    // (name, value) of the custom fields this todo has a value for
+    custom_values: Vec<(String, String)>,
     ui_state: TodoUiState,
+    // the move up/down buttons only make sense when the list is in its own order
+    movable: bool,
+    // sent back by the edit form, see update
+    version: i32,
+    // who emailed it in and the subject they used, see inbound_email
+    email_from: Option<String>,
+    email_subject: Option<String>,
 }
 
 impl From<Todo> for TodoLiTemplate {
     fn from(todo: Todo) -> Self {
+        let now = OffsetDateTime::now_utc();
+        let today = now.date();
         TodoLiTemplate {
             done: todo.done,
+            completed_ago: todo
+                .completed_at
+                .filter(|_| todo.done)
+                .map(|completed_at| utils::time_ago(completed_at, now)),
+            created_ago: utils::time_ago(todo.created_at, now),
+            updated_ago: (todo.updated_at > todo.created_at)
+                .then(|| utils::time_ago(todo.updated_at, now)),
             id: todo.id,
+            list_id: todo.list_id,
             description: todo.description,
+            notes_html: utils::render_markdown(&todo.notes),
+            notes: todo.notes,
+            overdue: !todo.done && todo.due_at.is_some_and(|due_at| due_at < today),
+            due_at: todo.due_at,
+            priority: todo.priority,
+            recurrence: todo.recurrence,
+            remind_days_before: todo.remind_days_before,
+            estimate_minutes: todo.estimate_minutes,
+            version: todo.version,
+            email_from: todo.email_from,
+            email_subject: todo.email_subject,
+            pinned: todo.pinned,
+            color: todo.color,
+            tags: todo.tags,
+            blockers: todo
+                .blocker_ids
+                .into_iter()
+                .zip(todo.blocker_descriptions)
+                .collect(),
+            custom_values: todo
+                .custom_field_names
+                .into_iter()
+                .zip(todo.custom_field_values)
+                .collect(),
             ui_state: TodoUiState::Normal,
+            movable: true,
         }
     }
 }
 
-pub async fn render_all_todos(pool: &PgPool) -> Result<TodosInnerTemplate, (StatusCode, String)> {
-    let todos = db::get_todos(pool).await?;
-    Ok(render_todos(todos, None))
+pub async fn render_counter(
+    list_id: i32,
+    pool: &PgPool,
+) -> Result<TodosCounterTemplate, (StatusCode, String)> {
+    let counts = db::count_todos(list_id, &TodoFilter::default(), pool).await?;
+    let streak = db::get_streak(OffsetDateTime::now_utc().date(), pool).await?;
+    Ok(TodosCounterTemplate {
+        pending: counts.active,
+        streak,
+    })
+}
+
+pub async fn render_all_todos(
+    list_id: i32,
+    filter: &TodoFilter,
+    pool: &PgPool,
+) -> Result<TodosInnerTemplate, (StatusCode, String)> {
+    let page = Page::first();
+    let todos = db::get_todos(list_id, filter, Some(page), pool).await?;
+    let next_page = page.next(todos.len());
+    let mut template = render_todos(list_id, filter.clone(), todos, None);
+    template.next_page = next_page;
+    template.counts = db::count_todos(list_id, filter, pool).await?;
+    Ok(template)
 }
 
-pub fn render_todos(todos: Vec<Todo>, editable_id: Option<i32>) -> TodosInnerTemplate {
-    let todos: Vec<TodoLiTemplate> = todos.into_iter().map(|t| t.into()).collect::<Vec<_>>();
+pub async fn render_page(
+    list_id: i32,
+    filter: &TodoFilter,
+    page: Page,
+    pool: &PgPool,
+) -> Result<TodosPageTemplate, (StatusCode, String)> {
+    let todos = db::get_todos(list_id, filter, Some(page), pool).await?;
+    let movable = filter.sort == TodoSort::Position;
+    Ok(TodosPageTemplate {
+        list_id,
+        next_page: page.next(todos.len()),
+        todos: todos
+            .into_iter()
+            .map(|t| TodoLiTemplate {
+                movable,
+                ..t.into()
+            })
+            .collect(),
+    })
+}
+
+pub fn render_todos(
+    list_id: i32,
+    filter: TodoFilter,
+    todos: Vec<Todo>,
+    editable_id: Option<i32>,
+) -> TodosInnerTemplate {
+    let movable = filter.sort == TodoSort::Position;
+    let todos: Vec<TodoLiTemplate> = todos
+        .into_iter()
+        .map(|t| TodoLiTemplate {
+            movable,
+            ..t.into()
+        })
+        .collect::<Vec<_>>();
     if let Some(editable_id) = editable_id {
         let todos = todos
             .into_iter()
@@ -117,12 +525,44 @@ pub fn render_todos(todos: Vec<Todo>, editable_id: Option<i32>) -> TodosInnerTem
             })
             .collect::<Vec<_>>();
         return TodosInnerTemplate {
+            list_id,
             todos,
             editable: true,
+            filter,
+            next_page: None,
+            undo: None,
+            notice: None,
+            counts: TodoCounts::default(),
         };
     }
     TodosInnerTemplate {
+        list_id,
         todos,
         editable: false,
+        filter,
+        next_page: None,
+        undo: None,
+        notice: None,
+        counts: TodoCounts::default(),
     }
 }
+
+pub async fn render_swapped(
+    list_id: i32,
+    todo_id: i32,
+    neighbour_id: i32,
+    up: bool,
+    pool: &PgPool,
+) -> Result<TodosSwappedTemplate, (StatusCode, String)> {
+    let todo = db::get_todo(list_id, todo_id, pool).await?.into();
+    let neighbour = db::get_todo(list_id, neighbour_id, pool).await?.into();
+    let todos = if up {
+        vec![todo, neighbour]
+    } else {
+        vec![neighbour, todo]
+    };
+    Ok(TodosSwappedTemplate {
+        neighbour_id,
+        todos,
+    })
+}
@@ -0,0 +1,328 @@
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+
+use sqlx::{PgPool, Pool, Postgres};
+use time::{format_description::FormatItem, macros::format_description, Date};
+
+use super::{
+    db,
+    import::{ImportOutcome, ImportRow, ImportedTodo},
+    routes::validate_description,
+    Priority, TodoTxtTodo,
+};
+
+// todo.txt routes, nested under /export
+// See https://github.com/todotxt/todo.txt for the format
+pub fn routes(pool: &Pool<Postgres>) -> Router {
+    Router::new()
+        .route("/todo.txt", get(export))
+        .with_state(pool.clone())
+}
+
+// get /export/todo.txt
+// Every list's current todos, archived ones are left out
+async fn export(State(pool): State<PgPool>) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let todos = db::get_todotxt_todos(&pool).await?;
+    let body: String = todos.iter().map(|todo| format_line(todo) + "\n").collect();
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/plain; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"todo.txt\"",
+            ),
+        ],
+        body,
+    ))
+}
+
+const DATE_FORMAT: &[FormatItem<'static>] = format_description!("[year]-[month]-[day]");
+
+// todo.txt priorities are letters, A first. Normal todos have none, which sorts after every letter
+// in todo.txt apps, so low todos get a letter further down the alphabet than high ones.
+fn priority_letter(priority: Priority) -> Option<char> {
+    match priority {
+        Priority::Urgent => Some('A'),
+        Priority::High => Some('B'),
+        Priority::Normal => None,
+        Priority::Low => Some('D'),
+    }
+}
+
+fn parse_priority_letter(letter: char) -> Priority {
+    match letter {
+        'A' => Priority::Urgent,
+        'B' => Priority::High,
+        'C' => Priority::Normal,
+        _ => Priority::Low,
+    }
+}
+
+// Projects and contexts are single words
+fn word(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join("-")
+}
+
+// e.g. "x 2026-10-16 2026-10-01 Buy milk +Groceries @shop due:2026-10-17 pri:B".
+// Done todos keep their priority as a pri: tag, as the format only allows it on pending ones.
+fn format_line(todo: &TodoTxtTodo) -> String {
+    let date = |date: Date| date.format(DATE_FORMAT).unwrap_or_default();
+    let mut parts = Vec::new();
+    if todo.done {
+        parts.push(String::from("x"));
+        if let Some(completed_at) = todo.completed_at {
+            parts.push(date(completed_at.date()));
+        }
+    } else if let Some(letter) = priority_letter(todo.priority) {
+        parts.push(format!("({letter})"));
+    }
+    parts.push(date(todo.created_at.date()));
+    parts.push(
+        todo.description
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+    );
+    parts.push(format!("+{}", word(&todo.list_name)));
+    parts.extend(todo.tags.iter().map(|tag| format!("@{}", word(tag))));
+    if let Some(due_at) = todo.due_at {
+        parts.push(format!("due:{}", date(due_at)));
+    }
+    if todo.done {
+        if let Some(letter) = priority_letter(todo.priority) {
+            parts.push(format!("pri:{letter}"));
+        }
+    }
+    parts.join(" ")
+}
+
+/// Read a todo.txt file. Returns the tasks to insert, in file order, along with one summary row
+/// per non-empty line. Contexts (@home) become tags. Projects (+work) are dropped, the tasks go in
+/// the list being imported into.
+pub(super) fn parse(text: &str) -> (Vec<ImportedTodo>, Vec<ImportRow>) {
+    let mut todos = Vec::new();
+    let mut rows = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let outcome = match parse_line(line) {
+            Ok(todo) => {
+                todos.push(todo);
+                ImportOutcome::Imported
+            }
+            Err(err) => ImportOutcome::Failed(err),
+        };
+        rows.push(ImportRow {
+            line: index as u64 + 1,
+            content: line.to_string(),
+            outcome,
+        });
+    }
+    (todos, rows)
+}
+
+fn parse_line(line: &str) -> Result<ImportedTodo, String> {
+    let mut words = line.split_whitespace().peekable();
+    let parse_date = |word: &str| Date::parse(word, DATE_FORMAT).ok();
+
+    let done = words.next_if_eq(&"x").is_some();
+    let completed_on = words
+        .next_if(|word| done && parse_date(word).is_some())
+        .and_then(parse_date);
+    let mut priority = words
+        .next_if(|word| {
+            let bytes = word.as_bytes();
+            bytes.len() == 3
+                && bytes[0] == b'('
+                && bytes[1].is_ascii_uppercase()
+                && bytes[2] == b')'
+        })
+        .map(|word| parse_priority_letter(char::from(word.as_bytes()[1])))
+        .unwrap_or_default();
+    // the creation date isn't kept, the todo counts as added when it is imported
+    words.next_if(|word| parse_date(word).is_some());
+
+    let mut description = Vec::new();
+    let mut tags: Vec<String> = Vec::new();
+    let mut due_at = None;
+    for word in words {
+        if let Some(context) = word.strip_prefix('@').filter(|context| !context.is_empty()) {
+            if !tags.iter().any(|tag| tag == context) {
+                tags.push(context.to_string());
+            }
+        } else if word.len() > 1 && word.starts_with('+') {
+            continue;
+        } else if let Some(date) = word.strip_prefix("due:") {
+            due_at = Some(
+                parse_date(date)
+                    .ok_or_else(|| format!("Couldn't understand the due date '{date}'"))?,
+            );
+        } else if let Some(letter) = word.strip_prefix("pri:").and_then(|pri| {
+            let mut chars = pri.chars();
+            chars
+                .next()
+                .filter(|letter| letter.is_ascii_uppercase() && chars.next().is_none())
+        }) {
+            priority = parse_priority_letter(letter);
+        } else {
+            description.push(word);
+        }
+    }
+    if description.is_empty() {
+        return Err(String::from("The task has no description"));
+    }
+    let description = validate_description(&description.join(" "))?.to_string();
+    Ok(ImportedTodo {
+        description,
+        notes: String::new(),
+        priority,
+        due_at,
+        recurrence: None,
+        done,
+        completed_on,
+        tags,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::{date, datetime};
+
+    use super::*;
+    use crate::todos::routes::MAX_DESCRIPTION_CHARS;
+
+    fn todo() -> TodoTxtTodo {
+        TodoTxtTodo {
+            list_name: String::from("Groceries"),
+            description: String::from("Buy milk"),
+            done: false,
+            completed_at: None,
+            created_at: datetime!(2026-10-01 09:30 UTC),
+            priority: Priority::Normal,
+            due_at: None,
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn formats_a_pending_todo() {
+        let todo = TodoTxtTodo {
+            priority: Priority::Urgent,
+            due_at: Some(date!(2026 - 10 - 17)),
+            tags: vec![String::from("shop"), String::from("after work")],
+            ..todo()
+        };
+        assert_eq!(
+            format_line(&todo),
+            "(A) 2026-10-01 Buy milk +Groceries @shop @after-work due:2026-10-17"
+        );
+    }
+
+    #[test]
+    fn formats_a_done_todo_with_its_priority_as_a_tag() {
+        let todo = TodoTxtTodo {
+            done: true,
+            completed_at: Some(datetime!(2026-10-16 18:00 UTC)),
+            priority: Priority::High,
+            ..todo()
+        };
+        assert_eq!(
+            format_line(&todo),
+            "x 2026-10-16 2026-10-01 Buy milk +Groceries pri:B"
+        );
+    }
+
+    #[test]
+    fn parses_what_it_formats() {
+        let todo = TodoTxtTodo {
+            done: true,
+            completed_at: Some(datetime!(2026-10-16 18:00 UTC)),
+            priority: Priority::Low,
+            due_at: Some(date!(2026 - 10 - 17)),
+            tags: vec![String::from("shop")],
+            ..todo()
+        };
+        let parsed = parse_line(&format_line(&todo)).unwrap();
+        assert_eq!(parsed.description, "Buy milk");
+        assert!(parsed.done);
+        assert_eq!(parsed.completed_on, Some(date!(2026 - 10 - 16)));
+        assert_eq!(parsed.priority, Priority::Low);
+        assert_eq!(parsed.due_at, Some(date!(2026 - 10 - 17)));
+        assert_eq!(parsed.tags, vec![String::from("shop")]);
+    }
+
+    #[test]
+    fn parses_a_priority() {
+        let parsed = parse_line("(A) 2026-10-01 Call mum").unwrap();
+        assert_eq!(parsed.priority, Priority::Urgent);
+        assert_eq!(parsed.description, "Call mum");
+        assert!(!parsed.done);
+        assert_eq!(parsed.completed_on, None);
+    }
+
+    #[test]
+    fn parses_a_completion_without_dates() {
+        let parsed = parse_line("x Call mum").unwrap();
+        assert!(parsed.done);
+        assert_eq!(parsed.completed_on, None);
+        assert_eq!(parsed.description, "Call mum");
+    }
+
+    #[test]
+    fn drops_projects_and_keeps_contexts_as_tags() {
+        let parsed =
+            parse_line("Paint the fence +House @home @outside @home due:2026-11-01").unwrap();
+        assert_eq!(parsed.description, "Paint the fence");
+        assert_eq!(
+            parsed.tags,
+            vec![String::from("home"), String::from("outside")]
+        );
+        assert_eq!(parsed.due_at, Some(date!(2026 - 11 - 01)));
+    }
+
+    #[test]
+    fn keeps_a_lone_plus_or_at_in_the_description() {
+        let parsed = parse_line("Add 2 + 2 @ the desk").unwrap();
+        assert_eq!(parsed.description, "Add 2 + 2 @ the desk");
+        assert!(parsed.tags.is_empty());
+    }
+
+    #[test]
+    fn rejects_a_bad_due_date() {
+        assert_eq!(
+            parse_line("Pay rent due:tomorrow").unwrap_err(),
+            "Couldn't understand the due date 'tomorrow'"
+        );
+    }
+
+    #[test]
+    fn rejects_a_task_with_only_tags() {
+        assert_eq!(
+            parse_line("x 2026-10-16 +House @home").unwrap_err(),
+            "The task has no description"
+        );
+    }
+
+    #[test]
+    fn rejects_an_overlong_description() {
+        let line = "a".repeat(MAX_DESCRIPTION_CHARS + 1);
+        assert!(parse_line(&line).is_err());
+        assert!(parse_line(&line[1..]).is_ok());
+    }
+
+    #[test]
+    fn reports_every_non_empty_line() {
+        let (todos, rows) = parse("Buy milk\n\n   \n+House\nCall mum\n");
+        assert_eq!(todos.len(), 2);
+        let lines: Vec<u64> = rows.iter().map(|row| row.line).collect();
+        assert_eq!(lines, vec![1, 4, 5]);
+        assert!(matches!(rows[1].outcome, ImportOutcome::Failed(_)));
+    }
+}
@@ -0,0 +1,22 @@
+use sqlx::PgPool;
+use tokio::time;
+
+use crate::config::TrashConfig;
+
+use super::db;
+
+/// Periodically remove todos that were deleted more than TRASH_RETENTION_DAYS ago.
+/// Until then they can still be found in the database, e.g. to restore them by hand.
+pub fn spawn(config: TrashConfig, pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = time::interval(config.interval);
+        loop {
+            interval.tick().await;
+            match db::purge_deleted(config.retention_days, &pool).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!(count, "removed deleted todos for good"),
+                Err((_, err)) => tracing::error!(%err, "could not remove deleted todos"),
+            }
+        }
+    });
+}
@@ -0,0 +1,74 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+
+use crate::utils;
+
+use super::{AccessToken, TokenScope};
+
+pub async fn get_tokens(pool: &PgPool) -> Result<Vec<AccessToken>, (StatusCode, String)> {
+    sqlx::query_as!(
+        AccessToken,
+        r#"select id, name, scope as "scope: TokenScope", created_at, last_used_at
+           from access_tokens ORDER BY created_at DESC, id DESC"#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(utils::internal_error)
+}
+
+pub async fn get_token(id: i32, pool: &PgPool) -> Result<AccessToken, (StatusCode, String)> {
+    sqlx::query_as!(
+        AccessToken,
+        r#"select id, name, scope as "scope: TokenScope", created_at, last_used_at
+           from access_tokens where id = $1"#,
+        id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(utils::internal_error)?
+    .ok_or((StatusCode::NOT_FOUND, String::from("Token not found")))
+}
+
+// Only the sha256 of the token is stored, it can't be shown again
+pub async fn create_token(
+    name: &str,
+    scope: TokenScope,
+    token: &str,
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        "insert into access_tokens (name, scope, token_hash)
+         values ($1, $2, sha256(convert_to($3, 'UTF8')))",
+        name,
+        scope as _,
+        token,
+    )
+    .execute(pool)
+    .await
+    .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+pub async fn delete_token(id: i32, pool: &PgPool) -> Result<(), (StatusCode, String)> {
+    sqlx::query!("delete from access_tokens where id = $1", id)
+        .execute(pool)
+        .await
+        .map_err(utils::internal_error)?;
+    Ok(())
+}
+
+// The scope of the token, if it exists, noting that it was just used
+pub async fn use_token(
+    token: &str,
+    pool: &PgPool,
+) -> Result<Option<TokenScope>, (StatusCode, String)> {
+    sqlx::query_scalar!(
+        r#"update access_tokens set last_used_at = now()
+           where token_hash = sha256(convert_to($1, 'UTF8'))
+           returning scope as "scope: TokenScope""#,
+        token,
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(utils::internal_error)
+}
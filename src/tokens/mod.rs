@@ -0,0 +1,127 @@
+use std::fmt;
+
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts, Request},
+    http::{header, request::Parts, Method, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+use time::OffsetDateTime;
+
+use crate::utils::{self, WantsJson};
+
+mod db;
+pub mod routes;
+mod templates;
+
+// Tokens are shown once as this prefix followed by random characters
+const TOKEN_PREFIX: &str = "todo_";
+const TOKEN_LENGTH: usize = 40;
+
+/// What a personal access token may do with the API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "token_scope", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum TokenScope {
+    Read,
+    ReadWrite,
+}
+
+impl fmt::Display for TokenScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TokenScope::Read => "read-only",
+            TokenScope::ReadWrite => "read-write",
+        })
+    }
+}
+
+// A token as listed on the tokens page, the token itself is never stored
+#[derive(Debug, Clone)]
+struct AccessToken {
+    id: i32,
+    name: String,
+    scope: TokenScope,
+    created_at: OffsetDateTime,
+    last_used_at: Option<OffsetDateTime>,
+}
+
+/// The scope of the personal access token sent in `Authorization: Bearer`, if any.
+///
+/// Requests without the header get `None`, a token that is unknown or revoked is
+/// rejected with a 401. Using a token records when it was last used.
+#[derive(Clone, Copy, Debug)]
+pub struct BearerToken(pub Option<TokenScope>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for BearerToken
+where
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // already checked by csrf::protect
+        if let Some(token) = parts.extensions.get::<BearerToken>() {
+            return Ok(*token);
+        }
+        let Some(value) = parts.headers.get(header::AUTHORIZATION) else {
+            return Ok(BearerToken(None));
+        };
+        let token = value
+            .to_str()
+            .ok()
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+            .ok_or((
+                StatusCode::UNAUTHORIZED,
+                String::from("Send the token as Authorization: Bearer <token>"),
+            ))?;
+        let pool = PgPool::from_ref(state);
+        scope_of(token, &pool)
+            .await?
+            .map(|scope| BearerToken(Some(scope)))
+            .ok_or((
+                StatusCode::UNAUTHORIZED,
+                String::from("Unknown or revoked token"),
+            ))
+    }
+}
+
+/// The scope of a personal access token, recording that it was used. `None` when there is
+/// no such token, or it was revoked.
+pub async fn scope_of(
+    token: &str,
+    pool: &PgPool,
+) -> Result<Option<TokenScope>, (StatusCode, String)> {
+    db::use_token(token, pool).await
+}
+
+/// Middleware for the API routes: requests for JSON must carry a bearer token, and read-only
+/// tokens are turned away on anything that can change state. Pages and htmx requests from the
+/// browser are covered by the CSRF check instead.
+pub async fn check_scope(
+    BearerToken(scope): BearerToken,
+    WantsJson(json): WantsJson,
+    request: Request,
+    next: Next,
+) -> Response {
+    let safe = matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    );
+    match scope {
+        None if json => utils::json_error(
+            StatusCode::UNAUTHORIZED,
+            "Send a personal access token as Authorization: Bearer <token>",
+        ),
+        Some(TokenScope::Read) if !safe => {
+            utils::json_error(StatusCode::FORBIDDEN, "This token is read-only")
+        }
+        _ => next.run(request).await,
+    }
+}
@@ -0,0 +1,235 @@
+use axum::{
+    extract::{Path, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{delete, get},
+    Extension, Router,
+};
+
+use axum_extra::extract::Form;
+
+use serde::Deserialize;
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::config::TokensConfig;
+use crate::csrf::CsrfToken;
+use crate::utils;
+use crate::utils::{ConfirmDialogTemplate, HtmlTemplate};
+
+use super::{db, templates, TokenScope, TOKEN_LENGTH, TOKEN_PREFIX};
+
+// personal access tokens routes, nested under /tokens
+// The app has no accounts, so making a token takes the TOKENS_SECRET of the deployment. Without
+// one, tokens can still be listed and revoked but not made.
+pub fn routes(pool: &Pool<Postgres>, config: TokensConfig) -> Router {
+    Router::new()
+        .route("/", get(show).post(create))
+        .route("/:id", delete(destroy))
+        .route("/:id/confirm_delete", get(confirm_destroy))
+        .route_layer(middleware::from_fn(browser_only))
+        .layer(Extension(Minting {
+            secret: config.secret,
+        }))
+        .with_state(pool.clone())
+}
+
+#[derive(Clone)]
+struct Minting {
+    secret: Option<String>,
+}
+
+// Tokens are managed from this page, behind the CSRF check like the rest of the app. A token
+// can't be used to make more tokens, or to revoke them.
+async fn browser_only(request: Request, next: Next) -> Response {
+    if request.headers().contains_key(header::AUTHORIZATION) {
+        return (
+            StatusCode::FORBIDDEN,
+            "Personal access tokens are managed from /tokens in the browser",
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+#[derive(Deserialize)]
+struct CreateTokenParams {
+    name: String,
+    scope: TokenScope,
+    secret: String,
+}
+
+// get /tokens
+async fn show(
+    State(pool): State<PgPool>,
+    Extension(minting): Extension<Minting>,
+    CsrfToken(csrf_token): CsrfToken,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let tokens = db::get_tokens(&pool).await?;
+    Ok(HtmlTemplate(templates::TokensShowTemplate::new(
+        csrf_token,
+        tokens,
+        minting.secret.is_some(),
+    )))
+}
+
+// post /tokens
+async fn create(
+    State(pool): State<PgPool>,
+    Extension(minting): Extension<Minting>,
+    Form(params): Form<CreateTokenParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let Some(secret) = minting.secret else {
+        return Err((
+            StatusCode::FORBIDDEN,
+            String::from("Set TOKENS_SECRET to make tokens"),
+        ));
+    };
+    if !utils::tokens_match(&secret, &params.secret) {
+        return Err((StatusCode::FORBIDDEN, String::from("Wrong secret")));
+    }
+    let name = params.name.trim();
+    if name.is_empty() {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("Token names can't be blank"),
+        ));
+    }
+    let token = format!("{TOKEN_PREFIX}{}", utils::random_token(TOKEN_LENGTH));
+    db::create_token(name, params.scope, &token, &pool).await?;
+    let tokens = db::get_tokens(&pool).await?;
+    Ok(HtmlTemplate(templates::TokensListTemplate::new(
+        tokens,
+        Some(token),
+        true,
+    )))
+}
+
+// get /tokens/:id/confirm_delete
+async fn confirm_destroy(
+    State(pool): State<PgPool>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let token = db::get_token(id, &pool).await?;
+    Ok(HtmlTemplate(ConfirmDialogTemplate {
+        message: format!(
+            "Revoke {}? Anything using it will stop working.",
+            token.name
+        ),
+        confirm_label: String::from("Revoke token"),
+        method: "delete",
+        url: format!("/tokens/{id}"),
+        target: String::from("#tokens"),
+    }))
+}
+
+// delete /tokens/:id
+// Revokes the token, API requests using it get a 401 from now on
+async fn destroy(
+    State(pool): State<PgPool>,
+    Extension(minting): Extension<Minting>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    db::delete_token(id, &pool).await?;
+    let tokens = db::get_tokens(&pool).await?;
+    Ok(HtmlTemplate(templates::TokensListTemplate::new(
+        tokens,
+        None,
+        minting.secret.is_some(),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, routing::post};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::csrf;
+
+    fn app(pool: &PgPool) -> Router {
+        let config = TokensConfig {
+            secret: Some(String::from("s3cret")),
+        };
+        Router::new()
+            .nest("/tokens", routes(pool, config))
+            .route("/lists", post(|| async { StatusCode::CREATED }))
+            .layer(middleware::from_fn_with_state(pool.clone(), csrf::protect))
+    }
+
+    fn post_with_token(uri: &str, token: &str) -> Request {
+        Request::post(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from("name=another&scope=read_write&secret=s3cret"))
+            .unwrap()
+    }
+
+    // as the tokens page sends it, with the CSRF token
+    fn create_from_page(secret: &str) -> Request {
+        Request::post("/tokens")
+            .header(header::COOKIE, "csrf_token=abc")
+            .header("X-CSRF-Token", "abc")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(format!(
+                "name=script&scope=read_write&secret={secret}"
+            )))
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn only_known_tokens_get_past_the_csrf_check(pool: PgPool) {
+        db::create_token("script", TokenScope::ReadWrite, "todo_write", &pool)
+            .await
+            .unwrap();
+        db::create_token("dashboard", TokenScope::Read, "todo_read", &pool)
+            .await
+            .unwrap();
+
+        let made_up = app(&pool)
+            .oneshot(post_with_token("/lists", "todo_made_up"))
+            .await
+            .unwrap();
+        let read_only = app(&pool)
+            .oneshot(post_with_token("/lists", "todo_read"))
+            .await
+            .unwrap();
+        let read_write = app(&pool)
+            .oneshot(post_with_token("/lists", "todo_write"))
+            .await
+            .unwrap();
+
+        assert_eq!(made_up.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(read_only.status(), StatusCode::FORBIDDEN);
+        assert_eq!(read_write.status(), StatusCode::CREATED);
+    }
+
+    #[sqlx::test]
+    async fn tokens_cant_make_more_tokens(pool: PgPool) {
+        db::create_token("script", TokenScope::ReadWrite, "todo_write", &pool)
+            .await
+            .unwrap();
+
+        let response = app(&pool)
+            .oneshot(post_with_token("/tokens", "todo_write"))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(db::get_tokens(&pool).await.unwrap().len(), 1);
+    }
+
+    #[sqlx::test]
+    async fn making_a_token_takes_the_secret(pool: PgPool) {
+        let wrong = app(&pool).oneshot(create_from_page("guess")).await.unwrap();
+        assert_eq!(wrong.status(), StatusCode::FORBIDDEN);
+        assert!(db::get_tokens(&pool).await.unwrap().is_empty());
+
+        let right = app(&pool)
+            .oneshot(create_from_page("s3cret"))
+            .await
+            .unwrap();
+        assert_eq!(right.status(), StatusCode::OK);
+        assert_eq!(db::get_tokens(&pool).await.unwrap().len(), 1);
+    }
+}
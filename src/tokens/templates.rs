@@ -0,0 +1,77 @@
+use askama::Template;
+use time::OffsetDateTime;
+
+use crate::utils;
+
+use super::{AccessToken, TokenScope};
+
+#[derive(Template)]
+#[template(path = "base.html")]
+struct BaseTemplate {}
+
+// A token as shown on the page, with its times as "3 days ago"
+pub struct TokenRow {
+    id: i32,
+    name: String,
+    scope: TokenScope,
+    created_ago: String,
+    last_used_ago: Option<String>,
+}
+
+fn token_rows(tokens: Vec<AccessToken>) -> Vec<TokenRow> {
+    let now = OffsetDateTime::now_utc();
+    tokens
+        .into_iter()
+        .map(|token| TokenRow {
+            id: token.id,
+            name: token.name,
+            scope: token.scope,
+            created_ago: utils::time_ago(token.created_at, now),
+            last_used_ago: token.last_used_at.map(|at| utils::time_ago(at, now)),
+        })
+        .collect()
+}
+
+// The personal access tokens page
+#[derive(Template)]
+#[template(path = "tokens_show.html")]
+pub struct TokensShowTemplate<'a> {
+    _parent: &'a BaseTemplate,
+    csrf_token: String,
+    tokens: Vec<TokenRow>,
+    new_token: Option<String>,
+    // whether TOKENS_SECRET is set, the form to make a token is only shown then
+    minting: bool,
+}
+
+impl<'a> TokensShowTemplate<'a> {
+    pub fn new(csrf_token: String, tokens: Vec<AccessToken>, minting: bool) -> Self {
+        TokensShowTemplate {
+            _parent: &BaseTemplate {},
+            csrf_token,
+            tokens: token_rows(tokens),
+            new_token: None,
+            minting,
+        }
+    }
+}
+
+// The tokens and the form to make one, swapped in after a change. A token that was
+// just made is shown this once.
+#[derive(Template)]
+#[template(path = "tokens_list.html")]
+pub struct TokensListTemplate {
+    tokens: Vec<TokenRow>,
+    new_token: Option<String>,
+    minting: bool,
+}
+
+impl TokensListTemplate {
+    pub fn new(tokens: Vec<AccessToken>, new_token: Option<String>, minting: bool) -> Self {
+        TokensListTemplate {
+            tokens: token_rows(tokens),
+            new_token,
+            minting,
+        }
+    }
+}
@@ -1,8 +1,17 @@
-use askama::Template;
+use ammonia::clean;
+use askama::{DynTemplate, Template};
 use axum::{
-    http::StatusCode,
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, StatusCode},
     response::{Html, IntoResponse, Response},
+    Json,
 };
+use pulldown_cmark::{html, Parser};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Serialize;
+use time::OffsetDateTime;
+use utoipa::ToSchema;
 
 pub struct HtmlTemplate<T>(pub T);
 
@@ -22,6 +31,92 @@ where
     }
 }
 
+/// A modal asking the user to confirm a destructive request before it is sent.
+/// Rendered into `#modal`; answering it (or cancelling) closes it again, see myscript.js.
+#[derive(Template)]
+#[template(path = "confirm_dialog.html")]
+pub struct ConfirmDialogTemplate {
+    pub message: String,
+    pub confirm_label: String,
+    // the htmx verb and url of the request to send when confirmed
+    pub method: &'static str,
+    pub url: String,
+    pub target: String,
+}
+
+/// Several templates rendered one after the other into a single response, e.g. the swap
+/// target followed by `hx-swap-oob` fragments for other parts of the page.
+#[derive(Default)]
+pub struct HtmlFragments(Vec<Box<dyn DynTemplate + Send>>);
+
+impl HtmlFragments {
+    pub fn new() -> Self {
+        HtmlFragments::default()
+    }
+
+    pub fn with<T>(mut self, template: T) -> Self
+    where
+        T: Template + Send + 'static,
+    {
+        self.0.push(Box::new(template));
+        self
+    }
+}
+
+impl IntoResponse for HtmlFragments {
+    fn into_response(self) -> Response {
+        let mut html = String::new();
+        for template in self.0 {
+            match template.dyn_render() {
+                Ok(fragment) => html.push_str(&fragment),
+                Err(err) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Failed to render template. Error: {err}"),
+                    )
+                        .into_response()
+                }
+            }
+        }
+        Html(html).into_response()
+    }
+}
+
+/// Whether to answer with JSON rather than HTML: the request asks for it in its Accept header.
+/// Anything else, curl's `*/*` included, keeps getting HTML.
+pub struct WantsJson(pub bool);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for WantsJson
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let accept = parts
+            .headers
+            .get(header::ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .unwrap_or_default();
+        Ok(WantsJson(accept.contains("application/json")))
+    }
+}
+
+/// The body of an error for requests that want JSON, see json_error
+#[derive(Serialize, ToSchema)]
+pub struct JsonError {
+    error: String,
+}
+
+/// An error as `{"error": "..."}`, for requests that want JSON
+pub fn json_error(status: StatusCode, message: impl Into<String>) -> Response {
+    let error = JsonError {
+        error: message.into(),
+    };
+    (status, Json(error)).into_response()
+}
+
 /// Utility function for mapping any error into a `500 Internal Server Error`
 /// response.
 pub fn internal_error<E>(err: E) -> (StatusCode, String)
@@ -30,3 +125,50 @@ where
 {
     (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
 }
+
+/// Render user-supplied Markdown to HTML that is safe to embed in a page.
+pub fn render_markdown(markdown: &str) -> String {
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, Parser::new(markdown));
+    clean(&unsafe_html)
+}
+
+/// A random alphanumeric string, for tokens that mustn't be guessable.
+pub fn random_token(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+/// Compare secret tokens without short-circuiting, so the check doesn't leak how much
+/// of the token matched.
+pub fn tokens_match(expected: &str, submitted: &str) -> bool {
+    expected.len() == submitted.len()
+        && expected
+            .bytes()
+            .zip(submitted.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// How long before `now` something happened, e.g. "2 hours ago". Falls back to the date
+/// once it is more than a month old.
+pub fn time_ago(at: OffsetDateTime, now: OffsetDateTime) -> String {
+    let elapsed = now - at;
+    let plural = |n: i64, unit: &str| {
+        if n == 1 {
+            format!("1 {unit} ago")
+        } else {
+            format!("{n} {unit}s ago")
+        }
+    };
+    match elapsed.whole_minutes() {
+        minutes if minutes < 1 => String::from("just now"),
+        minutes if minutes < 60 => plural(minutes, "minute"),
+        _ if elapsed.whole_hours() < 24 => plural(elapsed.whole_hours(), "hour"),
+        _ if elapsed.whole_days() <= 30 => plural(elapsed.whole_days(), "day"),
+        _ => format!("on {}", at.date()),
+    }
+}